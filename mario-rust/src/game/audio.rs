@@ -1,33 +1,213 @@
 use macroquad::audio::{
-    load_sound, load_sound_from_bytes, play_sound, stop_sound, PlaySoundParams, Sound,
+    load_sound, load_sound_from_bytes, play_sound, set_sound_volume, stop_sound, PlaySoundParams,
+    Sound,
 };
 
+use super::{camera::FollowCamera, rng::Rng, theme::LevelTheme};
+
+/// Number of distinct rising stomp tones for combos; later steps reuse the last.
+const STOMP_COMBO_STEPS: usize = 6;
+/// Number of rising coin tones a quick run of pickups steps through; later
+/// pickups stay on the highest.
+const COIN_PITCH_STEPS: usize = 8;
+/// Seconds without a coin after which the pickup tone drops back to the base.
+const COIN_STREAK_RESET: f32 = 0.4;
+/// Seed whose music is the original hand-written loop rather than a
+/// generated one.
+const CLASSIC_MUSIC_SEED: u32 = 0x1234_5678;
+const MUSIC_STEPS: usize = 64;
+/// Natural minor, in semitones above the root.
+const MINOR_SCALE: [i32; 7] = [0, 2, 3, 5, 7, 8, 10];
+/// Root notes the generated loops are built on; `MusicParams::key`
+/// transposes from here. A4 for the melody and A2 for the bass, matching the
+/// classic loop.
+const MELODY_ROOT: i32 = 69;
+const BASS_ROOT: i32 = 45;
+
+/// Cutoff of the one-pole low-pass filter behind muffled music.
+const MUFFLE_CUTOFF_HZ: f32 = 560.0;
+
+/// Horizontal distance from the camera focus, in pixels, within which
+/// positioned sounds play at full volume; roughly half a screen.
+const HEARING_HALF_WIDTH: f32 = 480.0;
+/// Volume of positioned sounds a full screen width or more off-screen.
+const OFFSCREEN_VOLUME: f32 = 0.3;
+
+/// Format of every generated sound. Loaded WAV files keep their own.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AudioConfig {
+    pub sample_rate: u32,
+    /// Writes both channels, with the same signal in each for now.
+    pub stereo: bool,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            sample_rate: 44_100,
+            stereo: false,
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Wraps mono `samples` in a WAV file of this format.
+    fn encode_wav(&self, samples: &[i16]) -> Vec<u8> {
+        if self.stereo {
+            let frames: Vec<[i16; 2]> = samples.iter().map(|&s| [s, s]).collect();
+            wav_pcm_stereo_16(self.sample_rate, &frames)
+        } else {
+            wav_pcm_mono_16(self.sample_rate, samples)
+        }
+    }
+}
+
 pub struct Sfx {
     jump: Option<Sound>,
     coin: Option<Sound>,
+    coin_pitches: Vec<Sound>,
+    /// Coins picked up in the current quick run, and the time since the last.
+    coin_streak: usize,
+    coin_streak_timer: f32,
     stomp: Option<Sound>,
+    stomp_combo: Vec<Sound>,
     powerup: Option<Sound>,
     hurt: Option<Sound>,
     win: Option<Sound>,
-    music: Option<Sound>,
+    one_up: Option<Sound>,
+    brick: Option<Sound>,
+    bump: Option<Sound>,
+    checkpoint: Option<Sound>,
+    splash: Option<Sound>,
+    fireball: Option<Sound>,
+    star: Option<Sound>,
+    warp: Option<Sound>,
+    boing: Option<Sound>,
+    unlock: Option<Sound>,
+    /// One looping track per level theme.
+    music: Vec<(LevelTheme, MusicTrack)>,
+    /// Theme whose track plays outside star mode.
+    music_theme: LevelTheme,
+    star_music: Option<MusicTrack>,
+    /// Plays `star_music` in place of the theme track while a star is active.
+    star_mode: bool,
+    /// Plays the low-pass filtered version of the current track.
+    muffled: bool,
     music_playing: bool,
+    master_volume: f32,
     volume: f32,
     music_volume: f32,
+    muted: bool,
 }
 
 impl Sfx {
-    pub async fn new() -> Self {
+    /// `music_seed` varies the generated theme tracks; the star track always
+    /// uses the classic loop so it stays recognizable.
+    pub async fn new(music_seed: u32, audio: &AudioConfig) -> Self {
         Self {
-            jump: load_or_generate("sfx/jump.wav", default_jump_sound).await,
-            coin: load_or_generate("sfx/coin.wav", default_coin_sound).await,
-            stomp: load_or_generate("sfx/stomp.wav", default_stomp_sound).await,
-            powerup: load_or_generate("sfx/powerup.wav", default_powerup_sound).await,
-            hurt: load_or_generate("sfx/hurt.wav", default_hurt_sound).await,
-            win: load_or_generate("sfx/win.wav", default_win_sound).await,
-            music: load_or_generate("music.wav", default_music_sound).await,
+            jump: load_or_generate("sfx/jump.wav", audio, default_jump_sound).await,
+            coin: load_or_generate("sfx/coin.wav", audio, default_coin_sound).await,
+            coin_pitches: generate_coin_pitch_sounds(audio).await,
+            stomp: load_or_generate("sfx/stomp.wav", audio, default_stomp_sound).await,
+            stomp_combo: generate_stomp_combo_sounds(audio).await,
+            powerup: load_or_generate("sfx/powerup.wav", audio, default_powerup_sound).await,
+            hurt: load_or_generate("sfx/hurt.wav", audio, default_hurt_sound).await,
+            win: load_or_generate("sfx/win.wav", audio, default_win_sound).await,
+            one_up: load_or_generate("sfx/oneup.wav", audio, default_one_up_sound).await,
+            brick: load_or_generate("sfx/brick.wav", audio, default_brick_sound).await,
+            bump: load_or_generate("sfx/bump.wav", audio, default_bump_sound).await,
+            checkpoint: load_or_generate("sfx/checkpoint.wav", audio, default_checkpoint_sound)
+                .await,
+            splash: load_or_generate("sfx/splash.wav", audio, default_splash_sound).await,
+            fireball: load_or_generate("sfx/fireball.wav", audio, default_fireball_sound).await,
+            star: load_or_generate("sfx/star.wav", audio, default_star_sound).await,
+            warp: load_or_generate("sfx/warp.wav", audio, default_warp_sound).await,
+            boing: load_or_generate("sfx/boing.wav", audio, default_boing_sound).await,
+            unlock: load_or_generate("sfx/unlock.wav", audio, default_unlock_sound).await,
+            music: load_theme_music(music_seed, audio).await,
+            star_music: load_music("music_star.wav", audio, || {
+                default_star_music_sound(audio.sample_rate)
+            })
+            .await,
+            ..Self::silent()
+        }
+    }
+
+    /// A sound bank with nothing loaded; every `play_*` call is a no-op.
+    /// Used when running the simulation without an audio device.
+    pub fn silent() -> Self {
+        Self {
+            jump: None,
+            coin: None,
+            coin_pitches: Vec::new(),
+            coin_streak: 0,
+            coin_streak_timer: 0.0,
+            stomp: None,
+            stomp_combo: Vec::new(),
+            powerup: None,
+            hurt: None,
+            win: None,
+            one_up: None,
+            brick: None,
+            bump: None,
+            checkpoint: None,
+            splash: None,
+            fireball: None,
+            star: None,
+            warp: None,
+            boing: None,
+            unlock: None,
+            music: Vec::new(),
+            music_theme: LevelTheme::default(),
+            star_music: None,
+            muffled: false,
+            star_mode: false,
             music_playing: false,
+            master_volume: 1.0,
             volume: 0.45,
             music_volume: 0.22,
+            muted: false,
+        }
+    }
+
+    pub fn master_volume(&self) -> f32 {
+        self.master_volume
+    }
+
+    pub fn is_muted(&self) -> bool {
+        self.muted
+    }
+
+    pub fn set_master_volume(&mut self, volume: f32) {
+        self.master_volume = volume.clamp(0.0, 1.0);
+        self.apply_music_volume();
+    }
+
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+
+    pub fn set_music_volume(&mut self, volume: f32) {
+        self.music_volume = volume.clamp(0.0, 1.0);
+        self.apply_music_volume();
+    }
+
+    /// Silences everything without forgetting the stored volumes; music that
+    /// was playing resumes when unmuted.
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        if !self.music_playing {
+            return;
+        }
+
+        let Some(sound) = self.current_music() else {
+            return;
+        };
+
+        if self.muted {
+            stop_sound(sound);
+        } else {
+            self.play_music(sound);
         }
     }
 
@@ -35,12 +215,42 @@ impl Sfx {
         self.play(&self.jump);
     }
 
-    pub fn play_coin(&self) {
-        self.play(&self.coin);
+    /// Plays the coin sound, one step higher for each coin picked up within
+    /// `COIN_STREAK_RESET` seconds of the previous one.
+    pub fn play_coin(&mut self, world_x: f32, camera: &FollowCamera) {
+        let step = self.coin_streak;
+        self.coin_streak += 1;
+        self.coin_streak_timer = 0.0;
+        if step == 0 || self.coin_pitches.is_empty() {
+            self.play_at(&self.coin, world_x, camera);
+            return;
+        }
+        let idx = (step - 1).min(self.coin_pitches.len() - 1);
+        self.play_at(&self.coin_pitches.get(idx).cloned(), world_x, camera);
+    }
+
+    /// Advances timers that depend on game time rather than on sounds
+    /// playing, such as the coin pitch streak.
+    pub fn update(&mut self, dt: f32) {
+        self.coin_streak_timer += dt;
+        if self.coin_streak_timer >= COIN_STREAK_RESET {
+            self.coin_streak = 0;
+        }
     }
 
-    pub fn play_stomp(&self) {
-        self.play(&self.stomp);
+    pub fn play_stomp(&self, world_x: f32, camera: &FollowCamera) {
+        self.play_at(&self.stomp, world_x, camera);
+    }
+
+    /// Plays the stomp sound for the `step`-th stomp of a combo, rising in
+    /// pitch with each step after the first.
+    pub fn play_stomp_combo(&self, step: u32, world_x: f32, camera: &FollowCamera) {
+        if step <= 1 || self.stomp_combo.is_empty() {
+            self.play_stomp(world_x, camera);
+            return;
+        }
+        let idx = (step as usize - 2).min(self.stomp_combo.len() - 1);
+        self.play_at(&self.stomp_combo.get(idx).cloned(), world_x, camera);
     }
 
     pub fn play_powerup(&self) {
@@ -55,22 +265,106 @@ impl Sfx {
         self.play(&self.win);
     }
 
-    pub fn start_music(&mut self) {
-        if self.music_playing {
+    pub fn play_one_up(&self) {
+        self.play(&self.one_up);
+    }
+
+    pub fn play_brick(&self) {
+        self.play(&self.brick);
+    }
+
+    pub fn play_bump(&self) {
+        self.play(&self.bump);
+    }
+
+    pub fn play_checkpoint(&self) {
+        self.play(&self.checkpoint);
+    }
+
+    pub fn play_splash(&self) {
+        self.play(&self.splash);
+    }
+
+    pub fn play_fireball(&self) {
+        self.play(&self.fireball);
+    }
+
+    pub fn play_star(&self) {
+        self.play(&self.star);
+    }
+
+    pub fn play_warp(&self) {
+        self.play(&self.warp);
+    }
+
+    pub fn play_unlock(&self) {
+        self.play(&self.unlock);
+    }
+
+    pub fn play_boing(&self, world_x: f32, camera: &FollowCamera) {
+        self.play_at(&self.boing, world_x, camera);
+    }
+
+    /// Switches between the normal and the faster star track, picking up the
+    /// new one right away if music is playing.
+    pub fn set_star_music(&mut self, on: bool) {
+        if self.star_mode != on {
+            self.switch_music(|sfx| sfx.star_mode = on);
+        }
+    }
+
+    /// Swaps to the low-pass filtered version of the current track, e.g.
+    /// underwater. Macroquad can't seek, so the track restarts from the top.
+    /// Tracks loaded from files have no filtered version and keep playing.
+    pub fn start_music_muffled(&mut self) {
+        if !self.muffled {
+            self.switch_music(|sfx| sfx.muffled = true);
+        }
+    }
+
+    /// Undoes `start_music_muffled`.
+    pub fn start_music_normal(&mut self) {
+        if self.muffled {
+            self.switch_music(|sfx| sfx.muffled = false);
+        }
+    }
+
+    /// Applies `change` to the music selection, restarting playback only if
+    /// it picked a different sound.
+    fn switch_music(&mut self, change: impl FnOnce(&mut Self)) {
+        let before = self.current_music().cloned();
+        let before_ptr = self.current_music().map(|sound| sound as *const Sound);
+        change(self);
+        let after = self.current_music();
+        let unchanged = after.map(|sound| sound as *const Sound) == before_ptr;
+        if !self.music_playing || self.muted || unchanged {
             return;
         }
 
-        let Some(sound) = &self.music else {
+        if let Some(sound) = &before {
+            stop_sound(sound);
+        }
+        if let Some(sound) = after {
+            self.play_music(sound);
+        }
+    }
+
+    /// Starts the track for `theme`, stopping whatever track is playing
+    /// first so two never overlap.
+    pub fn start_music_for(&mut self, theme: LevelTheme) {
+        if self.music_playing && self.music_theme == theme {
+            return;
+        }
+        self.stop_music();
+        self.music_theme = theme;
+
+        let Some(sound) = self.current_music() else {
             return;
         };
 
-        play_sound(
-            sound,
-            PlaySoundParams {
-                looped: true,
-                volume: self.music_volume,
-            },
-        );
+        if !self.muted {
+            self.play_music(sound);
+        }
         self.music_playing = true;
     }
 
@@ -79,16 +373,65 @@ impl Sfx {
             return;
         }
 
-        let Some(sound) = &self.music else {
+        let Some(sound) = self.current_music() else {
             self.music_playing = false;
             return;
         };
 
-        stop_sound(sound);
+        if !self.muted {
+            stop_sound(sound);
+        }
         self.music_playing = false;
     }
 
+    fn current_music(&self) -> Option<&Sound> {
+        if self.star_mode {
+            self.star_music.as_ref()
+        } else {
+            self.music
+                .iter()
+                .find(|(theme, _)| *theme == self.music_theme)
+                .map(|(_, track)| track)
+        }
+        .map(|track| track.sound(self.muffled))
+    }
+
+    fn play_music(&self, sound: &Sound) {
+        play_sound(
+            sound,
+            PlaySoundParams {
+                looped: true,
+                volume: self.music_volume * self.master_volume,
+            },
+        );
+    }
+
+    fn apply_music_volume(&self) {
+        if !self.music_playing || self.muted {
+            return;
+        }
+
+        if let Some(sound) = self.current_music() {
+            set_sound_volume(sound, self.music_volume * self.master_volume);
+        }
+    }
+
     fn play(&self, sound: &Option<Sound>) {
+        self.play_scaled(sound, 1.0);
+    }
+
+    /// Plays a sound coming from `world_x`, quieter the further it is from
+    /// the camera. Macroquad only takes a single volume per sound, so there
+    /// is no real left/right panning.
+    fn play_at(&self, sound: &Option<Sound>, world_x: f32, camera: &FollowCamera) {
+        self.play_scaled(sound, spatial_volume(world_x - camera.focus().x));
+    }
+
+    fn play_scaled(&self, sound: &Option<Sound>, scale: f32) {
+        if self.muted {
+            return;
+        }
+
         let Some(sound) = sound else {
             return;
         };
@@ -97,51 +440,195 @@ impl Sfx {
             sound,
             PlaySoundParams {
                 looped: false,
-                volume: self.volume,
+                volume: self.volume * self.master_volume * scale,
             },
         );
     }
 }
 
-async fn load_or_generate(path: &str, generator: fn() -> Vec<u8>) -> Option<Sound> {
+/// Volume scale for a sound `offset_x` pixels from the camera focus: full
+/// on screen, fading to `OFFSCREEN_VOLUME` a further screen width away.
+fn spatial_volume(offset_x: f32) -> f32 {
+    let beyond = (offset_x.abs() - HEARING_HALF_WIDTH).max(0.0);
+    let t = (beyond / (HEARING_HALF_WIDTH * 2.0)).min(1.0);
+    1.0 + (OFFSCREEN_VOLUME - 1.0) * t
+}
+
+/// A looping music track and, for generated ones, a muffled copy.
+struct MusicTrack {
+    normal: Sound,
+    muffled: Option<Sound>,
+}
+
+impl MusicTrack {
+    fn sound(&self, muffled: bool) -> &Sound {
+        match &self.muffled {
+            Some(sound) if muffled => sound,
+            _ => &self.normal,
+        }
+    }
+}
+
+/// Loads the track at `path`, or generates it along with a low-pass filtered
+/// copy if the file is missing.
+async fn load_music(
+    path: &str,
+    audio: &AudioConfig,
+    generator: impl FnOnce() -> Vec<i16>,
+) -> Option<MusicTrack> {
+    if let Ok(normal) = load_sound(path).await {
+        return Some(MusicTrack {
+            normal,
+            muffled: None,
+        });
+    }
+    let samples = generator();
+    let normal = load_sound_from_bytes(&audio.encode_wav(&samples))
+        .await
+        .ok()?;
+    let muffled = low_pass(&samples, audio.sample_rate);
+    let muffled = load_sound_from_bytes(&audio.encode_wav(&muffled))
+        .await
+        .ok();
+    Some(MusicTrack { normal, muffled })
+}
+
+async fn load_or_generate(
+    path: &str,
+    audio: &AudioConfig,
+    generator: fn(&AudioConfig) -> Vec<u8>,
+) -> Option<Sound> {
     match load_sound(path).await {
         Ok(sound) => Some(sound),
-        Err(_) => load_sound_from_bytes(&generator()).await.ok(),
+        Err(_) => load_sound_from_bytes(&generator(audio)).await.ok(),
     }
 }
 
-fn default_jump_sound() -> Vec<u8> {
-    synth_sine_wav(720.0, 0.12, 0.25)
+/// Loads `music/<theme>.wav` for every theme, generating the ones that are
+/// missing from `seed`.
+async fn load_theme_music(seed: u32, audio: &AudioConfig) -> Vec<(LevelTheme, MusicTrack)> {
+    let mut tracks = Vec::with_capacity(LevelTheme::ALL.len());
+    for (idx, theme) in LevelTheme::ALL.into_iter().enumerate() {
+        let path = format!("music/{}.wav", theme.name());
+        let seed = seed.wrapping_add(idx as u32 * 0x9E37_79B9);
+        let generate = || default_music_sound(theme, seed, audio.sample_rate);
+        if let Some(track) = load_music(&path, audio, generate).await {
+            tracks.push((theme, track));
+        }
+    }
+    tracks
 }
 
-fn default_coin_sound() -> Vec<u8> {
-    synth_sine_wav(980.0, 0.08, 0.28)
+async fn generate_stomp_combo_sounds(audio: &AudioConfig) -> Vec<Sound> {
+    let mut sounds = Vec::with_capacity(STOMP_COMBO_STEPS);
+    for step in 1..=STOMP_COMBO_STEPS {
+        // Two semitones per step above the base stomp tone.
+        let freq = 220.0 * 2f32.powf(step as f32 * 2.0 / 12.0);
+        if let Ok(sound) = load_sound_from_bytes(&synth_sine_wav(audio, freq, 0.10, 0.35)).await {
+            sounds.push(sound);
+        }
+    }
+    sounds
 }
 
-fn default_stomp_sound() -> Vec<u8> {
-    synth_sine_wav(220.0, 0.10, 0.35)
+async fn generate_coin_pitch_sounds(audio: &AudioConfig) -> Vec<Sound> {
+    let mut sounds = Vec::with_capacity(COIN_PITCH_STEPS);
+    for step in 1..=COIN_PITCH_STEPS {
+        // One semitone per step above the base coin tone.
+        let freq = 980.0 * 2f32.powf(step as f32 / 12.0);
+        if let Ok(sound) = load_sound_from_bytes(&synth_sine_wav(audio, freq, 0.08, 0.28)).await {
+            sounds.push(sound);
+        }
+    }
+    sounds
 }
 
-fn default_powerup_sound() -> Vec<u8> {
-    synth_sine_wav(540.0, 0.18, 0.28)
+fn default_jump_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 720.0, 0.12, 0.25)
 }
 
-fn default_hurt_sound() -> Vec<u8> {
-    synth_sine_wav(160.0, 0.16, 0.32)
+fn default_coin_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 980.0, 0.08, 0.28)
 }
 
-fn default_win_sound() -> Vec<u8> {
-    synth_sine_wav(660.0, 0.22, 0.24)
+fn default_stomp_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 220.0, 0.10, 0.35)
 }
 
-fn default_music_sound() -> Vec<u8> {
-    synth_chiptune_wav()
+fn default_powerup_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 540.0, 0.18, 0.28)
 }
 
-fn synth_sine_wav(freq_hz: f32, duration_s: f32, amplitude: f32) -> Vec<u8> {
-    let sample_rate = 44_100u32;
-    let samples = synth_sine_mono_16(sample_rate, freq_hz, duration_s, amplitude);
-    wav_pcm_mono_16(sample_rate, &samples)
+fn default_hurt_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 160.0, 0.16, 0.32)
+}
+
+fn default_win_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 660.0, 0.22, 0.24)
+}
+
+fn default_one_up_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 1320.0, 0.28, 0.26)
+}
+
+fn default_brick_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 120.0, 0.14, 0.36)
+}
+
+fn default_bump_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 180.0, 0.06, 0.3)
+}
+
+fn default_checkpoint_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 880.0, 0.2, 0.26)
+}
+
+fn default_splash_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 300.0, 0.12, 0.22)
+}
+
+fn default_fireball_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 420.0, 0.07, 0.24)
+}
+
+fn default_star_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 1180.0, 0.3, 0.26)
+}
+
+fn default_warp_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 110.0, 0.45, 0.3)
+}
+
+fn default_boing_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 260.0, 0.25, 0.3)
+}
+
+fn default_unlock_sound(audio: &AudioConfig) -> Vec<u8> {
+    synth_sine_wav(audio, 990.0, 0.35, 0.28)
+}
+
+/// A generated loop in each theme's own key and tempo.
+fn default_music_sound(theme: LevelTheme, seed: u32, sample_rate: u32) -> Vec<i16> {
+    let (key, bpm) = match theme {
+        LevelTheme::Overworld => (0, 140.0),
+        LevelTheme::Cave => (-7, 100.0),
+        LevelTheme::Night => (-3, 115.0),
+        LevelTheme::Snow => (5, 125.0),
+    };
+    synth_chiptune(&MusicParams { seed, key, bpm }, sample_rate)
+}
+
+fn default_star_music_sound(sample_rate: u32) -> Vec<i16> {
+    let params = MusicParams {
+        bpm: 210.0,
+        ..MusicParams::default()
+    };
+    synth_chiptune(&params, sample_rate)
+}
+
+fn synth_sine_wav(audio: &AudioConfig, freq_hz: f32, duration_s: f32, amplitude: f32) -> Vec<u8> {
+    let samples = synth_sine_mono_16(audio.sample_rate, freq_hz, duration_s, amplitude);
+    audio.encode_wav(&samples)
 }
 
 fn synth_sine_mono_16(sample_rate: u32, freq_hz: f32, duration_s: f32, amplitude: f32) -> Vec<i16> {
@@ -169,32 +656,135 @@ fn synth_sine_mono_16(sample_rate: u32, freq_hz: f32, duration_s: f32, amplitude
     out
 }
 
-fn synth_chiptune_wav() -> Vec<u8> {
-    let sample_rate = 44_100u32;
-    let bpm = 140.0;
-    let step_s = 60.0 / bpm / 4.0;
-    let steps = 64usize;
+/// Inputs to the procedural music generator.
+#[derive(Clone, Copy, Debug)]
+struct MusicParams {
+    /// Picks the melody, bass line and drum pattern. `CLASSIC_MUSIC_SEED`
+    /// gives the original hand-written loop.
+    seed: u32,
+    /// Semitones above A minor.
+    key: i32,
+    bpm: f32,
+}
+
+impl Default for MusicParams {
+    fn default() -> Self {
+        Self {
+            seed: CLASSIC_MUSIC_SEED,
+            key: 0,
+            bpm: 140.0,
+        }
+    }
+}
+
+/// One loop's notes as MIDI numbers in A minor (0 = rest) and drum hits
+/// (1 = kick, 2 = noise snare), one entry per sixteenth-note step.
+struct MusicPattern {
+    melody: [i32; MUSIC_STEPS],
+    bass: [i32; MUSIC_STEPS],
+    drum: [u8; MUSIC_STEPS],
+}
+
+impl MusicPattern {
+    fn classic() -> Self {
+        Self {
+            melody: [
+                69, 0, 72, 0, 76, 0, 72, 0, 69, 0, 67, 0, 64, 0, 67, 0, 72, 0, 76, 0, 79, 0, 76, 0,
+                72, 0, 71, 0, 67, 0, 69, 0, 76, 0, 79, 0, 83, 0, 79, 0, 76, 0, 74, 0, 71, 0, 74, 0,
+                72, 0, 76, 0, 79, 0, 76, 0, 72, 0, 71, 0, 67, 0, 69, 0,
+            ],
+            bass: [
+                45, 0, 45, 0, 48, 0, 45, 0, 43, 0, 43, 0, 40, 0, 43, 0, 45, 0, 45, 0, 48, 0, 45, 0,
+                43, 0, 43, 0, 40, 0, 43, 0, 48, 0, 48, 0, 52, 0, 48, 0, 47, 0, 47, 0, 43, 0, 47, 0,
+                45, 0, 45, 0, 48, 0, 45, 0, 43, 0, 43, 0, 40, 0, 43, 0,
+            ],
+            drum: [
+                1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 2, 0, 0, 1, 0, 1, 0, 0, 0, 2, 0, 1, 0, 1, 0, 2, 0,
+                0, 0, 1, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 2, 0, 0, 1, 0, 1, 0, 0, 0, 2, 0, 1, 0,
+                1, 0, 2, 0, 0, 0, 1, 0,
+            ],
+        }
+    }
+
+    /// A four-bar loop built from `seed`. Each bar gets a chord from the
+    /// minor scale; the bass walks its root and fifth, and the melody leans
+    /// on chord tones on the beat and steps through the scale between them,
+    /// so every note stays in key.
+    fn generate(seed: u32) -> Self {
+        if seed == CLASSIC_MUSIC_SEED {
+            return Self::classic();
+        }
+
+        let mut rng = Rng::new(seed);
+        let mut pick = |count: usize| (rng.next_u32() as usize) % count;
+        let mut pattern = Self {
+            melody: [0; MUSIC_STEPS],
+            bass: [0; MUSIC_STEPS],
+            drum: [0; MUSIC_STEPS],
+        };
+
+        // Scale degrees of each bar's chord: start home, wander, and end on
+        // a chord that leads back.
+        const WANDER: [i32; 5] = [5, 2, 6, 3, 4];
+        const TURNAROUND: [i32; 2] = [4, 6];
+        let chords = [0, WANDER[pick(5)], WANDER[pick(5)], TURNAROUND[pick(2)]];
+
+        let mut degree = 7;
+        for (bar, &chord) in chords.iter().enumerate() {
+            for beat_step in (0..16).step_by(2) {
+                let step = bar * 16 + beat_step;
+
+                let bass_degree = if beat_step % 8 == 4 { chord + 4 } else { chord };
+                pattern.bass[step] = scale_note(BASS_ROOT, bass_degree);
+
+                if beat_step % 4 == 0 {
+                    // On the beat: the chord tone nearest the last note.
+                    let tones = [chord, chord + 2, chord + 4, chord + 7, chord + 9];
+                    degree = *tones
+                        .iter()
+                        .min_by_key(|tone| (**tone - degree).abs() + pick(3) as i32)
+                        .unwrap_or(&chord);
+                } else if pick(10) < 3 {
+                    continue;
+                } else {
+                    degree += [-1, 1][pick(2)];
+                }
+                degree = degree.clamp(0, 11);
+                pattern.melody[step] = scale_note(MELODY_ROOT, degree);
+            }
+
+            // Kick and snare on the main beats, with a few random extra hits.
+            for beat_step in 0..16 {
+                let step = bar * 16 + beat_step;
+                pattern.drum[step] = match beat_step {
+                    0 | 8 => 1,
+                    4 | 12 => 2,
+                    _ if beat_step % 2 == 0 && pick(6) == 0 => 1,
+                    _ if pick(10) == 0 => 2,
+                    _ => 0,
+                };
+            }
+        }
+        pattern
+    }
+}
+
+/// MIDI note `degree` steps up the minor scale from `root`.
+fn scale_note(root: i32, degree: i32) -> i32 {
+    let octave = degree.div_euclid(MINOR_SCALE.len() as i32);
+    let idx = degree.rem_euclid(MINOR_SCALE.len() as i32) as usize;
+    root + octave * 12 + MINOR_SCALE[idx]
+}
+
+fn synth_chiptune(params: &MusicParams, sample_rate: u32) -> Vec<i16> {
+    let step_s = 60.0 / params.bpm / 4.0;
+    let steps = MUSIC_STEPS;
     let duration_s = step_s * steps as f32;
     let total_samples = (duration_s * sample_rate as f32).round() as usize;
 
-    let melody: [i32; 64] = [
-        69, 0, 72, 0, 76, 0, 72, 0, 69, 0, 67, 0, 64, 0, 67, 0, 72, 0, 76, 0, 79, 0, 76, 0, 72, 0,
-        71, 0, 67, 0, 69, 0, 76, 0, 79, 0, 83, 0, 79, 0, 76, 0, 74, 0, 71, 0, 74, 0, 72, 0, 76, 0,
-        79, 0, 76, 0, 72, 0, 71, 0, 67, 0, 69, 0,
-    ];
-    let bass: [i32; 64] = [
-        45, 0, 45, 0, 48, 0, 45, 0, 43, 0, 43, 0, 40, 0, 43, 0, 45, 0, 45, 0, 48, 0, 45, 0, 43, 0,
-        43, 0, 40, 0, 43, 0, 48, 0, 48, 0, 52, 0, 48, 0, 47, 0, 47, 0, 43, 0, 47, 0, 45, 0, 45, 0,
-        48, 0, 45, 0, 43, 0, 43, 0, 40, 0, 43, 0,
-    ];
-
-    let drum: [u8; 64] = [
-        1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 2, 0, 0, 1, 0, 1, 0, 0, 0, 2, 0, 1, 0, 1, 0, 2, 0, 0, 0,
-        1, 0, 1, 0, 0, 0, 2, 0, 0, 0, 1, 0, 0, 2, 0, 0, 1, 0, 1, 0, 0, 0, 2, 0, 1, 0, 1, 0, 2, 0,
-        0, 0, 1, 0,
-    ];
-
-    let mut rng = 0x1234_5678u32;
+    let MusicPattern { melody, bass, drum } = MusicPattern::generate(params.seed);
+
+    let mut rng = Rng::new(params.seed);
     let sample_rate_f = sample_rate as f32;
     let mut out = Vec::with_capacity(total_samples);
 
@@ -224,13 +814,13 @@ fn synth_chiptune_wav() -> Vec<u8> {
 
         let mel = melody[step];
         if mel != 0 {
-            let f = midi_to_freq(mel);
+            let f = midi_to_freq(mel + params.key);
             sample += square_wave(t, f) * 0.18 * note_env;
         }
 
         let b = bass[step];
         if b != 0 {
-            let f = midi_to_freq(b);
+            let f = midi_to_freq(b + params.key);
             sample += square_wave(t, f) * 0.16 * note_env;
         }
 
@@ -243,7 +833,7 @@ fn synth_chiptune_wav() -> Vec<u8> {
             }
             2 => {
                 let env = (1.0 - step_pos).powf(2.5);
-                sample += noise(&mut rng) * 0.16 * env;
+                sample += rng.range(-1.0, 1.0) * 0.16 * env;
             }
             _ => {}
         }
@@ -253,7 +843,20 @@ fn synth_chiptune_wav() -> Vec<u8> {
         out.push((sample * i16::MAX as f32) as i16);
     }
 
-    wav_pcm_mono_16(sample_rate, &out)
+    out
+}
+
+/// One-pole low-pass filter at `MUFFLE_CUTOFF_HZ`, used to muffle music.
+fn low_pass(samples: &[i16], sample_rate: u32) -> Vec<i16> {
+    let alpha = 1.0 - (-std::f32::consts::TAU * MUFFLE_CUTOFF_HZ / sample_rate as f32).exp();
+    let mut level = 0.0;
+    samples
+        .iter()
+        .map(|&sample| {
+            level += (sample as f32 - level) * alpha;
+            level as i16
+        })
+        .collect()
 }
 
 fn midi_to_freq(midi_note: i32) -> f32 {
@@ -268,19 +871,21 @@ fn square_wave(t: f32, freq_hz: f32) -> f32 {
     }
 }
 
-fn noise(rng: &mut u32) -> f32 {
-    *rng ^= *rng << 13;
-    *rng ^= *rng >> 17;
-    *rng ^= *rng << 5;
-    (*rng as f32 / u32::MAX as f32) * 2.0 - 1.0
+fn wav_pcm_mono_16(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
+    wav_pcm_16(sample_rate, 1, samples)
 }
 
-fn wav_pcm_mono_16(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
-    let num_channels: u16 = 1;
+/// Like `wav_pcm_mono_16`, with one `[left, right]` pair per frame.
+fn wav_pcm_stereo_16(sample_rate: u32, frames: &[[i16; 2]]) -> Vec<u8> {
+    wav_pcm_16(sample_rate, 2, frames.as_flattened())
+}
+
+/// 16-bit PCM WAV file from already interleaved samples.
+fn wav_pcm_16(sample_rate: u32, num_channels: u16, samples: &[i16]) -> Vec<u8> {
     let bits_per_sample: u16 = 16;
     let block_align = num_channels * (bits_per_sample / 8);
     let byte_rate = sample_rate * block_align as u32;
-    let data_size = (samples.len() as u32) * block_align as u32;
+    let data_size = (samples.len() as u32) * (bits_per_sample / 8) as u32;
     let chunk_size = 36 + data_size;
 
     let mut out = Vec::with_capacity((44 + data_size) as usize);
@@ -307,3 +912,90 @@ fn wav_pcm_mono_16(sample_rate: u32, samples: &[i16]) -> Vec<u8> {
 
     out
 }
+
+#[cfg(test)]
+mod tests {
+    use macroquad::math::Vec2;
+
+    use super::*;
+
+    #[test]
+    fn coin_streak_resets_after_a_pause() {
+        let mut sfx = Sfx::silent();
+        let camera = FollowCamera::new(Vec2::ZERO);
+        sfx.play_coin(0.0, &camera);
+        sfx.update(0.1);
+        sfx.play_coin(0.0, &camera);
+        sfx.play_coin(0.0, &camera);
+        assert_eq!(sfx.coin_streak, 3);
+
+        sfx.update(COIN_STREAK_RESET);
+        assert_eq!(sfx.coin_streak, 0);
+    }
+
+    #[test]
+    fn generated_music_stays_in_key_and_depends_on_the_seed() {
+        let in_scale =
+            |note: i32| note == 0 || MINOR_SCALE.contains(&(note - MELODY_ROOT).rem_euclid(12));
+        let first = MusicPattern::generate(7);
+        assert!(first
+            .melody
+            .iter()
+            .chain(&first.bass)
+            .all(|note| in_scale(*note)));
+        assert!(first.melody.iter().any(|note| *note != 0));
+
+        assert_eq!(MusicPattern::generate(7).melody, first.melody);
+        assert_ne!(MusicPattern::generate(8).melody, first.melody);
+        assert_eq!(
+            MusicPattern::generate(CLASSIC_MUSIC_SEED).melody,
+            MusicPattern::classic().melody
+        );
+    }
+
+    #[test]
+    fn low_pass_keeps_slow_changes_and_damps_fast_ones() {
+        let steady = low_pass(&[10_000; 200], 44_100);
+        assert!(steady[199] > 9_900);
+
+        let buzz: Vec<i16> = (0..200)
+            .map(|i| if i % 2 == 0 { 10_000 } else { -10_000 })
+            .collect();
+        assert!(low_pass(&buzz, 44_100)[100..]
+            .iter()
+            .all(|s| s.abs() < 1_000));
+    }
+
+    #[test]
+    fn wav_headers_match_the_channel_count() {
+        let field_u16 = |wav: &[u8], at: usize| u16::from_le_bytes([wav[at], wav[at + 1]]);
+        let field_u32 = |wav: &[u8], at: usize| {
+            u32::from_le_bytes([wav[at], wav[at + 1], wav[at + 2], wav[at + 3]])
+        };
+
+        let mono = wav_pcm_mono_16(22_050, &[0; 10]);
+        assert_eq!(field_u16(&mono, 22), 1);
+        assert_eq!(field_u32(&mono, 24), 22_050);
+        assert_eq!(field_u32(&mono, 28), 44_100);
+        assert_eq!(field_u16(&mono, 32), 2);
+        assert_eq!(field_u32(&mono, 40), 20);
+        assert_eq!(mono.len(), 44 + 20);
+
+        let stereo = wav_pcm_stereo_16(48_000, &[[0, 0]; 10]);
+        assert_eq!(field_u16(&stereo, 22), 2);
+        assert_eq!(field_u32(&stereo, 24), 48_000);
+        assert_eq!(field_u32(&stereo, 28), 192_000);
+        assert_eq!(field_u16(&stereo, 32), 4);
+        assert_eq!(field_u32(&stereo, 40), 40);
+        assert_eq!(stereo.len(), 44 + 40);
+    }
+
+    #[test]
+    fn offscreen_sounds_are_quieter() {
+        assert_eq!(spatial_volume(0.0), 1.0);
+        assert_eq!(spatial_volume(-HEARING_HALF_WIDTH), 1.0);
+        let near = spatial_volume(HEARING_HALF_WIDTH * 1.5);
+        assert!(near < 1.0 && near > OFFSCREEN_VOLUME);
+        assert_eq!(spatial_volume(HEARING_HALF_WIDTH * 10.0), OFFSCREEN_VOLUME);
+    }
+}
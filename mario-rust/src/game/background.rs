@@ -2,22 +2,150 @@ use macroquad::prelude::*;
 
 use super::{world::World, Config};
 
-pub fn draw(camera: &Camera2D, world: &World, config: &Config) {
-    let screen_w = screen_width();
-    let cam_left = camera.target.x - screen_w * 0.5;
+const FAR_PARALLAX: f32 = 0.25;
+const NEAR_PARALLAX: f32 = 0.55;
+const CLOUD_PARALLAX: f32 = 0.15;
+/// Above 1, so the foreground slides past faster than the level and reads
+/// as closer to the viewer.
+const FOREGROUND_PARALLAX: f32 = 1.3;
+/// Spacing of foreground tuft slots; some slots are left empty.
+const FOREGROUND_SPACING: f32 = 90.0;
+
+struct Layer {
+    texture: Texture2D,
+    parallax: f32,
+}
+
+/// Optional art that replaces the procedural hills. `bg_far.png` stands in for
+/// the far hills and clouds, `bg_near.png` for the near hills.
+#[derive(Default)]
+pub struct BackgroundLayers {
+    far: Option<Layer>,
+    near: Option<Layer>,
+}
+
+impl BackgroundLayers {
+    pub async fn load() -> Self {
+        Self {
+            far: load_layer("bg_far.png", FAR_PARALLAX).await,
+            near: load_layer("bg_near.png", NEAR_PARALLAX).await,
+        }
+    }
+}
+
+async fn load_layer(path: &str, parallax: f32) -> Option<Layer> {
+    let texture = load_texture(path).await.ok()?;
+    texture.set_filter(FilterMode::Nearest);
+    Some(Layer { texture, parallax })
+}
+
+pub fn draw(camera: &Camera2D, world: &World, layers: &BackgroundLayers, config: &Config) {
+    let (cam_left, screen_w) = visible_span(camera);
     let world_w = world.width as f32 * config.tile_size;
     let world_h = world.height as f32 * config.tile_size;
+    let horizon_y = world_h - config.tile_size * 1.25;
+    let theme = world.meta.theme.palette();
 
-    draw_hills(cam_left, world_w, world_h, config);
-    draw_clouds(cam_left, world_w);
+    match &layers.far {
+        Some(layer) => draw_tiled_layer(layer, cam_left, screen_w, world_w, world_h),
+        None => draw_far_hills(cam_left, world_w, horizon_y, theme.far_hills),
+    }
+    match &layers.near {
+        Some(layer) => draw_tiled_layer(layer, cam_left, screen_w, world_w, world_h),
+        None => draw_near_hills(cam_left, world_w, horizon_y, theme.near_hills),
+    }
+    if let (None, Some(color)) = (&layers.far, theme.clouds) {
+        draw_clouds(cam_left, world_w, color);
+    }
 }
 
-fn draw_hills(cam_left: f32, world_w: f32, world_h: f32, config: &Config) {
-    let horizon_y = world_h - config.tile_size * 1.25;
+/// Grass tufts along the bottom edge of the view, drawn after the player and
+/// enemies. Themes without a `foreground` color skip it.
+pub fn draw_foreground(camera: &Camera2D, world: &World) {
+    let Some(color) = world.meta.theme.palette().foreground else {
+        return;
+    };
+    let (cam_left, view_w) = visible_span(camera);
+    let bottom = camera.target.y + 1.0 / camera.zoom.y;
+    let offset = foreground_offset(cam_left);
+
+    let first = ((cam_left - offset) / FOREGROUND_SPACING).floor() as i32 - 1;
+    let last = ((cam_left + view_w - offset) / FOREGROUND_SPACING).ceil() as i32 + 1;
+    for slot in first..=last {
+        // Cheap per-slot hash so the pattern looks random but never changes.
+        let hash = (slot as u32).wrapping_mul(2_654_435_761) >> 16;
+        if hash.is_multiple_of(3) {
+            continue;
+        }
+        let x = slot as f32 * FOREGROUND_SPACING + (hash % 40) as f32 + offset;
+        let height = 14.0 + (hash % 12) as f32;
+        draw_tuft(vec2(x, bottom), height, color);
+    }
+}
 
-    let far_parallax = 0.25;
-    let far_offset = cam_left * (1.0 - far_parallax);
-    let far_color = Color::new(0.35, 0.68, 0.84, 1.0);
+/// World-space shift of the foreground slots for a view starting at
+/// `cam_left`.
+fn foreground_offset(cam_left: f32) -> f32 {
+    cam_left * (1.0 - FOREGROUND_PARALLAX)
+}
+
+/// Three grass blades fanning out from `base`.
+fn draw_tuft(base: Vec2, height: f32, color: Color) {
+    for (lean, scale) in [(-7.0, 0.75), (0.0, 1.0), (6.0, 0.85)] {
+        draw_triangle(
+            base + vec2(-3.0, 0.0),
+            base + vec2(3.0, 0.0),
+            base + vec2(lean, -height * scale),
+            color,
+        );
+    }
+}
+
+/// Left edge and width of the world `camera` shows, in world units. Derived
+/// from the zoom rather than the window so parallax lines up with the tiles
+/// on any window shape.
+fn visible_span(camera: &Camera2D) -> (f32, f32) {
+    let width = 2.0 / camera.zoom.x;
+    (camera.target.x - width * 0.5, width)
+}
+
+/// Repeats `layer` horizontally across the visible part of the world, bottom
+/// aligned to the world floor. Edge tiles are cropped at 0 and `world_w`.
+fn draw_tiled_layer(layer: &Layer, cam_left: f32, screen_w: f32, world_w: f32, world_h: f32) {
+    let tex_w = layer.texture.width();
+    let tex_h = layer.texture.height();
+    if tex_w <= 0.0 {
+        return;
+    }
+
+    let offset = cam_left * (1.0 - layer.parallax);
+    let start = cam_left.max(0.0);
+    let end = (cam_left + screen_w).min(world_w);
+    let y = world_h - tex_h;
+
+    let mut tile_x = offset + ((start - offset) / tex_w).floor() * tex_w;
+    while tile_x < end {
+        let left = tile_x.max(start);
+        let right = (tile_x + tex_w).min(end);
+        if right > left {
+            draw_texture_ex(
+                &layer.texture,
+                left,
+                y,
+                WHITE,
+                DrawTextureParams {
+                    dest_size: Some(vec2(right - left, tex_h)),
+                    source: Some(Rect::new(left - tile_x, 0.0, right - left, tex_h)),
+                    ..Default::default()
+                },
+            );
+        }
+        tile_x += tex_w;
+    }
+}
+
+fn draw_far_hills(cam_left: f32, world_w: f32, horizon_y: f32, far_color: Color) {
+    let far_offset = cam_left * (1.0 - FAR_PARALLAX);
     for (x, radius) in [
         (world_w * 0.18, 190.0),
         (world_w * 0.52, 230.0),
@@ -25,10 +153,10 @@ fn draw_hills(cam_left: f32, world_w: f32, world_h: f32, config: &Config) {
     ] {
         draw_circle(x + far_offset, horizon_y + 60.0, radius, far_color);
     }
+}
 
-    let near_parallax = 0.55;
-    let near_offset = cam_left * (1.0 - near_parallax);
-    let near_color = Color::new(0.28, 0.62, 0.34, 1.0);
+fn draw_near_hills(cam_left: f32, world_w: f32, horizon_y: f32, near_color: Color) {
+    let near_offset = cam_left * (1.0 - NEAR_PARALLAX);
     for (x, radius) in [
         (world_w * 0.05, 140.0),
         (world_w * 0.36, 160.0),
@@ -39,10 +167,8 @@ fn draw_hills(cam_left: f32, world_w: f32, world_h: f32, config: &Config) {
     }
 }
 
-fn draw_clouds(cam_left: f32, world_w: f32) {
-    let parallax = 0.15;
-    let offset = cam_left * (1.0 - parallax);
-    let color = Color::new(1.0, 1.0, 1.0, 0.9);
+fn draw_clouds(cam_left: f32, world_w: f32, color: Color) {
+    let offset = cam_left * (1.0 - CLOUD_PARALLAX);
 
     for (x, y, scale) in [
         (world_w * 0.12, 90.0, 1.0),
@@ -57,3 +183,47 @@ fn draw_clouds(cam_left: f32, world_w: f32) {
         draw_circle(cx + r * 0.85, y, r * 0.95, color);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::camera::Letterbox;
+
+    #[test]
+    fn parallax_span_matches_the_camera_on_any_window_shape() {
+        let config = Config::default();
+        let level = format!("P{}G\n{}\n", ".".repeat(98), "#".repeat(100));
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let world_w = world.width as f32 * config.tile_size;
+
+        for screen in [
+            vec2(960.0, 540.0),
+            vec2(1280.0, 1024.0),
+            vec2(700.0, 900.0),
+            vec2(3440.0, 1440.0),
+        ] {
+            let letterbox = Letterbox::fit(screen, &config);
+            let camera = world.camera_for_focus(vec2(0.0, 0.0), letterbox.view, &config);
+            // Square world units: same scale across and down the drawn area.
+            let scale = letterbox.area.size() / letterbox.view;
+            assert!((scale.x - scale.y).abs() < 1e-3, "{screen}: scale {scale}");
+
+            let (left, width) = visible_span(&camera);
+            assert!((width - letterbox.view.x).abs() < 1e-3, "{screen}");
+            assert!(left.abs() < 1e-3, "{screen}: view starts at {left}");
+
+            let camera = world.camera_for_focus(vec2(world_w, 0.0), letterbox.view, &config);
+            let (left, width) = visible_span(&camera);
+            assert!((left + width - world_w).abs() < 1e-3, "{screen}");
+        }
+    }
+
+    #[test]
+    fn foreground_slides_faster_than_the_camera() {
+        // A prop's position relative to the view's left edge, before and
+        // after the camera pans right by 100.
+        let on_screen = |cam_left: f32| 500.0 + foreground_offset(cam_left) - cam_left;
+        let moved = on_screen(200.0) - on_screen(300.0);
+        assert!((moved - 100.0 * FOREGROUND_PARALLAX).abs() < 1e-3);
+    }
+}
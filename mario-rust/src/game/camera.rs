@@ -0,0 +1,144 @@
+use macroquad::prelude::*;
+
+use super::Config;
+
+/// Smoothed point the camera is centered on. It eases toward a target a
+/// little ahead of the player so more of the level is visible in the
+/// direction they're heading. World-bounds clamping happens when the
+/// `Camera2D` is built in `World::camera_for_focus`.
+///
+/// Vertically the camera holds still while the player is airborne inside a
+/// dead-zone band around the focus, so ordinary jumps don't bob the view; it
+/// only chases them once they leave the band, and re-centers after landing.
+pub struct FollowCamera {
+    focus: Vec2,
+}
+
+impl FollowCamera {
+    pub fn new(player_center: Vec2) -> Self {
+        Self {
+            focus: player_center,
+        }
+    }
+
+    /// Jumps straight to the player, e.g. after a respawn.
+    pub fn snap(&mut self, player_center: Vec2, facing: f32, config: &Config) {
+        self.focus = Self::target(player_center, facing, config);
+    }
+
+    pub fn update(
+        &mut self,
+        player_center: Vec2,
+        facing: f32,
+        grounded: bool,
+        config: &Config,
+        dt: f32,
+    ) {
+        let mut target = Self::target(player_center, facing, config);
+        if !grounded {
+            let half_band = config.camera_dead_zone * 0.5;
+            let offset = player_center.y - self.focus.y;
+            target.y = if offset > half_band {
+                player_center.y - half_band
+            } else if offset < -half_band {
+                player_center.y + half_band
+            } else {
+                self.focus.y
+            };
+        }
+
+        // Frame-rate independent exponential ease toward the target.
+        let t = 1.0 - (-config.camera_lerp * dt).exp();
+        self.focus += (target - self.focus) * t;
+    }
+
+    pub fn focus(&self) -> Vec2 {
+        self.focus
+    }
+
+    fn target(player_center: Vec2, facing: f32, config: &Config) -> Vec2 {
+        player_center + vec2(facing * config.camera_lookahead, 0.0)
+    }
+}
+
+/// Part of the window the world is drawn into. The world is always shown at
+/// `Config::view_height` world units tall, so sprites keep their shape on any
+/// window; windows outside the allowed aspect range get black bars instead
+/// of a stretched or overly wide view.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Letterbox {
+    /// Window-space rectangle the world fills, in pixels.
+    pub area: Rect,
+    /// Visible world size, in world units.
+    pub view: Vec2,
+}
+
+impl Letterbox {
+    pub fn fit(screen: Vec2, config: &Config) -> Self {
+        let aspect = (screen.x / screen.y).clamp(config.min_view_aspect, config.max_view_aspect);
+        let size = if screen.x / screen.y > aspect {
+            vec2(screen.y * aspect, screen.y)
+        } else {
+            vec2(screen.x, screen.x / aspect)
+        };
+        let origin = ((screen - size) * 0.5).floor();
+        Self {
+            area: Rect::new(origin.x, origin.y, size.x, size.y),
+            view: vec2(config.view_height * aspect, config.view_height),
+        }
+    }
+
+    /// `area` as a GL viewport, which counts rows from the window's bottom.
+    pub fn viewport(&self, screen: Vec2) -> (i32, i32, i32, i32) {
+        let bottom = screen.y - self.area.y - self.area.h;
+        (
+            self.area.x as i32,
+            bottom as i32,
+            self.area.w as i32,
+            self.area.h as i32,
+        )
+    }
+
+    /// Blacks out the window outside `area`. Call with the default camera.
+    pub fn draw_bars(&self, screen: Vec2) {
+        let Rect { x, y, w, h } = self.area;
+        draw_rectangle(0.0, 0.0, screen.x, y, BLACK);
+        draw_rectangle(0.0, y + h, screen.x, screen.y - y - h, BLACK);
+        draw_rectangle(0.0, y, x, h, BLACK);
+        draw_rectangle(x + w, y, screen.x - x - w, h, BLACK);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::world::World;
+
+    #[test]
+    fn ultrawide_window_keeps_a_uniform_zoom() {
+        let config = Config::default();
+        let level = "\
+P......................................................................G
+########################################################################
+";
+        let world = World::from_ascii(level, &config).expect("test level is valid");
+
+        let screen = vec2(2560.0, 1080.0);
+        let letterbox = Letterbox::fit(screen, &config);
+        assert_eq!(letterbox.area, Rect::new(320.0, 0.0, 1920.0, 1080.0));
+        assert_eq!(letterbox.view, vec2(960.0, 540.0));
+        assert_eq!(letterbox.viewport(screen), (320, 0, 1920, 1080));
+
+        let camera = world.camera_for_focus(vec2(0.0, 0.0), letterbox.view, &config);
+        assert_eq!(camera.zoom, vec2(2.0 / 960.0, 2.0 / 540.0));
+        // World units per pixel match on both axes.
+        let per_px = 2.0 / (camera.zoom * vec2(letterbox.area.w, letterbox.area.h));
+        assert_eq!(per_px.x, per_px.y);
+        // Clamped against the visible width, not the window's.
+        assert_eq!(camera.target.x, 480.0);
+
+        let square = Letterbox::fit(vec2(1000.0, 1000.0), &config);
+        assert_eq!(square.area, Rect::new(0.0, 125.0, 1000.0, 750.0));
+        assert_eq!(square.view, vec2(720.0, 540.0));
+    }
+}
@@ -0,0 +1,92 @@
+use super::Config;
+
+/// How a difficulty changes the base `Config`.
+struct Scaling {
+    /// Multiplier for walking and flying enemy speeds.
+    enemy_speed: f32,
+    /// Added to `Config::starting_lives` (at least one life remains).
+    extra_lives: i32,
+    /// Multiplier for the invulnerability time after getting hurt.
+    hurt_invuln_time: f32,
+}
+
+const EASY: Scaling = Scaling {
+    enemy_speed: 0.75,
+    extra_lives: 2,
+    hurt_invuln_time: 1.5,
+};
+const NORMAL: Scaling = Scaling {
+    enemy_speed: 1.0,
+    extra_lives: 0,
+    hurt_invuln_time: 1.0,
+};
+const HARD: Scaling = Scaling {
+    enemy_speed: 1.3,
+    extra_lives: -2,
+    hurt_invuln_time: 0.6,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 3] = [Self::Easy, Self::Normal, Self::Hard];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "easy" => Some(Self::Easy),
+            "normal" => Some(Self::Normal),
+            "hard" => Some(Self::Hard),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Easy => "easy",
+            Self::Normal => "normal",
+            Self::Hard => "hard",
+        }
+    }
+
+    /// `base` with this difficulty's scaling applied.
+    pub fn apply(self, base: &Config) -> Config {
+        let scaling = match self {
+            Self::Easy => &EASY,
+            Self::Normal => &NORMAL,
+            Self::Hard => &HARD,
+        };
+        Config {
+            enemy_speed: base.enemy_speed * scaling.enemy_speed,
+            flyer_speed: base.flyer_speed * scaling.enemy_speed,
+            starting_lives: (base.starting_lives as i32 + scaling.extra_lives).max(1) as u32,
+            hurt_invuln_time: base.hurt_invuln_time * scaling.hurt_invuln_time,
+            ..*base
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn easy_is_gentler_than_hard_and_normal_changes_nothing() {
+        let base = Config::default();
+        let easy = Difficulty::Easy.apply(&base);
+        let normal = Difficulty::Normal.apply(&base);
+        let hard = Difficulty::Hard.apply(&base);
+
+        assert!(easy.enemy_speed < normal.enemy_speed && normal.enemy_speed < hard.enemy_speed);
+        assert!(easy.starting_lives > normal.starting_lives);
+        assert!(hard.starting_lives < normal.starting_lives && hard.starting_lives >= 1);
+        assert_eq!(normal.enemy_speed, base.enemy_speed);
+        assert_eq!(normal.starting_lives, base.starting_lives);
+        assert_eq!(normal.hurt_invuln_time, base.hurt_invuln_time);
+    }
+}
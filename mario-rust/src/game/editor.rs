@@ -0,0 +1,210 @@
+use macroquad::prelude::*;
+
+use super::{
+    world::{LevelMeta, World},
+    Config,
+};
+
+/// Tiles a click cycles through, in order.
+const PALETTE: [char; 32] = [
+    '.', '#', 'B', '?', 'I', 'O', '<', '>', '/', '\\', 'W', '^', '~', '[', ']', '|', 'C', '*', 'M',
+    'E', 'F', 'K', 'T', 'Y', 'S', 'Z', 'P', 'G', 't', 'o', 'H', 'J',
+];
+const PAN_SPEED: f32 = 480.0;
+const TOOLBAR_H: f32 = 64.0;
+
+/// What the game should do after an editor frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorAction {
+    None,
+    Save,
+    Exit,
+}
+
+/// In-game editor working on a copy of the level's ASCII grid. Edits only
+/// become a `World` when saved, which re-parses the text to validate it.
+pub struct Editor {
+    grid: Vec<Vec<char>>,
+    meta: LevelMeta,
+    scroll: Vec2,
+    status: String,
+}
+
+impl Editor {
+    pub fn new(world: &World) -> Self {
+        Self {
+            grid: world.tiles.clone(),
+            meta: world.meta.clone(),
+            scroll: Vec2::ZERO,
+            status: String::new(),
+        }
+    }
+
+    /// Reads mouse and keyboard for one frame. Left click cycles the tile
+    /// under the cursor forward through `PALETTE`, right click backward.
+    pub fn update(&mut self, config: &Config, dt: f32) -> EditorAction {
+        if is_key_pressed(KeyCode::Escape) {
+            return EditorAction::Exit;
+        }
+        let ctrl = is_key_down(KeyCode::LeftControl) || is_key_down(KeyCode::RightControl);
+        if ctrl && is_key_pressed(KeyCode::S) {
+            return EditorAction::Save;
+        }
+
+        let mut pan = Vec2::ZERO;
+        if is_key_down(KeyCode::Left) {
+            pan.x -= 1.0;
+        }
+        if is_key_down(KeyCode::Right) {
+            pan.x += 1.0;
+        }
+        if is_key_down(KeyCode::Up) {
+            pan.y -= 1.0;
+        }
+        if is_key_down(KeyCode::Down) {
+            pan.y += 1.0;
+        }
+        self.scroll += pan * PAN_SPEED * dt;
+        let extent = self.extent(config);
+        let view = vec2(screen_width(), screen_height() - TOOLBAR_H);
+        self.scroll = self
+            .scroll
+            .clamp(Vec2::ZERO, (extent - view).max(Vec2::ZERO));
+
+        let step: isize = if is_mouse_button_pressed(MouseButton::Left) {
+            1
+        } else if is_mouse_button_pressed(MouseButton::Right) {
+            -1
+        } else {
+            0
+        };
+        if step != 0 {
+            if let Some((col, row)) = self.hovered_cell(config) {
+                let tile = &mut self.grid[row][col];
+                let idx = PALETTE.iter().position(|ch| ch == tile).unwrap_or(0) as isize;
+                let len = PALETTE.len() as isize;
+                *tile = PALETTE[(idx + step).rem_euclid(len) as usize];
+            }
+        }
+
+        EditorAction::None
+    }
+
+    /// The edited level as file contents, header included.
+    pub fn to_level_text(&self) -> String {
+        let mut text = self.meta.to_header();
+        for row in &self.grid {
+            text.extend(row.iter());
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn set_status(&mut self, status: impl Into<String>) {
+        self.status = status.into();
+    }
+
+    pub fn draw(&self, config: &Config) {
+        let tile = config.tile_size;
+        let hovered = self.hovered_cell(config);
+
+        for (row, line) in self.grid.iter().enumerate() {
+            for (col, &ch) in line.iter().enumerate() {
+                let pos = vec2(col as f32, row as f32) * tile - self.scroll + vec2(0.0, TOOLBAR_H);
+                if pos.x + tile < 0.0
+                    || pos.y + tile < TOOLBAR_H
+                    || pos.x > screen_width()
+                    || pos.y > screen_height()
+                {
+                    continue;
+                }
+
+                if let Some(color) = tile_color(ch) {
+                    draw_rectangle(pos.x, pos.y, tile, tile, color);
+                }
+                if ch != '.' {
+                    draw_text(
+                        &ch.to_string(),
+                        pos.x + tile * 0.3,
+                        pos.y + tile * 0.7,
+                        tile * 0.6,
+                        BLACK,
+                    );
+                }
+                draw_rectangle_lines(
+                    pos.x,
+                    pos.y,
+                    tile,
+                    tile,
+                    1.0,
+                    Color::new(0.0, 0.0, 0.0, 0.12),
+                );
+                if hovered == Some((col, row)) {
+                    draw_rectangle_lines(pos.x, pos.y, tile, tile, 2.0, WHITE);
+                }
+            }
+        }
+
+        draw_rectangle(
+            0.0,
+            0.0,
+            screen_width(),
+            TOOLBAR_H,
+            Color::new(0.0, 0.0, 0.0, 0.7),
+        );
+        draw_text(
+            "Level editor - click: next tile, right click: previous, arrows: pan, Ctrl+S: save, Esc: exit",
+            12.0,
+            24.0,
+            20.0,
+            WHITE,
+        );
+        draw_text(&self.status, 12.0, 50.0, 20.0, YELLOW);
+    }
+
+    fn extent(&self, config: &Config) -> Vec2 {
+        let cols = self.grid.first().map_or(0, Vec::len);
+        vec2(cols as f32, self.grid.len() as f32) * config.tile_size
+    }
+
+    fn hovered_cell(&self, config: &Config) -> Option<(usize, usize)> {
+        let (mouse_x, mouse_y) = mouse_position();
+        if mouse_y < TOOLBAR_H {
+            return None;
+        }
+        let world = vec2(mouse_x, mouse_y - TOOLBAR_H) + self.scroll;
+        let col = (world.x / config.tile_size).floor();
+        let row = (world.y / config.tile_size).floor();
+        if col < 0.0 || row < 0.0 {
+            return None;
+        }
+        let (col, row) = (col as usize, row as usize);
+        let in_bounds = self.grid.get(row).is_some_and(|line| col < line.len());
+        in_bounds.then_some((col, row))
+    }
+}
+
+fn tile_color(ch: char) -> Option<Color> {
+    let color = match ch {
+        '#' => Color::new(0.25, 0.55, 0.25, 1.0),
+        'B' => Color::new(0.72, 0.36, 0.2, 1.0),
+        '?' => Color::new(0.95, 0.75, 0.2, 1.0),
+        'I' => Color::new(0.7, 0.9, 1.0, 1.0),
+        'O' => Color::new(0.15, 0.65, 0.2, 1.0),
+        '<' | '>' => Color::new(0.45, 0.45, 0.5, 1.0),
+        '/' | '\\' => Color::new(0.35, 0.6, 0.3, 1.0),
+        'W' => Color::new(0.2, 0.45, 0.9, 0.6),
+        '^' => Color::new(0.8, 0.8, 0.85, 1.0),
+        'C' => Color::new(0.95, 0.8, 0.2, 0.6),
+        '*' => Color::new(1.0, 0.95, 0.4, 0.8),
+        'E' | 'F' | 'K' | 'T' | 'Y' | 'S' | 'Z' => Color::new(0.85, 0.3, 0.3, 0.6),
+        'P' => Color::new(1.0, 1.0, 1.0, 0.7),
+        'G' => Color::new(0.2, 0.9, 0.4, 0.7),
+        'J' => Color::new(0.9, 0.25, 0.2, 0.8),
+        'H' => Color::new(0.6, 0.4, 0.2, 0.8),
+        't' | 'o' => Color::new(0.5, 0.6, 0.5, 0.3),
+        '.' => return None,
+        _ => Color::new(0.7, 0.6, 0.9, 0.6),
+    };
+    Some(color)
+}
@@ -1,87 +1,445 @@
 use macroquad::prelude::*;
 
-use super::{physics, sprites::Sprites, world::World, Config};
+use super::{
+    physics,
+    sprites::Sprites,
+    world::{EnemySpawn, World},
+    Config,
+};
+
+/// Sprite height, as a fraction of normal, while a dead enemy is squashed.
+const SQUASHED_HEIGHT: f32 = 0.3;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnemyKind {
+    /// Walks along the ground and turns at walls and ledges.
+    #[default]
+    Walker,
+    /// Ignores gravity and bobs on a sine wave while drifting sideways.
+    Flyer,
+    /// Walks like a walker but retreats into a kickable shell when stomped.
+    Shelled,
+    /// Sits still and periodically fires a shot toward the player.
+    Turret,
+    /// Walks like a walker and hops every `Config::hop_interval` seconds.
+    Hopper,
+    /// Walks like a walker, but its spikes hurt a player who lands on it.
+    Spiky,
+    /// A large walker that takes `Config::boss_health` hits to defeat and
+    /// speeds up once it is down to half.
+    Boss,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EnemyState {
+    #[default]
+    Active,
+    /// A stomped shelled enemy sitting still, waiting to be kicked.
+    Shell,
+    /// A kicked shell sliding along the ground.
+    SlidingShell,
+}
 
 #[derive(Clone)]
 pub struct Enemy {
     pub pos: Vec2,
     pub vel: Vec2,
+    kind: EnemyKind,
+    state: EnemyState,
     dir: f32,
     pub alive: bool,
     size: Vec2,
     on_ground: bool,
+    base_y: f32,
+    phase: f32,
+    contact_timer: f32,
+    chain: u32,
+    /// Time left on the squash animation after death; drawn until it ends.
+    death_timer: f32,
+    /// Turrets only: seconds until the next shot.
+    shoot_timer: f32,
+    /// Hoppers only: seconds until the next hop, counted while grounded.
+    jump_timer: f32,
+    /// Hits left before a boss dies; other kinds die on the first hit.
+    health: u32,
+    /// Bosses only: seconds left ignoring hits after taking one. Unlike
+    /// `contact_timer` it doesn't stop the boss hurting the player.
+    hurt_timer: f32,
+    /// Multiplier on `Config::enemy_speed` from the spawn's speed tier.
+    speed_scale: f32,
+    /// `Config::gravity_dir` at spawn; an enemy on the ceiling draws flipped.
+    gravity_dir: f32,
+    min_x: Option<f32>,
+    max_x: Option<f32>,
+    /// Where the enemy came from, for resets and respawns.
+    spawn: EnemySpawn,
 }
 
 impl Enemy {
-    pub fn new(tile_pos: Vec2, world: &World, config: &Config) -> Self {
-        let size = config.enemy_size;
+    pub fn new(spawn: EnemySpawn, world: &World, config: &Config) -> Self {
+        let EnemySpawn { tile_pos, kind, .. } = spawn;
+        let size = if kind == EnemyKind::Boss {
+            config.boss_size
+        } else {
+            config.enemy_size
+        };
         let tile = config.tile_size;
         let x = tile_pos.x + (tile - size.x) * 0.5;
-        let sample_x = tile_pos.x + tile * 0.5;
-        let base_y = world
-            .ground_y_for_x(sample_x, tile_pos.y, config)
-            .unwrap_or(tile_pos.y + tile);
-        let y = base_y - size.y;
+        let y = match kind {
+            EnemyKind::Walker
+            | EnemyKind::Shelled
+            | EnemyKind::Turret
+            | EnemyKind::Boss
+            | EnemyKind::Hopper
+            | EnemyKind::Spiky => {
+                let sample_x = tile_pos.x + tile * 0.5;
+                let surface =
+                    world.surface_y_for_x(sample_x, tile_pos.y, config.gravity_dir, config);
+                if config.gravity_dir < 0.0 {
+                    surface.unwrap_or(tile_pos.y)
+                } else {
+                    surface.unwrap_or(tile_pos.y + tile) - size.y
+                }
+            }
+            EnemyKind::Flyer => tile_pos.y + (tile - size.y) * 0.5,
+        };
 
         Self {
             pos: vec2(x, y),
             vel: Vec2::ZERO,
+            kind,
+            state: EnemyState::Active,
             dir: -1.0,
             alive: true,
             size,
             on_ground: false,
+            base_y: y,
+            phase: 0.0,
+            contact_timer: 0.0,
+            chain: 0,
+            death_timer: 0.0,
+            shoot_timer: config.turret_fire_interval,
+            jump_timer: config.hop_interval,
+            health: if kind == EnemyKind::Boss {
+                config.boss_health.max(1)
+            } else {
+                1
+            },
+            hurt_timer: 0.0,
+            speed_scale: spawn.speed_tier.max(1) as f32,
+            gravity_dir: config.gravity_dir,
+            min_x: spawn.min_x,
+            max_x: spawn.max_x,
+            spawn,
         }
     }
 
-    pub fn reset(&mut self, tile_pos: Vec2, world: &World, config: &Config) {
-        *self = Self::new(tile_pos, world, config);
+    pub fn reset(&mut self, world: &World, config: &Config) {
+        *self = Self::new(self.spawn, world, config);
+    }
+
+    /// Brings a dead enemy back at its spawn once `player` is more than
+    /// `Config::respawn_distance` from it. Bosses stay dead.
+    pub fn respawn_if_far(&mut self, player: Vec2, world: &World, config: &Config) {
+        if self.alive || self.death_timer > 0.0 || self.is_boss() {
+            return;
+        }
+        let spawn_center = self.spawn.tile_pos + Vec2::splat(config.tile_size * 0.5);
+        if spawn_center.distance(player) > config.respawn_distance {
+            self.reset(world, config);
+        }
     }
 
     pub fn update(&mut self, world: &World, config: &Config, dt: f32) {
         if !self.alive {
+            self.death_timer = (self.death_timer - dt).max(0.0);
             return;
         }
 
-        self.vel.y = (self.vel.y + config.gravity * dt).min(config.terminal_velocity);
-        self.vel.x = config.enemy_speed * self.dir;
+        self.contact_timer = (self.contact_timer - dt).max(0.0);
+        self.hurt_timer = (self.hurt_timer - dt).max(0.0);
 
-        let desired_x = self.vel.x;
-        let (pos, vel, on_ground) =
-            physics::move_with_collisions(self.pos, self.size, self.vel, &world.solids, dt);
+        match (self.kind, self.state) {
+            (_, EnemyState::Shell) => self.update_shell(world, config, 0.0, dt),
+            (_, EnemyState::SlidingShell) => {
+                self.update_shell(world, config, config.shell_speed, dt)
+            }
+            (
+                EnemyKind::Walker
+                | EnemyKind::Shelled
+                | EnemyKind::Boss
+                | EnemyKind::Hopper
+                | EnemyKind::Spiky,
+                EnemyState::Active,
+            ) => self.update_walker(world, config, dt),
+            (EnemyKind::Flyer, EnemyState::Active) => self.update_flyer(world, config, dt),
+            (EnemyKind::Turret, EnemyState::Active) => self.update_turret(world, config, dt),
+        }
 
-        let hit_wall = desired_x.abs() > f32::EPSILON && vel.x.abs() <= f32::EPSILON;
-        self.pos = pos;
-        self.vel = vel;
-        self.on_ground = on_ground;
+        if self.state == EnemyState::Active {
+            self.apply_patrol_bounds();
+        }
+
+        let world_w = world.width as f32 * config.tile_size;
+        if self.pos.x <= 0.0 {
+            self.pos.x = 0.0;
+            self.dir = 1.0;
+        } else if self.pos.x + self.size.x >= world_w {
+            self.pos.x = (world_w - self.size.x).max(0.0);
+            self.dir = -1.0;
+        }
+    }
+
+    fn apply_patrol_bounds(&mut self) {
+        if let Some(min_x) = self.min_x {
+            if self.pos.x <= min_x {
+                self.pos.x = min_x;
+                self.dir = 1.0;
+            }
+        }
+        if let Some(max_x) = self.max_x {
+            if self.pos.x + self.size.x >= max_x {
+                self.pos.x = max_x - self.size.x;
+                self.dir = -1.0;
+            }
+        }
+    }
+
+    fn walk_speed(&self, config: &Config) -> f32 {
+        if self.kind != EnemyKind::Boss {
+            return config.enemy_speed * self.speed_scale;
+        }
+        if self.health * 2 <= config.boss_health {
+            config.boss_speed * config.boss_enraged_speed_scale
+        } else {
+            config.boss_speed
+        }
+    }
+
+    fn update_walker(&mut self, world: &World, config: &Config, dt: f32) {
+        let speed = self.walk_speed(config);
+        if self.kind == EnemyKind::Hopper && self.on_ground {
+            self.jump_timer -= dt;
+            if self.jump_timer <= 0.0 {
+                self.jump_timer = config.hop_interval;
+                self.launch(config.hop_speed * config.gravity_dir);
+            }
+        }
+        self.fall(config, dt);
+        self.vel.x = speed * self.dir;
+
+        let result = self.move_and_collide(world, config, dt);
+
+        let hit_wall = result.touching_left || result.touching_right;
+        self.pos = result.pos;
+        self.vel = result.vel;
+        self.on_ground = result.touching_ground;
 
         if hit_wall {
             self.dir *= -1.0;
-            self.vel.x = config.enemy_speed * self.dir;
+            self.vel.x = speed * self.dir;
         } else if self.on_ground {
             let foot_x = if self.dir >= 0.0 {
                 self.pos.x + self.size.x + 1.0
             } else {
                 self.pos.x - 1.0
             };
-            let foot_y = self.pos.y + self.size.y + 1.0;
-            if world
-                .ground_y_for_x(foot_x, foot_y, config)
-                .map(|ground_y| ground_y <= foot_y)
-                != Some(true)
-            {
+            let foot_y = if config.gravity_dir < 0.0 {
+                self.pos.y - 1.0
+            } else {
+                self.pos.y + self.size.y + 1.0
+            };
+            let tile = config.tile_size;
+            if !world.is_solid_tile(
+                (foot_x / tile).floor() as i32,
+                (foot_y / tile).floor() as i32,
+            ) {
                 self.dir *= -1.0;
-                self.vel.x = config.enemy_speed * self.dir;
+                self.vel.x = speed * self.dir;
             }
         }
+    }
 
-        let world_w = world.width as f32 * config.tile_size;
-        if self.pos.x <= 0.0 {
-            self.pos.x = 0.0;
-            self.dir = 1.0;
-        } else if self.pos.x + self.size.x >= world_w {
-            self.pos.x = (world_w - self.size.x).max(0.0);
-            self.dir = -1.0;
+    fn update_flyer(&mut self, world: &World, config: &Config, dt: f32) {
+        self.phase += dt * config.flyer_frequency * std::f32::consts::TAU;
+        let target_y = self.base_y + self.phase.sin() * config.flyer_amplitude;
+        self.vel = vec2(config.flyer_speed * self.dir, (target_y - self.pos.y) / dt);
+
+        let result = physics::move_with_collisions(self.pos, self.size, self.vel, world, dt);
+        self.pos = result.pos;
+        self.vel = result.vel;
+
+        if result.touching_left || result.touching_right {
+            self.dir *= -1.0;
+            self.vel.x = config.flyer_speed * self.dir;
+        }
+    }
+
+    fn update_turret(&mut self, world: &World, config: &Config, dt: f32) {
+        self.shoot_timer = (self.shoot_timer - dt).max(0.0);
+        self.vel.x = 0.0;
+        self.fall(config, dt);
+
+        let result = self.move_and_collide(world, config, dt);
+        self.pos = result.pos;
+        self.vel = result.vel;
+        self.on_ground = result.touching_ground;
+    }
+
+    /// For a live turret whose reload has finished: turns it toward
+    /// `target_x`, restarts the reload and returns the direction to fire in.
+    pub fn take_shot(&mut self, target_x: f32, config: &Config) -> Option<f32> {
+        if !self.alive || self.kind != EnemyKind::Turret || self.shoot_timer > 0.0 {
+            return None;
+        }
+        self.shoot_timer = config.turret_fire_interval;
+        self.dir = if target_x < self.rect().center().x {
+            -1.0
+        } else {
+            1.0
+        };
+        Some(self.dir)
+    }
+
+    fn update_shell(&mut self, world: &World, config: &Config, speed: f32, dt: f32) {
+        self.fall(config, dt);
+        self.vel.x = speed * self.dir;
+
+        let result = self.move_and_collide(world, config, dt);
+        self.pos = result.pos;
+        self.vel = result.vel;
+        self.on_ground = result.touching_ground;
+
+        if result.touching_left || result.touching_right {
+            self.dir *= -1.0;
+            self.vel.x = speed * self.dir;
+        }
+    }
+
+    fn fall(&mut self, config: &Config, dt: f32) {
+        self.vel.y = physics::apply_gravity(
+            self.vel.y,
+            config.gravity,
+            config.terminal_velocity,
+            config.gravity_dir,
+            dt,
+        );
+    }
+
+    fn move_and_collide(
+        &self,
+        world: &World,
+        config: &Config,
+        dt: f32,
+    ) -> physics::CollisionResult {
+        physics::move_with_gravity(
+            self.pos,
+            self.size,
+            self.vel,
+            world,
+            dt,
+            0.0,
+            config.gravity_dir,
+        )
+    }
+
+    /// Applies a stomp: shelled enemies retreat into (or stop) their shell,
+    /// everything else takes a hit. Returns `false` if the hit was ignored.
+    pub fn stomp(&mut self, config: &Config) -> bool {
+        if self.kind == EnemyKind::Shelled {
+            self.state = EnemyState::Shell;
+            self.vel.x = 0.0;
+            self.chain = 0;
+            self.contact_timer = config.shell_contact_grace;
+            true
+        } else {
+            self.take_hit(config)
+        }
+    }
+
+    /// Damages the enemy from a stomp, fireball, shell or star. Most enemies
+    /// die outright; a boss loses one health and flashes, ignoring further
+    /// hits for `Config::boss_hurt_time`. Returns `false` if the hit was
+    /// ignored.
+    pub fn take_hit(&mut self, config: &Config) -> bool {
+        if !self.alive || self.hurt_timer > 0.0 {
+            return false;
+        }
+        self.health = self.health.saturating_sub(1);
+        if self.health == 0 {
+            self.kill(config);
+        } else {
+            self.hurt_timer = config.boss_hurt_time;
         }
+        true
+    }
+
+    /// Takes the enemy out of play immediately but leaves it squashed on
+    /// screen for `Config::enemy_squash_time`.
+    pub fn kill(&mut self, config: &Config) {
+        self.alive = false;
+        self.vel = Vec2::ZERO;
+        self.death_timer = config.enemy_squash_time;
+    }
+
+    /// Sends an idle shell sliding in `dir`.
+    pub fn kick(&mut self, dir: f32, config: &Config) {
+        self.state = EnemyState::SlidingShell;
+        self.dir = dir;
+        self.vel.x = config.shell_speed * dir;
+        self.chain = 0;
+        self.contact_timer = config.shell_contact_grace;
+    }
+
+    pub fn is_idle_shell(&self) -> bool {
+        self.state == EnemyState::Shell
+    }
+
+    pub fn is_sliding_shell(&self) -> bool {
+        self.alive && self.state == EnemyState::SlidingShell
+    }
+
+    /// True briefly after a stomp or kick so the player's own contact doesn't
+    /// immediately kick the shell again or get hurt by it.
+    pub fn ignores_player(&self) -> bool {
+        self.contact_timer > 0.0
+    }
+
+    /// Reverses a patrolling enemy after bumping into another one, nudging it
+    /// `push` pixels horizontally so the pair separates.
+    pub fn turn_around(&mut self, push: f32) {
+        self.dir = -self.dir;
+        self.vel.x = -self.vel.x;
+        self.pos.x += push;
+    }
+
+    /// Advances the sliding shell's kill chain and returns the new chain length.
+    pub fn bump_chain(&mut self) -> u32 {
+        self.chain += 1;
+        self.chain
+    }
+
+    /// True for enemies that can't be stomped.
+    pub fn is_spiky(&self) -> bool {
+        self.kind == EnemyKind::Spiky
+    }
+
+    pub fn is_boss(&self) -> bool {
+        self.kind == EnemyKind::Boss
+    }
+
+    pub fn health(&self) -> u32 {
+        self.health
+    }
+
+    /// Throws the enemy straight up, e.g. off a spring.
+    pub fn launch(&mut self, speed: f32) {
+        self.vel.y = -speed;
+        self.on_ground = false;
+    }
+
+    pub fn is_on_ground(&self) -> bool {
+        self.alive && self.on_ground
     }
 
     pub fn rect(&self) -> Rect {
@@ -89,20 +447,188 @@ impl Enemy {
     }
 
     pub fn draw(&self, sprites: &Sprites) {
-        if !self.alive {
+        if !self.alive && self.death_timer <= 0.0 {
             return;
         }
+        let height_scale = if self.alive { 1.0 } else { SQUASHED_HEIGHT };
 
+        if self.state != EnemyState::Active {
+            self.draw_shell(height_scale);
+            return;
+        }
+        if self.kind == EnemyKind::Turret {
+            self.draw_turret(height_scale);
+            return;
+        }
+
+        let tint = match self.kind {
+            // Faster tiers run hot.
+            EnemyKind::Walker if self.speed_scale > 1.0 => Color::new(1.0, 0.55, 0.5, 1.0),
+            EnemyKind::Walker => WHITE,
+            EnemyKind::Shelled => Color::new(0.6, 1.0, 0.6, 1.0),
+            EnemyKind::Hopper => Color::new(1.0, 0.85, 0.4, 1.0),
+            EnemyKind::Turret | EnemyKind::Spiky => WHITE,
+            EnemyKind::Boss if self.hurt_timer > 0.0 => {
+                // Flash while shrugging off a hit.
+                let visible = (self.hurt_timer * 20.0) as i32 % 2 == 0;
+                Color::new(1.0, 0.5, 0.45, if visible { 1.0 } else { 0.35 })
+            }
+            EnemyKind::Boss => Color::new(1.0, 0.5, 0.45, 1.0),
+            EnemyKind::Flyer if !self.alive => Color::new(0.85, 0.7, 1.0, 1.0),
+            EnemyKind::Flyer => {
+                let wing = Color::new(1.0, 1.0, 1.0, 0.85);
+                let wing_y = self.pos.y + self.size.y * 0.2;
+                let flap = self.phase.cos() * self.size.y * 0.12;
+                draw_ellipse(self.pos.x, wing_y - flap, 7.0, 4.0, 0.0, wing);
+                draw_ellipse(self.pos.x + self.size.x, wing_y - flap, 7.0, 4.0, 0.0, wing);
+                Color::new(0.85, 0.7, 1.0, 1.0)
+            }
+        };
+
+        let height = self.size.y * height_scale;
+        let upside_down = self.gravity_dir < 0.0;
+        let y = if upside_down {
+            self.pos.y
+        } else {
+            self.pos.y + self.size.y - height
+        };
         draw_texture_ex(
-            sprites.chestnut_guy(),
+            sprites.enemy(self.kind),
             self.pos.x,
-            self.pos.y,
-            WHITE,
+            y,
+            tint,
             DrawTextureParams {
-                dest_size: Some(self.size),
+                dest_size: Some(vec2(self.size.x, height)),
                 flip_x: self.vel.x < 0.0,
+                flip_y: upside_down,
                 ..Default::default()
             },
         );
     }
+
+    fn draw_turret(&self, height_scale: f32) {
+        let body = Color::new(0.25, 0.25, 0.3, 1.0);
+        let trim = Color::new(0.85, 0.75, 0.3, 1.0);
+        let height = self.size.y * height_scale;
+        let top = self.pos.y + self.size.y - height;
+        draw_rectangle(self.pos.x, top, self.size.x, height, body);
+        draw_rectangle(self.pos.x, top, self.size.x, 3.0 * height_scale, trim);
+
+        let barrel_w = self.size.x * 0.4;
+        let barrel_h = height * 0.35;
+        let barrel_x = if self.dir >= 0.0 {
+            self.pos.x + self.size.x
+        } else {
+            self.pos.x - barrel_w
+        };
+        draw_rectangle(
+            barrel_x,
+            top + (height - barrel_h) * 0.5,
+            barrel_w,
+            barrel_h,
+            body,
+        );
+    }
+
+    fn draw_shell(&self, height_scale: f32) {
+        let shell = Color::new(0.15, 0.6, 0.2, 1.0);
+        let rim = Color::new(0.95, 0.9, 0.7, 1.0);
+        let center = self.rect().center();
+        let half_w = self.size.x * 0.5;
+        let half_h = self.size.y * 0.35 * height_scale;
+        let base_y = self.pos.y + self.size.y - half_h;
+        draw_ellipse(center.x, base_y, half_w, half_h, 0.0, shell);
+        draw_rectangle(
+            self.pos.x,
+            self.pos.y + self.size.y - 4.0,
+            self.size.x,
+            3.0,
+            rim,
+        );
+        draw_ellipse_lines(center.x, base_y, half_w * 0.5, half_h * 0.6, 0.0, 1.5, rim);
+    }
+}
+
+/// Index pairs of live, non-sliding enemies whose rects overlap, each as
+/// `(lower, higher)` and sorted. This is the broad phase for enemy-to-enemy
+/// bumps: a sweep along x, so crowded levels don't cost O(n²) per step.
+pub fn overlapping_pairs(enemies: &[Enemy]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..enemies.len())
+        .filter(|&idx| enemies[idx].alive && !enemies[idx].is_sliding_shell())
+        .collect();
+    order.sort_by(|&a, &b| enemies[a].pos.x.total_cmp(&enemies[b].pos.x));
+
+    let mut pairs = Vec::new();
+    for (i, &a_idx) in order.iter().enumerate() {
+        let a = enemies[a_idx].rect();
+        for &b_idx in &order[i + 1..] {
+            let b = enemies[b_idx].rect();
+            if b.x >= a.x + a.w {
+                break;
+            }
+            if physics::rects_intersect(a, b) {
+                pairs.push((a_idx.min(b_idx), a_idx.max(b_idx)));
+            }
+        }
+    }
+    pairs.sort_unstable();
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn digit_after_walker_sets_its_speed_tier() {
+        let config = Config::default();
+        let level = "\
+..........
+.P.E..E3G.
+##########
+";
+        let world = World::from_ascii(level, &config).expect("test level is valid");
+        let enemies: Vec<Enemy> = world
+            .enemy_spawns
+            .iter()
+            .map(|spawn| Enemy::new(*spawn, &world, &config))
+            .collect();
+
+        assert_eq!(enemies[0].speed_scale, 1.0);
+        assert_eq!(enemies[1].speed_scale, 3.0);
+        assert_eq!(enemies[1].walk_speed(&config), config.enemy_speed * 3.0);
+    }
+
+    #[test]
+    fn hopper_hops_on_a_timer_and_still_turns_at_walls() {
+        let config = Config::default();
+        let level = "\
+..........
+#.P.Y..G.#
+##########
+";
+        let world = World::from_ascii(level, &config).expect("test level is valid");
+        let mut hopper = Enemy::new(world.enemy_spawns[0], &world, &config);
+        let ground_y = hopper.pos.y;
+        let world_w = world.width as f32 * config.tile_size;
+
+        let mut hops = 0;
+        let mut turned = false;
+        let mut was_airborne = false;
+        for _ in 0..(6.0 / config.fixed_dt) as usize {
+            let dir = hopper.dir;
+            hopper.update(&world, &config, config.fixed_dt);
+            turned |= hopper.dir != dir;
+            let airborne = hopper.pos.y < ground_y - 1.0;
+            if airborne && !was_airborne {
+                hops += 1;
+            }
+            was_airborne = airborne;
+            assert!(hopper.pos.x >= config.tile_size);
+            assert!(hopper.pos.x + hopper.size.x <= world_w - config.tile_size);
+        }
+
+        assert!((2..=4).contains(&hops), "{hops} hops in 6 seconds");
+        assert!(turned, "never turned at a wall");
+    }
 }
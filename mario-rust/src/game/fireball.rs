@@ -0,0 +1,61 @@
+use macroquad::prelude::*;
+
+use super::{physics, world::World, Config};
+
+pub struct Fireball {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    lifetime: f32,
+}
+
+impl Fireball {
+    /// Launches a fireball from the side of `thrower` it is facing.
+    pub fn new(thrower: Rect, facing: f32, config: &Config) -> Self {
+        let size = config.fireball_size;
+        let x = if facing >= 0.0 {
+            thrower.x + thrower.w
+        } else {
+            thrower.x - size
+        };
+        Self {
+            pos: vec2(x, thrower.y + thrower.h * 0.35),
+            vel: vec2(facing * config.fireball_speed, config.fireball_bounce * 0.5),
+            lifetime: config.fireball_lifetime,
+        }
+    }
+
+    /// Advances the fireball, returning `false` once it should despawn.
+    pub fn update(&mut self, world: &World, config: &Config, dt: f32) -> bool {
+        self.lifetime -= dt;
+        self.vel.y = (self.vel.y + config.gravity * dt).min(config.terminal_velocity);
+
+        let size = vec2(config.fireball_size, config.fireball_size);
+        let result = physics::move_with_collisions(self.pos, size, self.vel, world, dt);
+        self.pos = result.pos;
+        self.vel.y = result.vel.y;
+
+        if result.touching_ground {
+            self.vel.y = -config.fireball_bounce;
+        }
+
+        let fall_limit = world.height as f32 * config.tile_size;
+        let hit_wall = result.touching_left || result.touching_right;
+        self.lifetime > 0.0 && !hit_wall && self.pos.y < fall_limit
+    }
+
+    pub fn rect(&self, config: &Config) -> Rect {
+        physics::rect_at(self.pos, vec2(config.fireball_size, config.fireball_size))
+    }
+
+    pub fn draw(&self, config: &Config) {
+        let radius = config.fireball_size * 0.5;
+        let center = self.pos + vec2(radius, radius);
+        draw_circle(center.x, center.y, radius, Color::new(0.95, 0.45, 0.1, 1.0));
+        draw_circle(
+            center.x,
+            center.y,
+            radius * 0.5,
+            Color::new(1.0, 0.85, 0.3, 1.0),
+        );
+    }
+}
@@ -0,0 +1,46 @@
+use macroquad::prelude::*;
+
+const LIFETIME: f32 = 0.8;
+const RISE_SPEED: f32 = 60.0;
+const FONT_SIZE: f32 = 20.0;
+
+/// World-space text that drifts upward and fades, e.g. score popups.
+pub struct FloatingText {
+    pub text: String,
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub lifetime: f32,
+    pub color: Color,
+}
+
+pub fn spawn_score(texts: &mut Vec<FloatingText>, pos: Vec2, points: u32) {
+    texts.push(FloatingText {
+        text: format!("+{points}"),
+        pos,
+        vel: vec2(0.0, -RISE_SPEED),
+        lifetime: LIFETIME,
+        color: WHITE,
+    });
+}
+
+pub fn update(texts: &mut Vec<FloatingText>, dt: f32) {
+    for text in texts.iter_mut() {
+        text.pos += text.vel * dt;
+        text.lifetime -= dt;
+    }
+    texts.retain(|text| text.lifetime > 0.0);
+}
+
+pub fn draw(texts: &[FloatingText]) {
+    for text in texts {
+        let alpha = (text.lifetime / LIFETIME).clamp(0.0, 1.0);
+        let dims = measure_text(&text.text, None, FONT_SIZE as u16, 1.0);
+        let x = text.pos.x - dims.width * 0.5;
+        let shadow = Color::new(0.0, 0.0, 0.0, alpha * 0.6);
+        let mut color = text.color;
+        color.a *= alpha;
+
+        draw_text(&text.text, x + 1.0, text.pos.y + 1.0, FONT_SIZE, shadow);
+        draw_text(&text.text, x, text.pos.y, FONT_SIZE, color);
+    }
+}
@@ -0,0 +1,96 @@
+use gilrs::{Axis, Button, Gilrs};
+
+/// Stick deflection below this fraction counts as centred, so a drifting
+/// stick doesn't walk the player.
+const STICK_DEADZONE: f32 = 0.25;
+
+/// What the first connected gamepad is holding this frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PadState {
+    pub move_x: f32,
+    pub jump: bool,
+    pub start: bool,
+    pub back: bool,
+}
+
+/// Controller input, read alongside the keyboard. Holds no backend when
+/// running headless or when the platform has no gamepad support.
+#[derive(Default)]
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+    held: PadState,
+}
+
+impl Gamepads {
+    pub fn connect() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(error) => {
+                eprintln!("Gamepad init error: {error}");
+                None
+            }
+        };
+        Self {
+            gilrs,
+            held: PadState::default(),
+        }
+    }
+
+    /// This frame's state and last frame's, so callers can turn holds into
+    /// presses and releases.
+    pub fn poll(&mut self) -> (PadState, PadState) {
+        let now = self.gilrs.as_mut().map(read_first_pad).unwrap_or_default();
+        (now, std::mem::replace(&mut self.held, now))
+    }
+}
+
+fn read_first_pad(gilrs: &mut Gilrs) -> PadState {
+    // Gilrs only updates gamepad state while its events are drained.
+    while gilrs.next_event().is_some() {}
+    let Some((_, pad)) = gilrs.gamepads().next() else {
+        return PadState::default();
+    };
+    let dpad_x = match (
+        pad.is_pressed(Button::DPadLeft),
+        pad.is_pressed(Button::DPadRight),
+    ) {
+        (true, false) => -1.0,
+        (false, true) => 1.0,
+        _ => 0.0,
+    };
+    PadState {
+        move_x: if dpad_x != 0.0 {
+            dpad_x
+        } else {
+            stick_axis(pad.value(Axis::LeftStickX))
+        },
+        jump: pad.is_pressed(Button::South),
+        start: pad.is_pressed(Button::Start),
+        back: pad.is_pressed(Button::Select),
+    }
+}
+
+/// `raw` with the deadzone cut out and the rest rescaled to `-1..=1`, so
+/// movement still ramps up smoothly from the edge of the deadzone.
+fn stick_axis(raw: f32) -> f32 {
+    let magnitude = raw.abs().min(1.0);
+    if magnitude < STICK_DEADZONE {
+        return 0.0;
+    }
+    (magnitude - STICK_DEADZONE) / (1.0 - STICK_DEADZONE) * raw.signum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn small_stick_drift_does_not_move() {
+        assert_eq!(stick_axis(0.1), 0.0);
+        assert_eq!(stick_axis(-0.2), 0.0);
+        assert_eq!(stick_axis(1.0), 1.0);
+        assert_eq!(stick_axis(-1.5), -1.0);
+        let half = stick_axis(-(STICK_DEADZONE + 1.0) * 0.5);
+        assert!((half + 0.5).abs() < 1e-6);
+    }
+}
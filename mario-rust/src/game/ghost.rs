@@ -0,0 +1,100 @@
+//! The player's position on every fixed step of their fastest finish of a
+//! level, drawn as a translucent ghost to race against.
+
+use macroquad::prelude::*;
+
+pub const GHOST_PATH: &str = "ghost.txt";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Ghost {
+    /// Level file the run was on, relative to the assets folder.
+    pub level: String,
+    positions: Vec<Vec2>,
+}
+
+impl Ghost {
+    pub fn new(level: String, positions: Vec<Vec2>) -> Self {
+        Self { level, positions }
+    }
+
+    /// Reads `path`, or `None` if there is no usable ghost there.
+    pub fn load(path: &str) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        match Self::parse(&text) {
+            Ok(ghost) => Some(ghost),
+            Err(error) => {
+                eprintln!("Ghost file {path} ignored: {error}");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    /// Fixed steps the run took.
+    pub fn steps(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Player position on `step`, and on the step before it for working out
+    /// which way the ghost is moving. `None` once the run has finished.
+    pub fn frame(&self, step: usize) -> Option<(Vec2, Vec2)> {
+        let pos = *self.positions.get(step)?;
+        let prev = step
+            .checked_sub(1)
+            .and_then(|prev| self.positions.get(prev))
+            .copied()
+            .unwrap_or(pos);
+        Some((prev, pos))
+    }
+
+    /// A `LEVEL=` line followed by one `x y` line per step.
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text.lines().map(str::trim).filter(|line| !line.is_empty());
+        let level = lines
+            .next()
+            .and_then(|line| line.strip_prefix("LEVEL="))
+            .ok_or("missing LEVEL line")?
+            .trim()
+            .to_string();
+        let positions = lines
+            .map(|line| {
+                let (x, y) = line
+                    .split_once(' ')
+                    .ok_or_else(|| format!("malformed line '{line}'"))?;
+                match (x.parse(), y.parse()) {
+                    (Ok(x), Ok(y)) => Ok(vec2(x, y)),
+                    _ => Err(format!("malformed line '{line}'")),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { level, positions })
+    }
+
+    fn to_text(&self) -> String {
+        let mut out = format!("LEVEL={}\n", self.level);
+        for pos in &self.positions {
+            out.push_str(&format!("{} {}\n", pos.x, pos.y));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ghost_round_trips_through_text() {
+        let ghost = Ghost::new(
+            "levels/level1.txt".to_string(),
+            vec![vec2(64.0, 96.5), vec2(66.25, 96.5)],
+        );
+        assert_eq!(Ghost::parse(&ghost.to_text()), Ok(ghost.clone()));
+        assert_eq!(ghost.frame(1), Some((vec2(64.0, 96.5), vec2(66.25, 96.5))));
+        assert_eq!(ghost.frame(2), None);
+        assert!(Ghost::parse("1 2\n").is_err());
+    }
+}
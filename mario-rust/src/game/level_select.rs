@@ -0,0 +1,200 @@
+use macroquad::prelude::*;
+
+use super::{settings::find_levels, world::LevelMeta};
+
+/// Level list for builds that can't read directories, such as the web one:
+/// one file name per line, relative to `assets/levels`.
+pub const MANIFEST_PATH: &str = "levels/manifest.txt";
+const COLUMNS: usize = 3;
+const CELL_SIZE: Vec2 = vec2(260.0, 64.0);
+const CELL_GAP: f32 = 16.0;
+
+/// A level the stage menu can start.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LevelEntry {
+    /// Level file, relative to the assets folder.
+    pub path: String,
+    /// `NAME` from the level's header, or the file name if it has none.
+    pub name: String,
+}
+
+/// Every level under `assets/levels`, read from the directory where that
+/// works and from `MANIFEST_PATH` otherwise.
+pub async fn discover_levels() -> Vec<LevelEntry> {
+    let mut paths = find_levels();
+    if paths.is_empty() {
+        paths = match load_string(MANIFEST_PATH).await {
+            Ok(text) => parse_manifest(&text),
+            Err(error) => {
+                eprintln!("Level manifest error: {error}");
+                Vec::new()
+            }
+        };
+    }
+
+    let mut levels = Vec::with_capacity(paths.len());
+    for path in paths {
+        let name = match load_string(&path).await {
+            Ok(text) => LevelMeta::parse(&text).0.name,
+            Err(error) => {
+                eprintln!("Skipping level {path}: {error}");
+                continue;
+            }
+        };
+        let name = if name.is_empty() {
+            file_stem(&path).to_string()
+        } else {
+            name
+        };
+        levels.push(LevelEntry { path, name });
+    }
+    levels
+}
+
+/// Level paths listed in a manifest, skipping blank lines and `#` comments.
+fn parse_manifest(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|name| format!("levels/{name}"))
+        .collect()
+}
+
+fn file_stem(path: &str) -> &str {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    name.strip_suffix(".txt").unwrap_or(name)
+}
+
+/// What the game should do after a stage menu frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LevelSelectAction {
+    None,
+    /// Start a new run on the level at this path.
+    Play(String),
+    Exit,
+}
+
+/// Stage menu: a grid of level names picked with the arrow keys and Enter.
+pub struct LevelSelect {
+    levels: Vec<LevelEntry>,
+    selected: usize,
+}
+
+impl LevelSelect {
+    /// Opens with `current` (a level path) highlighted if it's listed.
+    pub fn new(levels: Vec<LevelEntry>, current: &str) -> Self {
+        let selected = levels
+            .iter()
+            .position(|level| level.path == current)
+            .unwrap_or(0);
+        Self { levels, selected }
+    }
+
+    pub fn update(&mut self) -> LevelSelectAction {
+        if is_key_pressed(KeyCode::Escape) {
+            return LevelSelectAction::Exit;
+        }
+        if self.levels.is_empty() {
+            return LevelSelectAction::None;
+        }
+        if is_key_pressed(KeyCode::Enter) {
+            return LevelSelectAction::Play(self.levels[self.selected].path.clone());
+        }
+
+        let step: isize = if is_key_pressed(KeyCode::Right) {
+            1
+        } else if is_key_pressed(KeyCode::Left) {
+            -1
+        } else if is_key_pressed(KeyCode::Down) {
+            COLUMNS as isize
+        } else if is_key_pressed(KeyCode::Up) {
+            -(COLUMNS as isize)
+        } else {
+            0
+        };
+        self.select_by(step);
+        LevelSelectAction::None
+    }
+
+    /// Moves the highlight `step` cells through the grid, wrapping around.
+    fn select_by(&mut self, step: isize) {
+        let len = self.levels.len() as isize;
+        if len > 0 {
+            self.selected = (self.selected as isize + step).rem_euclid(len) as usize;
+        }
+    }
+
+    pub fn draw(&self) {
+        let title = "Select a Stage";
+        let title_dim = measure_text(title, None, 48, 1.0);
+        let center_x = screen_width() * 0.5;
+        let top = screen_height() * 0.2;
+        draw_text(title, center_x - title_dim.width * 0.5, top, 48.0, BLACK);
+
+        if self.levels.is_empty() {
+            let text = "No levels found";
+            let dim = measure_text(text, None, 28, 1.0);
+            draw_text(text, center_x - dim.width * 0.5, top + 80.0, 28.0, DARKGRAY);
+        }
+
+        let columns = COLUMNS.min(self.levels.len()).max(1) as f32;
+        let grid_w = columns * CELL_SIZE.x + (columns - 1.0) * CELL_GAP;
+        let left = center_x - grid_w * 0.5;
+        for (idx, level) in self.levels.iter().enumerate() {
+            let col = (idx % COLUMNS) as f32;
+            let row = (idx / COLUMNS) as f32;
+            let x = left + col * (CELL_SIZE.x + CELL_GAP);
+            let y = top + 40.0 + row * (CELL_SIZE.y + CELL_GAP);
+            let selected = idx == self.selected;
+            let fill = if selected {
+                Color::new(1.0, 1.0, 1.0, 0.85)
+            } else {
+                Color::new(1.0, 1.0, 1.0, 0.45)
+            };
+            draw_rectangle(x, y, CELL_SIZE.x, CELL_SIZE.y, fill);
+            if selected {
+                draw_rectangle_lines(x, y, CELL_SIZE.x, CELL_SIZE.y, 3.0, MAROON);
+            }
+            let color = if selected { MAROON } else { DARKGRAY };
+            draw_text(&format!("{}", idx + 1), x + 12.0, y + 26.0, 22.0, color);
+            draw_text(&level.name, x + 12.0, y + 52.0, 26.0, color);
+        }
+
+        let hint = "Arrows: select, Enter: play, Esc: back";
+        let hint_dim = measure_text(hint, None, 20, 1.0);
+        draw_text(
+            hint,
+            center_x - hint_dim.width * 0.5,
+            screen_height() - 32.0,
+            20.0,
+            DARKGRAY,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_lists_levels_and_grid_wraps() {
+        let paths = parse_manifest("# stages\nlevel1.txt\n\n  level2.txt \n");
+        assert_eq!(paths, ["levels/level1.txt", "levels/level2.txt"]);
+        assert_eq!(file_stem(&paths[1]), "level2");
+
+        let levels = (1..=5)
+            .map(|n| LevelEntry {
+                path: format!("levels/level{n}.txt"),
+                name: format!("Stage {n}"),
+            })
+            .collect();
+        let mut menu = LevelSelect::new(levels, "levels/level2.txt");
+        assert_eq!(menu.selected, 1);
+        menu.select_by(COLUMNS as isize);
+        assert_eq!(menu.selected, 4);
+        menu.select_by(1);
+        assert_eq!(menu.selected, 0);
+        menu.select_by(-(COLUMNS as isize));
+        assert_eq!(menu.selected, 2);
+    }
+}
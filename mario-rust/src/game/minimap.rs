@@ -0,0 +1,52 @@
+use macroquad::prelude::*;
+
+use super::{world::World, Config};
+
+const MAX_SIZE: Vec2 = vec2(220.0, 90.0);
+const MARGIN: f32 = 16.0;
+const TOP: f32 = 44.0;
+
+/// Draws a scaled-down overview of the level in the top-right corner of the
+/// screen. Call with the default (screen-space) camera active.
+///
+/// The tile grid is sampled at most once per minimap pixel, so huge levels
+/// cost the same to draw as ones that fit the box exactly.
+pub fn draw_minimap(world: &World, player: Vec2, enemies: &[Vec2], config: &Config) {
+    let world_size = vec2(world.width as f32, world.height as f32) * config.tile_size;
+    let scale = (MAX_SIZE.x / world_size.x).min(MAX_SIZE.y / world_size.y);
+    let size = world_size * scale;
+    let origin = vec2(screen_width() - size.x - MARGIN, TOP);
+    let to_map = |pos: Vec2| origin + pos * scale;
+
+    draw_rectangle(
+        origin.x - 2.0,
+        origin.y - 2.0,
+        size.x + 4.0,
+        size.y + 4.0,
+        Color::new(0.0, 0.0, 0.0, 0.45),
+    );
+
+    let tile_px = config.tile_size * scale;
+    let step = (1.0 / tile_px).ceil().max(1.0) as usize;
+    let cell = tile_px * step as f32;
+    let ground = Color::new(0.8, 0.8, 0.8, 0.9);
+    for row in (0..world.height).step_by(step) {
+        for col in (0..world.width).step_by(step) {
+            if world.is_solid_tile(col as i32, row as i32) {
+                let pos = to_map(vec2(col as f32, row as f32) * config.tile_size);
+                draw_rectangle(pos.x, pos.y, cell, cell, ground);
+            }
+        }
+    }
+
+    let goal = to_map(world.goal_trigger_rect(config).center());
+    draw_rectangle(goal.x - 1.5, goal.y - 4.0, 3.0, 8.0, GREEN);
+
+    for &enemy in enemies {
+        let pos = to_map(enemy);
+        draw_circle(pos.x, pos.y, 2.0, RED);
+    }
+
+    let pos = to_map(player);
+    draw_circle(pos.x, pos.y, 3.0, YELLOW);
+}
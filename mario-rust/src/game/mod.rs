@@ -1,19 +1,97 @@
 mod audio;
 mod background;
+mod camera;
+mod difficulty;
+mod editor;
 mod enemy;
+mod fireball;
+mod floating_text;
+mod gamepad;
+mod ghost;
+mod level_select;
+mod minimap;
+mod mushroom;
+mod particles;
 mod physics;
+mod platform;
 mod player;
+mod projectile;
+mod replay;
+mod results;
+mod rng;
+mod save;
+mod settings;
+mod speedrun;
 mod sprites;
+mod theme;
+mod touch;
+mod validate;
 mod world;
 
 use macroquad::file::set_pc_assets_folder;
 use macroquad::prelude::*;
 
+pub use self::audio::AudioConfig;
 use self::audio::Sfx;
+use self::background::BackgroundLayers;
+use self::camera::{FollowCamera, Letterbox};
+use self::difficulty::Difficulty;
+use self::editor::{Editor, EditorAction};
 use self::enemy::Enemy;
+use self::fireball::Fireball;
+use self::floating_text::FloatingText;
+use self::gamepad::Gamepads;
+use self::ghost::{Ghost, GHOST_PATH};
+use self::level_select::{LevelEntry, LevelSelect, LevelSelectAction};
+use self::mushroom::Mushroom;
+use self::particles::Particle;
+use self::platform::MovingPlatform;
 use self::player::Player;
-use self::sprites::Sprites;
-use self::world::World;
+use self::projectile::EnemyProjectile;
+use self::results::LevelStats;
+use self::rng::Rng;
+use self::save::{SaveState, SAVE_PATH};
+use self::settings::{Settings, SettingsAction, SettingsMenu, SETTINGS_PATH};
+use self::speedrun::{SpeedrunTimer, BEST_TIME_PATH};
+use self::sprites::{PlayerMotion, Sprites, PLAYER_PALETTES};
+use self::touch::TouchZones;
+use self::world::{BlockPayload, BonkTarget, World};
+
+const ASSETS_DIR: &str = "assets";
+const LEVEL_PATH: &str = "levels/level1.txt";
+
+const VOLUME_STEP: f32 = 0.1;
+const TIME_SCALE_STEP: f32 = 0.25;
+const MAX_TIME_SCALE: f32 = 4.0;
+/// Fixed resolution the world is rendered at in pixel-perfect mode.
+const PIXEL_VIEW_SIZE: (u32, u32) = (960, 540);
+/// Weight of the newest frame in the debug overlay's running FPS average.
+const FPS_SMOOTHING: f32 = 0.05;
+
+/// Seed every run starts from, so identical inputs replay identically.
+const RNG_SEED: u32 = 0x5EED_1234;
+
+const COIN_COLOR: Color = Color::new(0.95, 0.8, 0.2, 1.0);
+const STOMP_COLOR: Color = Color::new(0.55, 0.35, 0.2, 1.0);
+const SPLASH_COLOR: Color = Color::new(0.6, 0.8, 1.0, 0.9);
+
+// Screen shake kicks, in pixels before `Config::shake_intensity` scaling.
+const SHAKE_STOMP: f32 = 5.0;
+const SHAKE_DEATH: f32 = 12.0;
+const SHAKE_LANDING: f32 = 4.0;
+
+/// How far below an enemy's top the player's feet may be and still count as
+/// a stomp rather than a side hit.
+const STOMP_TOLERANCE: f32 = 6.0;
+
+/// Highest doubling applied to chained kills (100 << 6 = 6400 points).
+const MAX_COMBO_STEP: u32 = 6;
+/// Flagpole bonus by how far up the pole the player grabbed it, as
+/// (minimum height fraction, points), highest first.
+const FLAGPOLE_BONUSES: [(f32, u32); 4] = [(0.9, 5000), (0.7, 2000), (0.45, 800), (0.2, 400)];
+const FLAGPOLE_MIN_BONUS: u32 = 100;
+/// Points for defeating a level's boss.
+const BOSS_BONUS: u32 = 5000;
 
 #[derive(Clone, Copy)]
 pub struct Config {
@@ -21,22 +99,138 @@ pub struct Config {
     pub max_frame_time: f32,
     pub tile_size: f32,
     pub player_size: Vec2,
+    pub crouch_height: f32,
+    pub powered_height: f32,
+    pub crouch_speed_scale: f32,
     pub move_speed: f32,
     pub move_accel: f32,
     pub move_decel: f32,
+    pub run_speed: f32,
+    pub run_accel: f32,
+    pub run_release_decel: f32,
+    pub ice_accel: f32,
+    pub ice_decel: f32,
+    pub air_accel: f32,
+    /// Air acceleration while run is held; defaults to `run_accel`.
+    pub air_run_accel: f32,
+    pub air_decel: f32,
     pub gravity: f32,
+    /// `1.0` pulls down, `-1.0` pulls up so the ceiling becomes the floor.
+    pub gravity_dir: f32,
     pub terminal_velocity: f32,
     pub jump_speed: f32,
+    pub jump_speed_max: f32,
+    pub max_air_jumps: u32,
+    pub air_jump_speed: f32,
     pub coyote_time: f32,
     pub jump_buffer_time: f32,
     pub jump_cut_multiplier: f32,
+    /// Widest head-on-corner overlap, in pixels, that a jump slides past.
+    pub corner_correction: f32,
+    pub water_gravity_scale: f32,
+    pub water_sink_speed: f32,
+    pub water_move_scale: f32,
+    pub swim_stroke_speed: f32,
+    pub wall_slide_speed: f32,
+    pub wall_jump_speed: Vec2,
+    pub wall_jump_lock_time: f32,
+    pub climb_speed: f32,
+    /// Sideways speed multiplier while on a ladder.
+    pub ladder_move_scale: f32,
+    pub dash_speed: f32,
+    pub dash_duration: f32,
+    pub dash_cooldown: f32,
+    /// How early a dash may be pressed before the cooldown ends.
+    pub dash_buffer_time: f32,
     pub stomp_bounce: f32,
+    /// Upward speed a spring launches the player or an enemy with.
+    pub spring_speed: f32,
+    pub spring_anim_time: f32,
     pub enemy_size: Vec2,
     pub enemy_speed: f32,
+    pub flyer_speed: f32,
+    pub flyer_amplitude: f32,
+    pub flyer_frequency: f32,
+    pub shell_speed: f32,
+    /// Upward speed of a hopping enemy's jump.
+    pub hop_speed: f32,
+    /// Seconds a hopping enemy walks on the ground between hops.
+    pub hop_interval: f32,
+    pub shell_contact_grace: f32,
+    pub enemy_squash_time: f32,
+    pub boss_size: Vec2,
+    pub boss_speed: f32,
+    /// Speed multiplier once a boss is down to half health.
+    pub boss_enraged_speed_scale: f32,
+    pub boss_health: u32,
+    /// Seconds a boss ignores hits after taking one.
+    pub boss_hurt_time: f32,
     pub mushroom_size: Vec2,
+    pub mushroom_speed: f32,
+    /// Keeps mushrooms where they appear instead of letting them move.
+    pub static_mushrooms: bool,
+    /// Pulls nearby coins toward the player while a star is active.
+    pub coin_magnet: bool,
+    pub magnet_radius: f32,
+    /// Top speed of a coin drifting toward the player.
+    pub magnet_speed: f32,
+    pub fireball_size: f32,
+    pub fireball_speed: f32,
+    pub fireball_bounce: f32,
+    pub fireball_lifetime: f32,
+    pub max_fireballs: usize,
+    /// Seconds between a turret's shots.
+    pub turret_fire_interval: f32,
+    pub turret_shot_speed: f32,
+    pub turret_shot_size: f32,
+    pub turret_shot_lifetime: f32,
+    pub platform_size: Vec2,
+    pub platform_speed: f32,
+    pub conveyor_speed: f32,
     pub hurt_invuln_time: f32,
+    pub star_duration: f32,
+    /// Seconds before a star wears off when the player starts flashing faster.
+    pub star_warning_time: f32,
     pub hurt_knockback_x: f32,
     pub hurt_knockback_y: f32,
+    pub starting_lives: u32,
+    pub coins_per_life: u32,
+    pub sfx_volume: f32,
+    pub music_volume: f32,
+    pub shake_intensity: f32,
+    pub shake_decay: f32,
+    pub hard_landing_speed: f32,
+    /// How far the player sprite squashes on a hard landing and stretches
+    /// on a jump, as a fraction of its size. Visual only.
+    pub squash_stretch: f32,
+    /// Seconds the squash or stretch takes to ease back to normal.
+    pub squash_stretch_time: f32,
+    pub camera_lerp: f32,
+    pub camera_lookahead: f32,
+    pub camera_dead_zone: f32,
+    /// How far the player must be from a dead enemy's spawn before it comes
+    /// back, on levels with `RESPAWN_ENEMIES=on`.
+    pub respawn_distance: f32,
+    /// Idle seconds before a `HINTS=on` level points out the goal.
+    pub hint_idle_time: f32,
+    /// World units shown top to bottom, whatever the window size.
+    pub view_height: f32,
+    /// Narrowest and widest visible view; windows beyond these are
+    /// letterboxed.
+    pub min_view_aspect: f32,
+    pub max_view_aspect: f32,
+    pub max_particles: usize,
+    /// Enemies farther than this from the camera focus are neither updated
+    /// nor drawn, so huge levels cost about the same per step as small ones.
+    pub active_radius: f32,
+    pub goal_slide_time: f32,
+    /// Seconds spent sinking into a warp pipe while the screen fades out.
+    pub warp_sink_time: f32,
+    /// Seconds the screen takes to fade back in at the destination pipe.
+    pub warp_fade_in_time: f32,
+    /// Seconds each half of the fade around starting a level or dying
+    /// takes. Zero swaps instantly; Enter skips a fade in progress.
+    pub fade_time: f32,
 }
 
 impl Default for Config {
@@ -46,125 +240,619 @@ impl Default for Config {
             max_frame_time: 0.25,
             tile_size: 32.0,
             player_size: vec2(22.0, 28.0),
+            crouch_height: 18.0,
+            powered_height: 40.0,
+            crouch_speed_scale: 0.5,
             move_speed: 220.0,
             move_accel: 1600.0,
             move_decel: 2000.0,
+            run_speed: 330.0,
+            run_accel: 2000.0,
+            run_release_decel: 450.0,
+            ice_accel: 1000.0,
+            ice_decel: 240.0,
+            air_accel: 1600.0,
+            air_run_accel: 2000.0,
+            air_decel: 2000.0,
             gravity: 1200.0,
+            gravity_dir: 1.0,
             terminal_velocity: 780.0,
             jump_speed: 420.0,
+            jump_speed_max: 470.0,
+            max_air_jumps: 0,
+            air_jump_speed: 380.0,
             coyote_time: 0.1,
             jump_buffer_time: 0.12,
             jump_cut_multiplier: 0.5,
+            corner_correction: 4.0,
+            water_gravity_scale: 0.3,
+            water_sink_speed: 110.0,
+            water_move_scale: 0.6,
+            swim_stroke_speed: 210.0,
+            wall_slide_speed: 90.0,
+            wall_jump_speed: vec2(260.0, 400.0),
+            wall_jump_lock_time: 0.15,
+            climb_speed: 120.0,
+            ladder_move_scale: 0.35,
+            dash_speed: 520.0,
+            dash_duration: 0.18,
+            dash_cooldown: 0.6,
+            dash_buffer_time: 0.12,
             stomp_bounce: 320.0,
+            spring_speed: 760.0,
+            spring_anim_time: 0.3,
             enemy_size: vec2(24.0, 20.0),
             enemy_speed: 65.0,
+            flyer_speed: 50.0,
+            flyer_amplitude: 24.0,
+            flyer_frequency: 0.8,
+            shell_speed: 340.0,
+            hop_speed: 420.0,
+            hop_interval: 1.5,
+            shell_contact_grace: 0.2,
+            enemy_squash_time: 0.35,
+            boss_size: vec2(56.0, 46.0),
+            boss_speed: 70.0,
+            boss_enraged_speed_scale: 1.8,
+            boss_health: 5,
+            boss_hurt_time: 0.8,
             mushroom_size: vec2(24.0, 22.0),
+            mushroom_speed: 80.0,
+            static_mushrooms: false,
+            coin_magnet: false,
+            magnet_radius: 120.0,
+            magnet_speed: 300.0,
+            fireball_size: 10.0,
+            fireball_speed: 360.0,
+            fireball_bounce: 260.0,
+            fireball_lifetime: 2.0,
+            max_fireballs: 2,
+            turret_fire_interval: 2.5,
+            turret_shot_speed: 140.0,
+            turret_shot_size: 12.0,
+            turret_shot_lifetime: 4.0,
+            platform_size: vec2(64.0, 14.0),
+            platform_speed: 60.0,
+            conveyor_speed: 90.0,
             hurt_invuln_time: 0.75,
+            star_duration: 10.0,
+            star_warning_time: 2.0,
             hurt_knockback_x: 200.0,
             hurt_knockback_y: 260.0,
+            starting_lives: 3,
+            coins_per_life: 100,
+            sfx_volume: 0.45,
+            music_volume: 0.22,
+            shake_intensity: 1.0,
+            shake_decay: 8.0,
+            hard_landing_speed: 600.0,
+            squash_stretch: 0.25,
+            squash_stretch_time: 0.15,
+            camera_lerp: 6.0,
+            camera_lookahead: 64.0,
+            camera_dead_zone: 120.0,
+            respawn_distance: 640.0,
+            hint_idle_time: 3.0,
+            view_height: 540.0,
+            min_view_aspect: 4.0 / 3.0,
+            max_view_aspect: 16.0 / 9.0,
+            goal_slide_time: 1.0,
+            warp_sink_time: 0.5,
+            warp_fade_in_time: 0.3,
+            fade_time: 0.25,
+            max_particles: 256,
+            active_radius: 1200.0,
         }
     }
 }
 
 pub struct Game {
     state: GameState,
+    editor: Option<Editor>,
+    settings: Settings,
+    settings_menu: Option<SettingsMenu>,
+    /// Levels offered on the stage menu, found once at startup.
+    levels: Vec<LevelEntry>,
+    level_select: Option<LevelSelect>,
+    /// Level picked on the stage menu, loaded between frames by
+    /// `load_pending_level`.
+    pending_level: Option<String>,
+    /// Run saved on the last quit to the title, offered as "Continue".
+    saved_run: Option<SaveState>,
+    speedrun: SpeedrunTimer,
+    /// Highlighted entry of `TITLE_OPTIONS`.
+    title_selection: usize,
+    /// Set by the title menu's Quit; `main` stops its loop on it.
+    quit_requested: bool,
+    /// Time, coins, stomps and score for the results screen.
+    stats: LevelStats,
+    /// Deaths respawn the player at the last checkpoint without costing a
+    /// life or resetting the level, and finishes set no records.
+    practice_mode: bool,
+    /// Fastest finish of a level, raced as a translucent ghost.
+    ghost: Option<Ghost>,
+    /// Player position on every step of the current run so far.
+    ghost_trail: Vec<Vec2>,
+    /// `config` before difficulty scaling and level overrides.
+    base_config: Config,
+    difficulty: Difficulty,
     accumulator: f32,
+    /// Multiplies frame time before it feeds the fixed-step accumulator:
+    /// 0 freezes the simulation, 2 runs it at double speed.
+    time_scale: f32,
+    debug_overlay: bool,
+    show_colliders: bool,
+    steps_last_frame: u32,
+    smoothed_fps: f32,
     config: Config,
     sfx: Sfx,
-    sprites: Sprites,
+    /// `None` when running headless; only drawing needs textures.
+    sprites: Option<Sprites>,
+    /// Offscreen target the world is drawn into when pixel-perfect
+    /// rendering is on; `None` stretches the world to the window.
+    pixel_target: Option<RenderTarget>,
+    /// Draws on-screen move and jump buttons and reads touches and mouse
+    /// clicks on them. On by default on phones and tablets.
+    touch_controls: bool,
+    /// On-screen buttons held last frame, to turn holds into jump presses.
+    touch_zones: TouchZones,
+    /// The first connected controller, polled next to the keyboard.
+    gamepads: Gamepads,
+    background: BackgroundLayers,
     world: World,
     player: Player,
     enemies: Vec<Enemy>,
+    fireballs: Vec<Fireball>,
+    enemy_projectiles: Vec<EnemyProjectile>,
     coin_spawns: Vec<Vec2>,
-    mushroom_spawns: Vec<Vec2>,
+    star_spawns: Vec<Vec2>,
+    mushroom_spawns: Vec<Mushroom>,
+    brick_spawns: Vec<Rect>,
+    platform_spawns: Vec<MovingPlatform>,
+    active_checkpoint: Option<Vec2>,
+    checkpoint_coins: Vec<Vec2>,
+    checkpoint_enemies_alive: Vec<bool>,
+    /// Question blocks, bricks and power-ups as of the active checkpoint.
+    checkpoint_blocks_used: Vec<bool>,
+    checkpoint_bricks: Vec<Rect>,
+    checkpoint_mushrooms: Vec<Mushroom>,
+    checkpoint_stars: Vec<Vec2>,
+    /// Coins collected since the level (re)started, for `COINS_REQUIRED`.
+    level_coins: u32,
+    /// `level_coins` as of the active checkpoint.
+    checkpoint_level_coins: u32,
+    particles: Vec<Particle>,
+    floating_texts: Vec<FloatingText>,
+    camera: FollowCamera,
+    /// Seconds of gameplay simulated so far; drives purely visual animation.
+    world_time: f32,
+    show_minimap: bool,
+    screen_shake: f32,
+    shake_offset: Vec2,
     score: u32,
     high_score: u32,
+    stomp_combo: u32,
+    /// Seconds since the player last moved or jumped, for `HINTS=on` levels.
+    idle_time: f32,
+    goal_slide: GoalSlide,
+    warp: WarpTransition,
+    /// Fade covering a level start or a death, if one is running.
+    transition: Option<Transition>,
+    /// Whether crouch was held last step, so warping needs a fresh press.
+    crouch_was_held: bool,
+    coins: u32,
+    lives: u32,
     input: InputState,
+    record_inputs: bool,
+    recorded_inputs: Vec<InputState>,
+    playback: Option<std::vec::IntoIter<InputState>>,
+    rng: Rng,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum GameState {
     Title,
     Playing,
+    GoalSequence,
+    Warping,
     LevelComplete,
+    Editor,
+    Settings,
+    LevelSelect,
+}
+
+/// The player sliding down the flagpole between touching the goal and the
+/// level complete screen.
+#[derive(Clone, Copy, Debug, Default)]
+struct GoalSlide {
+    timer: f32,
+    from_y: f32,
+    to_y: f32,
+}
+
+/// The player going down a warp pipe: they sink into it while the screen
+/// fades out, reappear on top of the linked pipe, and the screen fades back
+/// in.
+#[derive(Clone, Copy, Debug, Default)]
+struct WarpTransition {
+    timer: f32,
+    /// Index of the destination pipe in `World::pipes`.
+    dest: usize,
+    from_y: f32,
+    arrived: bool,
+}
+
+/// Entries of the title screen menu, top to bottom.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TitleOption {
+    Start,
+    LevelSelect,
+    Settings,
+    Quit,
+}
+
+const TITLE_OPTIONS: [TitleOption; 4] = [
+    TitleOption::Start,
+    TitleOption::LevelSelect,
+    TitleOption::Settings,
+    TitleOption::Quit,
+];
+
+impl TitleOption {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Start => "Start",
+            Self::LevelSelect => "Level Select",
+            Self::Settings => "Settings",
+            Self::Quit => "Quit",
+        }
+    }
+}
+
+/// The state change a fade makes once the screen is fully black.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TransitionSwap {
+    /// Leave the title screen for a fresh run.
+    StartRun,
+    /// Respawn, lose a life or start over after the player died.
+    Death,
+    /// Replay the level from the level complete screen.
+    Restart,
+}
+
+/// A fade to black and back around a state change. Gameplay is frozen until
+/// it ends; the swap happens at the midpoint.
+#[derive(Clone, Copy, Debug)]
+struct Transition {
+    timer: f32,
+    /// Still to run while fading out; `None` once fading back in.
+    pending: Option<TransitionSwap>,
+}
+
+impl Transition {
+    fn fade_in() -> Self {
+        Self {
+            timer: 0.0,
+            pending: None,
+        }
+    }
+
+    /// Opacity of the black overlay.
+    fn alpha(&self, fade_time: f32) -> f32 {
+        let t = (self.timer / fade_time).clamp(0.0, 1.0);
+        if self.pending.is_some() {
+            t
+        } else {
+            1.0 - t
+        }
+    }
 }
 
-#[derive(Clone, Copy, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct InputState {
     pub move_x: f32,
     pub jump_pressed: bool,
     pub jump_released: bool,
+    pub dash_pressed: bool,
+    pub fire_pressed: bool,
+    pub crouch_held: bool,
+    /// Climbs ladders. Up also jumps, which `jump_pressed` carries.
+    pub up_held: bool,
+    pub run_held: bool,
     pub start_pressed: bool,
     pub restart_pressed: bool,
     pub quit_pressed: bool,
+    /// Menu navigation; ignored during gameplay.
+    pub menu_up_pressed: bool,
+    pub menu_down_pressed: bool,
 }
 
 impl Game {
-    pub async fn new() -> Self {
-        set_pc_assets_folder("assets");
+    pub async fn new(audio: AudioConfig) -> Self {
+        set_pc_assets_folder(ASSETS_DIR);
         let config = Config::default();
-        let sfx = Sfx::new().await;
+        let settings = Settings::load(SETTINGS_PATH);
+        // A different soundtrack each launch; it has no effect on gameplay,
+        // so replays stay deterministic.
+        let sfx = Sfx::new(miniquad::date::now().to_bits() as u32, &audio).await;
         let sprites = Sprites::new();
-        let world = World::load("levels/level1.txt", &config).await;
+        let background = BackgroundLayers::load().await;
+        let world = World::load(&settings.level, &config).await;
+        if settings.fullscreen {
+            set_fullscreen(true);
+        }
+        let player_palette = settings.player_palette;
+        let mut game = Self::with_world(config, world, sfx, Some(sprites), background, settings);
+        game.apply_player_palette(player_palette);
+        game.gamepads = Gamepads::connect();
+        game.saved_run = SaveState::load(SAVE_PATH);
+        game.speedrun = SpeedrunTimer::load(BEST_TIME_PATH);
+        game.ghost = Ghost::load(GHOST_PATH);
+        game.levels = level_select::discover_levels().await;
+        game
+    }
+
+    /// Builds a game from an in-memory level without loading any files,
+    /// textures or sounds, for stepping the simulation in tests.
+    #[cfg(test)]
+    pub fn headless(level: &str, config: Config) -> Result<Self, String> {
+        let world = World::from_ascii(level, &config)?;
+        Ok(Self::with_world(
+            config,
+            world,
+            Sfx::silent(),
+            None,
+            BackgroundLayers::default(),
+            Settings::default(),
+        ))
+    }
+
+    fn with_world(
+        base_config: Config,
+        world: World,
+        mut sfx: Sfx,
+        sprites: Option<Sprites>,
+        background: BackgroundLayers,
+        settings: Settings,
+    ) -> Self {
+        let difficulty = settings.difficulty;
+        let config = level_config(&base_config, difficulty, &world);
+        sfx.set_volume(config.sfx_volume);
+        sfx.set_music_volume(config.music_volume);
+        sfx.set_master_volume(settings.master_volume);
         let player = Player::new(world.player_spawn, &config);
-        let enemies = world
-            .enemy_spawns
-            .iter()
-            .copied()
-            .map(|spawn| Enemy::new(spawn, &world, &config))
-            .collect();
+        let camera = FollowCamera::new(player.center());
+        let enemies = spawn_enemies(&world, &config);
         let coin_spawns = world.coins.clone();
+        let star_spawns = world.stars.clone();
         let mushroom_spawns = world.mushrooms.clone();
+        let brick_spawns = world.bricks.clone();
+        let platform_spawns = world.platforms.clone();
 
         Self {
             state: GameState::Title,
+            editor: None,
+            settings,
+            settings_menu: None,
+            levels: Vec::new(),
+            level_select: None,
+            pending_level: None,
+            saved_run: None,
+            speedrun: SpeedrunTimer::default(),
+            title_selection: 0,
+            quit_requested: false,
+            stats: LevelStats::default(),
+            practice_mode: false,
+            ghost: None,
+            ghost_trail: Vec::new(),
+            base_config,
+            difficulty,
             accumulator: 0.0,
+            time_scale: 1.0,
+            debug_overlay: false,
+            show_colliders: false,
+            steps_last_frame: 0,
+            smoothed_fps: 60.0,
             config,
             sfx,
             sprites,
+            pixel_target: None,
+            touch_controls: cfg!(any(target_os = "android", target_os = "ios")),
+            touch_zones: TouchZones::default(),
+            gamepads: Gamepads::default(),
+            background,
             world,
             player,
             enemies,
+            fireballs: Vec::with_capacity(config.max_fireballs),
+            enemy_projectiles: Vec::new(),
             coin_spawns,
+            star_spawns,
             mushroom_spawns,
+            brick_spawns,
+            platform_spawns,
+            active_checkpoint: None,
+            checkpoint_coins: Vec::new(),
+            checkpoint_blocks_used: Vec::new(),
+            checkpoint_bricks: Vec::new(),
+            checkpoint_mushrooms: Vec::new(),
+            checkpoint_stars: Vec::new(),
+            checkpoint_enemies_alive: Vec::new(),
+            level_coins: 0,
+            checkpoint_level_coins: 0,
+            particles: Vec::with_capacity(config.max_particles),
+            floating_texts: Vec::new(),
+            camera,
+            world_time: 0.0,
+            show_minimap: false,
+            screen_shake: 0.0,
+            shake_offset: Vec2::ZERO,
             score: 0,
             high_score: 0,
+            stomp_combo: 0,
+            idle_time: 0.0,
+            goal_slide: GoalSlide::default(),
+            warp: WarpTransition::default(),
+            transition: None,
+            crouch_was_held: false,
+            coins: 0,
+            lives: config.starting_lives,
             input: InputState::default(),
+            record_inputs: false,
+            recorded_inputs: Vec::new(),
+            playback: None,
+            rng: Rng::new(RNG_SEED),
         }
     }
 
     pub fn update(&mut self, frame_dt: f32) {
         self.capture_input();
-        self.accumulator += frame_dt.min(self.config.max_frame_time);
+        if self.state == GameState::Editor {
+            self.update_editor(frame_dt);
+            return;
+        }
+        if self.state == GameState::Settings {
+            self.update_settings();
+            return;
+        }
+        if self.state == GameState::LevelSelect {
+            self.update_level_select();
+            return;
+        }
+        self.accumulator += frame_dt.min(self.config.max_frame_time) * self.time_scale;
+        if frame_dt > 0.0 {
+            self.smoothed_fps += (1.0 / frame_dt - self.smoothed_fps) * FPS_SMOOTHING;
+        }
 
+        self.steps_last_frame = 0;
         while self.accumulator >= self.config.fixed_dt {
             let input = self.consume_fixed_input();
-            self.fixed_update(input);
+            self.step(input);
             self.accumulator -= self.config.fixed_dt;
+            self.steps_last_frame += 1;
+        }
+
+        // The simulation frozen with `[` doubles as a pause.
+        let paused = self.time_scale == 0.0;
+        if self.state == GameState::Playing && (paused || self.player.in_water()) {
+            self.sfx.start_music_muffled();
+        } else {
+            self.sfx.start_music_normal();
+        }
+    }
+
+    /// Advances the simulation by one fixed step. `live` is ignored while a
+    /// replay is playing back. Touches no window, input or audio state, so
+    /// tests can call it directly.
+    pub fn step(&mut self, live: InputState) {
+        let input = match &mut self.playback {
+            Some(playback) => match playback.next() {
+                Some(recorded) => recorded,
+                None => {
+                    self.playback = None;
+                    live
+                }
+            },
+            None => live,
+        };
+        if self.record_inputs {
+            self.recorded_inputs.push(input);
         }
+        self.fixed_update(input);
     }
 
     pub fn draw(&self) {
-        clear_background(Color::new(0.45, 0.75, 0.95, 1.0));
+        clear_background(self.world.meta.theme.palette().sky);
 
         match self.state {
-            GameState::Title => draw_title(),
-            GameState::Playing => self.draw_playing(),
+            GameState::Title => draw_title(
+                self.title_selection,
+                self.saved_run.is_some(),
+                self.speedrun.enabled,
+                self.practice_mode,
+            ),
+            GameState::Playing | GameState::GoalSequence | GameState::Warping => {
+                self.draw_playing()
+            }
             GameState::LevelComplete => self.draw_level_complete(),
+            GameState::Editor => {
+                if let Some(editor) = &self.editor {
+                    editor.draw(&self.config);
+                }
+            }
+            GameState::Settings => {
+                if let Some(menu) = &self.settings_menu {
+                    menu.draw();
+                }
+            }
+            GameState::LevelSelect => {
+                if let Some(menu) = &self.level_select {
+                    menu.draw();
+                }
+            }
+        }
+
+        if let Some(transition) = &self.transition {
+            set_default_camera();
+            draw_rectangle(
+                0.0,
+                0.0,
+                screen_width(),
+                screen_height(),
+                Color::new(0.0, 0.0, 0.0, transition.alpha(self.config.fade_time)),
+            );
+        }
+
+        if self.debug_overlay {
+            set_default_camera();
+            self.draw_debug_overlay();
+        }
+    }
+
+    fn draw_debug_overlay(&self) {
+        let lines = [
+            format!("FPS: {:.0}", self.smoothed_fps),
+            format!("Steps last frame: {}", self.steps_last_frame),
+            format!("Accumulator: {:.4}s", self.accumulator),
+            format!("Pos: ({:.1}, {:.1})", self.player.pos.x, self.player.pos.y),
+            format!("Vel: ({:.1}, {:.1})", self.player.vel.x, self.player.vel.y),
+            format!(
+                "Coyote: {:.3}s / {:.3}s",
+                self.player.coyote_time_left(),
+                self.config.coyote_time
+            ),
+            format!(
+                "Jump buffer: {:.3}s / {:.3}s",
+                self.player.jump_buffer_left(),
+                self.config.jump_buffer_time
+            ),
+        ];
+
+        let size = 20.0;
+        let line_h = 22.0;
+        let top = screen_height() - 16.0 - line_h * lines.len() as f32;
+        draw_rectangle(
+            8.0,
+            top - 18.0,
+            260.0,
+            line_h * lines.len() as f32 + 12.0,
+            Color::new(0.0, 0.0, 0.0, 0.55),
+        );
+        for (idx, line) in lines.iter().enumerate() {
+            draw_text(line, 16.0, top + line_h * idx as f32, size, WHITE);
         }
     }
 
     fn fixed_update(&mut self, input: InputState) {
+        if self.transition.is_some() {
+            self.update_transition(input.start_pressed);
+            return;
+        }
         match self.state {
-            GameState::Title => {
-                if input.start_pressed {
-                    self.state = GameState::Playing;
-                    self.restart_run();
-                    self.sfx.start_music();
-                }
-            }
+            GameState::Title => self.update_title_menu(input),
             GameState::Playing => {
                 if input.quit_pressed {
+                    self.save_run();
                     self.sfx.stop_music();
                     self.state = GameState::Title;
                     return;
@@ -172,66 +860,360 @@ impl Game {
 
                 if input.restart_pressed {
                     self.restart_run();
-                    self.sfx.start_music();
+                    self.sfx.start_music_for(self.world.meta.theme);
                     return;
                 }
 
+                self.speedrun.tick();
+                self.stats.steps += 1;
+                self.ghost_trail.push(self.player.pos);
+                self.sfx.update(self.config.fixed_dt);
+                self.world_time += self.config.fixed_dt;
+                self.world.update_platforms(self.config.fixed_dt);
+                self.world.update_springs(self.config.fixed_dt);
+                self.player.pos += self.world.platform_carry(self.player.rect());
+
                 let jumped =
                     self.player
                         .update(&input, &self.world, &self.config, self.config.fixed_dt);
                 if jumped {
                     self.sfx.play_jump();
                 }
+                if self.player.splashed() {
+                    particles::spawn_burst(
+                        &mut self.particles,
+                        self.config.max_particles,
+                        self.player.center(),
+                        SPLASH_COLOR,
+                        8,
+                        110.0,
+                    );
+                    self.sfx.play_splash();
+                }
+                if self.player.hit_ceiling() {
+                    self.handle_head_bonk();
+                }
+                if self.player.landing_speed() >= self.config.hard_landing_speed {
+                    self.add_shake(SHAKE_LANDING);
+                }
+                if self.player.on_ground {
+                    self.stomp_combo = 0;
+                }
+                if input.move_x.abs() > f32::EPSILON || input.jump_pressed {
+                    self.idle_time = 0.0;
+                } else {
+                    self.idle_time += self.config.fixed_dt;
+                }
+                let crouch_pressed = input.crouch_held && !self.crouch_was_held;
+                self.crouch_was_held = input.crouch_held;
+                if crouch_pressed && self.try_enter_pipe() {
+                    return;
+                }
 
+                let focus = self.camera.focus();
                 for enemy in &mut self.enemies {
+                    if !is_active(enemy, focus, &self.config) {
+                        continue;
+                    }
                     enemy.update(&self.world, &self.config, self.config.fixed_dt);
+                    if enemy.is_on_ground() {
+                        enemy.pos = self.world.conveyor_carry(
+                            enemy.pos,
+                            enemy.rect().size(),
+                            &self.config,
+                            self.config.fixed_dt,
+                        );
+                    }
+                }
+                if self.player.on_ground {
+                    self.player.pos = self.world.conveyor_carry(
+                        self.player.pos,
+                        self.player.size(),
+                        &self.config,
+                        self.config.fixed_dt,
+                    );
+                }
+
+                self.bounce_on_springs();
+                if self.world.meta.respawn_enemies {
+                    let player = self.player.center();
+                    for enemy in &mut self.enemies {
+                        enemy.respawn_if_far(player, &self.world, &self.config);
+                    }
                 }
+                self.world
+                    .update_mushrooms(&self.config, self.config.fixed_dt);
 
+                self.handle_shell_hits();
+                self.handle_enemy_bumps();
+                self.fire_turrets();
+
+                if input.fire_pressed {
+                    self.throw_fireball();
+                }
+                self.update_fireballs();
+
+                self.attract_coins();
                 if self.collect_coins() > 0 {
-                    self.sfx.play_coin();
+                    self.sfx.play_coin(self.player.center().x, &self.camera);
                 }
                 if self.collect_mushrooms() > 0 {
                     self.sfx.play_powerup();
                 }
+                if self.collect_stars() > 0 {
+                    self.sfx.play_star();
+                }
+                self.sfx.set_star_music(self.player.has_star());
                 self.handle_player_enemy_collisions();
+                self.update_enemy_projectiles();
+                self.check_hazards();
+                self.check_checkpoints();
+                self.check_boss_defeated();
                 self.check_goal();
                 self.check_fall_off();
+                particles::update(
+                    &mut self.particles,
+                    self.config.gravity,
+                    self.config.fixed_dt,
+                );
+                floating_text::update(&mut self.floating_texts, self.config.fixed_dt);
+                self.update_shake(self.config.fixed_dt);
+                self.camera.update(
+                    self.player.center(),
+                    self.player.facing_dir(),
+                    self.player.on_ground,
+                    &self.config,
+                    self.config.fixed_dt,
+                );
             }
+            GameState::GoalSequence => self.update_goal_slide(),
+            GameState::Warping => self.update_warp(),
             GameState::LevelComplete => {
+                let bonus = self.stats.take_bonus(self.config.fixed_dt);
+                self.add_score(bonus);
                 if input.quit_pressed {
+                    let bonus = self.stats.take_all_bonus();
+                    self.add_score(bonus);
                     self.sfx.stop_music();
                     self.state = GameState::Title;
                     return;
                 }
 
                 if input.restart_pressed {
+                    self.begin_transition(TransitionSwap::Restart);
+                }
+            }
+            GameState::Editor | GameState::Settings | GameState::LevelSelect => {}
+        }
+    }
+
+    /// Moves the title menu highlight and runs the highlighted entry on
+    /// Enter.
+    fn update_title_menu(&mut self, input: InputState) {
+        let len = TITLE_OPTIONS.len();
+        if input.menu_up_pressed {
+            self.title_selection = (self.title_selection + len - 1) % len;
+        }
+        if input.menu_down_pressed {
+            self.title_selection = (self.title_selection + 1) % len;
+        }
+        if !input.start_pressed {
+            return;
+        }
+        match TITLE_OPTIONS[self.title_selection] {
+            TitleOption::Start => self.begin_transition(TransitionSwap::StartRun),
+            TitleOption::LevelSelect => self.open_level_select(),
+            TitleOption::Settings => self.open_settings(),
+            TitleOption::Quit => self.quit_requested = true,
+        }
+    }
+
+    /// True once the player picked Quit on the title screen.
+    pub fn should_quit(&self) -> bool {
+        self.quit_requested
+    }
+
+    /// Writes out anything not yet on disk before the app exits: an input
+    /// recording still in progress, and a master volume changed with the
+    /// -/+ keys since the settings were last saved.
+    pub fn shutdown(&mut self) {
+        if self.record_inputs {
+            self.stop_recording();
+            if let Err(error) = self.save_recording(replay::REPLAY_PATH) {
+                eprintln!("Replay save error: {error}");
+            }
+        }
+        let volume = self.sfx.master_volume();
+        if volume != self.settings.master_volume {
+            self.settings.master_volume = volume;
+            if let Err(error) = self.settings.save(SETTINGS_PATH) {
+                eprintln!("Settings save error: {error}");
+            }
+        }
+    }
+
+    /// Fades to black, makes `swap`, then fades back in. Swaps straight away
+    /// when fades are turned off.
+    fn begin_transition(&mut self, swap: TransitionSwap) {
+        if self.config.fade_time <= 0.0 {
+            self.apply_transition_swap(swap);
+            return;
+        }
+        self.transition = Some(Transition {
+            timer: 0.0,
+            pending: Some(swap),
+        });
+    }
+
+    /// Advances the fade by one fixed step. `skip` makes any pending swap
+    /// and ends the fade at once.
+    fn update_transition(&mut self, skip: bool) {
+        let Some(mut transition) = self.transition.take() else {
+            return;
+        };
+        if skip {
+            if let Some(swap) = transition.pending {
+                self.apply_transition_swap(swap);
+            }
+            return;
+        }
+
+        transition.timer += self.config.fixed_dt;
+        if transition.timer < self.config.fade_time {
+            self.transition = Some(transition);
+        } else if let Some(swap) = transition.pending {
+            self.apply_transition_swap(swap);
+            self.transition = Some(Transition::fade_in());
+        }
+    }
+
+    fn apply_transition_swap(&mut self, swap: TransitionSwap) {
+        match swap {
+            TransitionSwap::StartRun => {
+                self.clear_saved_run();
+                self.state = GameState::Playing;
+                self.restart_run();
+                self.sfx.start_music_for(self.world.meta.theme);
+            }
+            TransitionSwap::Death => {
+                if self.practice_mode {
+                    self.practice_respawn();
+                } else if self.lives > 1 {
+                    self.lives -= 1;
+                    self.reset_level();
+                } else {
                     self.restart_run();
-                    self.state = GameState::Playing;
-                    self.sfx.start_music();
                 }
             }
+            TransitionSwap::Restart => {
+                self.restart_run();
+                self.state = GameState::Playing;
+                self.sfx.start_music_for(self.world.meta.theme);
+            }
         }
     }
 
+    /// Fades in from black after a level was swapped in outside the fixed
+    /// step, such as from the stage menu.
+    fn fade_in(&mut self) {
+        if self.config.fade_time > 0.0 {
+            self.transition = Some(Transition::fade_in());
+        }
+    }
+
+    /// Turns on the on-screen move and jump buttons.
+    pub fn enable_touch_controls(&mut self) {
+        self.touch_controls = true;
+    }
+
+    /// Renders the world at `PIXEL_VIEW_SIZE` and scales it to the window by
+    /// the largest whole factor that fits, so pixel art stays crisp.
+    pub fn enable_pixel_perfect(&mut self) {
+        let (width, height) = PIXEL_VIEW_SIZE;
+        let target = render_target(width, height);
+        target.texture.set_filter(FilterMode::Nearest);
+        self.pixel_target = Some(target);
+    }
+
     fn draw_playing(&self) {
-        let camera = self
+        let Some(sprites) = &self.sprites else {
+            return;
+        };
+        let screen = vec2(screen_width(), screen_height());
+        let letterbox = Letterbox::fit(screen, &self.config);
+        let view = match &self.pixel_target {
+            Some(target) => target.texture.size(),
+            None => letterbox.view,
+        };
+        let mut camera = self
             .world
-            .camera_for_focus(self.player.center(), &self.config);
+            .camera_for_focus(self.camera.focus(), view, &self.config);
+        camera.target += self.shake_offset;
+        camera.render_target = self.pixel_target.clone();
+        if self.pixel_target.is_none() {
+            camera.viewport = Some(letterbox.viewport(screen));
+        }
         set_camera(&camera);
+        if self.pixel_target.is_some() {
+            clear_background(self.world.meta.theme.palette().sky);
+        }
+
+        background::draw(&camera, &self.world, &self.background, &self.config);
+        self.world.draw(&self.config, self.world_time);
+        self.world
+            .draw_goal_post(!self.goal_unlocked(), &self.config);
+        if !self.goal_unlocked() {
+            self.draw_goal_progress();
+        }
+        self.world
+            .draw_checkpoints(self.active_checkpoint, &self.config);
 
-        background::draw(&camera, &self.world, &self.config);
-        self.world.draw(&self.config);
+        let focus = self.camera.focus();
+        for enemy in self
+            .enemies
+            .iter()
+            .filter(|enemy| is_active(enemy, focus, &self.config))
+        {
+            enemy.draw(sprites);
+        }
 
-        for enemy in &self.enemies {
-            enemy.draw(&self.sprites);
+        for fireball in &self.fireballs {
+            fireball.draw(&self.config);
+        }
+        for projectile in &self.enemy_projectiles {
+            projectile.draw(&self.config);
         }
 
-        let player_size = self.player.size();
-        let player_pos = self.player.pos;
-        let texture = self.sprites.player(self.player.is_powered());
+        particles::draw(&self.particles);
+        floating_text::draw(&self.floating_texts);
+        self.draw_ghost(sprites);
+
+        // Squash and stretch around the feet, leaving the hitbox alone.
+        let hitbox_size = self.player.size();
+        let player_size = hitbox_size * self.player.squash_stretch(&self.config);
+        let feet_y = if self.config.gravity_dir < 0.0 {
+            0.0
+        } else {
+            hitbox_size.y - player_size.y
+        };
+        let player_pos = self.player.pos + vec2((hitbox_size.x - player_size.x) * 0.5, feet_y);
+        let texture = sprites.player_frame(
+            self.player.is_powered(),
+            self.player.motion(),
+            self.player.anim_time(),
+        );
         let flip_x = self.player.facing_dir() < 0.0;
+        let flip_y = self.config.gravity_dir < 0.0;
         let mut tint = WHITE;
-        if self.player.is_invulnerable() && (get_time() * 12.0) as i32 % 2 == 0 {
+        if self.player.has_star() {
+            // Cycle through the rainbow, faster once the star is running out.
+            let speed = if self.player.star_time_left() < self.config.star_warning_time {
+                6.0
+            } else {
+                2.0
+            };
+            tint = macroquad::color::hsl_to_rgb((self.world_time * speed).fract(), 1.0, 0.7);
+        } else if self.player.is_invulnerable() && (get_time() * 12.0) as i32 % 2 == 0 {
             tint.a = 0.35;
         }
         draw_texture_ex(
@@ -242,240 +1224,1711 @@ impl Game {
             DrawTextureParams {
                 dest_size: Some(player_size),
                 flip_x,
+                flip_y,
                 ..Default::default()
             },
         );
+        self.world.draw_pipes();
+        self.world.draw_water();
+        background::draw_foreground(&camera, &self.world);
+        if self.shows_goal_hint() {
+            self.draw_goal_hint();
+        }
+        if self.show_colliders {
+            self.draw_colliders();
+        }
+        if self.debug_overlay {
+            self.draw_coyote_bar();
+        }
 
         set_default_camera();
-        draw_hud(self.high_score, self.score);
+        match &self.pixel_target {
+            Some(target) => draw_letterboxed(&target.texture),
+            None => letterbox.draw_bars(screen),
+        }
+        if self.state == GameState::Warping {
+            let fade = self.warp_fade();
+            draw_rectangle(
+                0.0,
+                0.0,
+                screen_width(),
+                screen_height(),
+                Color::new(0.0, 0.0, 0.0, fade),
+            );
+        }
+        self.draw_hud();
+        if self.show_minimap {
+            let enemies: Vec<Vec2> = self
+                .enemies
+                .iter()
+                .filter(|enemy| enemy.alive)
+                .map(|enemy| enemy.rect().center())
+                .collect();
+            minimap::draw_minimap(&self.world, self.player.center(), &enemies, &self.config);
+        }
+        if self.touch_controls {
+            touch::draw(self.touch_zones, screen);
+        }
+    }
+
+    /// Draws the best run's player at the point it had reached by this step
+    /// of the current run, if there is a ghost for this level.
+    fn draw_ghost(&self, sprites: &Sprites) {
+        let Some(ghost) = &self.ghost else {
+            return;
+        };
+        if ghost.level != self.settings.level || self.state == GameState::GoalSequence {
+            return;
+        }
+        let Some((prev, pos)) = ghost.frame(self.ghost_trail.len()) else {
+            return;
+        };
+
+        let delta = pos - prev;
+        let motion = PlayerMotion {
+            on_ground: delta.y.abs() < 0.01,
+            crouching: false,
+            speed_x: delta.x / self.config.fixed_dt,
+        };
+        let texture = sprites.player_frame(false, motion, self.world_time);
+        draw_texture_ex(
+            texture,
+            pos.x,
+            pos.y,
+            Color::new(0.7, 0.85, 1.0, 0.4),
+            DrawTextureParams {
+                dest_size: Some(self.config.player_size),
+                flip_x: delta.x < 0.0,
+                ..Default::default()
+            },
+        );
+    }
+
+    /// Keeps the finished run as the level's ghost if it beat the old one.
+    fn record_ghost(&mut self) {
+        let level = &self.settings.level;
+        let is_best = match &self.ghost {
+            Some(ghost) if ghost.level == *level => self.ghost_trail.len() < ghost.steps(),
+            _ => true,
+        };
+        if !is_best {
+            return;
+        }
+
+        let ghost = Ghost::new(level.clone(), std::mem::take(&mut self.ghost_trail));
+        if let Err(error) = ghost.save(GHOST_PATH) {
+            eprintln!("Ghost save error: {error}");
+        }
+        self.ghost = Some(ghost);
+    }
+
+    /// Bar under the player that drains over the coyote window, shown only
+    /// while airborne with a ground jump still available.
+    fn draw_coyote_bar(&self) {
+        let left = self.player.coyote_time_left();
+        if self.player.on_ground || left <= 0.0 || self.config.coyote_time <= 0.0 {
+            return;
+        }
+        let rect = self.player.rect();
+        let fill = (left / self.config.coyote_time).min(1.0);
+        let y = rect.y + rect.h + 4.0;
+        draw_rectangle(rect.x, y, rect.w, 4.0, Color::new(0.0, 0.0, 0.0, 0.5));
+        draw_rectangle(rect.x, y, rect.w * fill, 4.0, YELLOW);
+    }
+
+    fn shows_goal_hint(&self) -> bool {
+        self.world.meta.hints && self.idle_time >= self.config.hint_idle_time
+    }
+
+    /// Pulsing arrow next to the player, pointing the way to the goal.
+    fn draw_goal_hint(&self) {
+        let tile = self.config.tile_size;
+        let from = self.player.center();
+        let to = self.world.goal_tile + Vec2::splat(tile * 0.5);
+        let dir = (to - from).normalize_or_zero();
+        if dir == Vec2::ZERO {
+            return;
+        }
+        let pulse = 0.5 + 0.5 * (self.world_time * 6.0).sin();
+        let color = Color::new(1.0, 0.9, 0.2, 0.6 + 0.4 * pulse);
+        let base = from + dir * (tile * 1.5 + pulse * 8.0);
+        let side = dir.perp() * 10.0;
+        draw_line(
+            base.x - dir.x * 16.0,
+            base.y - dir.y * 16.0,
+            base.x,
+            base.y,
+            4.0,
+            color,
+        );
+        draw_triangle(base + side, base - side, base + dir * 14.0, color);
+    }
+
+    /// Outlines every hitbox in world space, one color per category.
+    fn draw_colliders(&self) {
+        let outline = |rect: Rect, color: Color| {
+            draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.5, color);
+        };
+
+        for solid in self.world.colliders() {
+            outline(*solid, Color::new(0.2, 0.4, 1.0, 0.7));
+        }
+        for hazard in &self.world.hazards {
+            outline(*hazard, Color::new(1.0, 0.3, 0.0, 0.8));
+        }
+        for coin in &self.world.coins {
+            outline(
+                coin_rect(*coin, &self.config),
+                Color::new(1.0, 0.9, 0.0, 0.8),
+            );
+        }
+        outline(
+            self.world.goal_trigger_rect(&self.config),
+            Color::new(0.0, 1.0, 0.3, 0.8),
+        );
+        for enemy in self.enemies.iter().filter(|enemy| enemy.alive) {
+            let rect = enemy.rect();
+            outline(rect, Color::new(1.0, 0.0, 0.2, 0.8));
+            // Stomp threshold: feet above this line count as a stomp.
+            draw_line(
+                rect.x,
+                rect.y + STOMP_TOLERANCE,
+                rect.x + rect.w,
+                rect.y + STOMP_TOLERANCE,
+                1.0,
+                Color::new(1.0, 0.6, 0.8, 0.9),
+            );
+        }
+        outline(self.player.rect(), Color::new(1.0, 1.0, 1.0, 0.9));
     }
 
     fn draw_level_complete(&self) {
         set_default_camera();
-        draw_hud(self.high_score, self.score);
-        draw_centered_text("Course Complete! Press R to restart.", 48.0, BLACK);
+        self.draw_hud();
+        let color = self.world.meta.theme.palette().hud_text;
+        results::draw(&self.stats, self.config.fixed_dt, color);
     }
 
     fn reset_level(&mut self) {
-        self.player.reset(self.world.player_spawn, &self.config);
-        self.world.coins = self.coin_spawns.clone();
-        self.world.mushrooms = self.mushroom_spawns.clone();
-        for (enemy, spawn) in self
-            .enemies
-            .iter_mut()
-            .zip(self.world.enemy_spawns.iter().copied())
-        {
-            enemy.reset(spawn, &self.world, &self.config);
+        let spawn = self.active_checkpoint.unwrap_or(self.world.player_spawn);
+        self.player.reset(spawn, &self.config);
+        self.camera
+            .snap(self.player.center(), self.player.facing_dir(), &self.config);
+        self.stomp_combo = 0;
+        self.idle_time = 0.0;
+        self.world.coins = if self.active_checkpoint.is_some() {
+            self.checkpoint_coins.clone()
+        } else {
+            self.coin_spawns.clone()
+        };
+        self.level_coins = if self.active_checkpoint.is_some() {
+            self.checkpoint_level_coins
+        } else {
+            0
+        };
+        if self.active_checkpoint.is_some() {
+            self.world.mushrooms = self.checkpoint_mushrooms.clone();
+            self.world.stars = self.checkpoint_stars.clone();
+            self.world
+                .reset_bricks(&self.checkpoint_bricks, &self.config);
+            for (block, used) in self
+                .world
+                .question_blocks
+                .iter_mut()
+                .zip(self.checkpoint_blocks_used.iter().copied())
+            {
+                block.used = used;
+            }
+        } else {
+            self.world.mushrooms = self.mushroom_spawns.clone();
+            self.world.stars = self.star_spawns.clone();
+            self.world.reset_bricks(&self.brick_spawns, &self.config);
+            for block in &mut self.world.question_blocks {
+                block.used = false;
+            }
+        }
+        self.sfx.set_star_music(false);
+        self.world.reset_platforms(&self.platform_spawns);
+        self.particles.clear();
+        self.fireballs.clear();
+        self.enemy_projectiles.clear();
+        self.floating_texts.clear();
+        for enemy in &mut self.enemies {
+            enemy.reset(&self.world, &self.config);
+        }
+        if self.active_checkpoint.is_some() {
+            for (enemy, alive) in self
+                .enemies
+                .iter_mut()
+                .zip(self.checkpoint_enemies_alive.iter().copied())
+            {
+                enemy.alive = alive;
+            }
+        }
+    }
+
+    fn draw_hud(&self) {
+        let color = self.world.meta.theme.palette().hud_text;
+        draw_hud(self.high_score, self.score, self.coins, self.lives, color);
+        draw_text(
+            &format!("Difficulty: {}", self.difficulty.name()),
+            16.0,
+            142.0,
+            22.0,
+            color,
+        );
+        if self.practice_mode {
+            draw_text(
+                "Practice mode: deaths and records don't count",
+                16.0,
+                170.0,
+                22.0,
+                color,
+            );
+        }
+        draw_level_name(&self.world.meta.name, color);
+        draw_volume(self.sfx.master_volume(), self.sfx.is_muted(), color);
+        if self.time_scale != 1.0 {
+            draw_time_scale(self.time_scale, color);
+        }
+        if self.speedrun.enabled {
+            draw_speedrun_timer(
+                self.speedrun.elapsed(self.config.fixed_dt),
+                self.speedrun.best(self.config.fixed_dt),
+                color,
+            );
+        }
+        // Show the health bar once the boss is within a screen of the player.
+        let boss = self.enemies.iter().find(|enemy| {
+            enemy.is_boss()
+                && enemy.alive
+                && (enemy.rect().center().x - self.player.center().x).abs() < screen_width()
+        });
+        if let Some(boss) = boss {
+            draw_boss_health(boss.health(), self.config.boss_health, color);
         }
     }
 
     fn restart_run(&mut self) {
+        self.difficulty = self.settings.difficulty;
+        self.config = level_config(&self.base_config, self.difficulty, &self.world);
         self.score = 0;
+        self.coins = 0;
+        self.lives = self.config.starting_lives;
+        self.active_checkpoint = None;
+        self.screen_shake = 0.0;
+        self.shake_offset = Vec2::ZERO;
+        self.reseed(RNG_SEED);
+        self.speedrun.restart();
+        self.stats = LevelStats::default();
+        self.ghost_trail.clear();
         self.reset_level();
     }
 
-    fn player_died(&mut self) {
-        self.sfx.play_hurt();
-        self.score = 0;
-        self.reset_level();
+    /// Returns to the title screen and records every fixed-step input from
+    /// there, so the recording replays from the same starting state.
+    pub fn start_recording(&mut self) {
+        self.return_to_title();
+        self.recorded_inputs.clear();
+        self.record_inputs = true;
     }
 
-    fn add_score(&mut self, points: u32) {
-        self.score = self.score.saturating_add(points);
-        self.high_score = self.high_score.max(self.score);
+    pub fn stop_recording(&mut self) -> &[InputState] {
+        self.record_inputs = false;
+        &self.recorded_inputs
     }
 
-    fn collect_coins(&mut self) -> u32 {
-        let player_rect = self.player.rect();
-        let radius = self.config.tile_size * 0.2;
-        let size = radius * 2.0;
-        let mut collected = 0u32;
+    pub fn save_recording(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, replay::encode(&self.recorded_inputs))
+    }
 
-        self.world.coins.retain(|coin| {
-            let coin_rect = Rect::new(coin.x - radius, coin.y - radius, size, size);
-            let hit = physics::rects_intersect(player_rect, coin_rect);
-            if hit {
-                collected += 1;
-            }
-            !hit
-        });
+    /// Returns to the title screen and drives `fixed_update` from `inputs`
+    /// until they run out, after which live input takes over again.
+    pub fn start_playback(&mut self, inputs: Vec<InputState>) {
+        self.return_to_title();
+        self.record_inputs = false;
+        self.playback = Some(inputs.into_iter());
+    }
 
-        if collected > 0 {
-            self.add_score(collected * 200);
-        }
+    pub fn load_replay(&mut self, path: &str) -> Result<(), String> {
+        let contents = std::fs::read_to_string(path).map_err(|error| error.to_string())?;
+        let inputs = replay::decode(&contents)?;
+        self.start_playback(inputs);
+        Ok(())
+    }
 
-        collected
+    fn open_editor(&mut self) {
+        self.editor = Some(Editor::new(&self.world));
+        self.state = GameState::Editor;
     }
 
-    fn collect_mushrooms(&mut self) -> u32 {
-        let player_rect = self.player.rect();
-        let size = self.config.mushroom_size;
-        let mut collected = 0u32;
+    fn update_editor(&mut self, frame_dt: f32) {
+        let Some(editor) = &mut self.editor else {
+            self.state = GameState::Title;
+            return;
+        };
 
-        self.world.mushrooms.retain(|pos| {
-            let mushroom_rect = Rect::new(pos.x, pos.y, size.x, size.y);
-            let hit = physics::rects_intersect(player_rect, mushroom_rect);
-            if hit {
-                collected += 1;
+        match editor.update(&self.config, frame_dt) {
+            EditorAction::None => {}
+            EditorAction::Exit => {
+                self.editor = None;
+                self.state = GameState::Title;
+                self.input = InputState::default();
+            }
+            EditorAction::Save => {
+                let text = editor.to_level_text();
+                match World::from_ascii(&text, &self.config) {
+                    Err(error) => editor.set_status(format!("Not saved, invalid level: {error}")),
+                    Ok(world) => {
+                        let path = format!("{ASSETS_DIR}/{}", self.settings.level);
+                        match std::fs::write(&path, text) {
+                            Err(error) => editor.set_status(format!("Save failed: {error}")),
+                            Ok(()) => {
+                                editor.set_status(format!("Saved to {path}"));
+                                self.replace_world(world);
+                            }
+                        }
+                    }
+                }
             }
-            !hit
-        });
-
-        if collected > 0 {
-            self.player.set_powered(true);
-            self.add_score(collected * 1000);
         }
+    }
 
-        collected
+    fn open_settings(&mut self) {
+        let mut settings = self.settings.clone();
+        settings.master_volume = self.sfx.master_volume();
+        self.settings_menu = Some(SettingsMenu::new(settings));
+        self.state = GameState::Settings;
     }
 
-    fn handle_player_enemy_collisions(&mut self) {
-        let player_rect = self.player.rect();
-        let player_bottom = player_rect.y + player_rect.h;
-        let mut stomped_index = None;
-        let mut power_down_dir = None;
-        let mut died = false;
+    fn update_settings(&mut self) {
+        let Some(menu) = &mut self.settings_menu else {
+            self.state = GameState::Title;
+            return;
+        };
 
-        for (idx, enemy) in self.enemies.iter().enumerate() {
-            if !enemy.alive {
-                continue;
+        match menu.update() {
+            SettingsAction::None => {}
+            SettingsAction::Changed => {
+                let settings = menu.settings().clone();
+                self.apply_settings(settings);
             }
-
-            let enemy_rect = enemy.rect();
-            if !physics::rects_intersect(player_rect, enemy_rect) {
-                continue;
+            SettingsAction::Exit => {
+                self.settings_menu = None;
+                self.state = GameState::Title;
+                self.input = InputState::default();
+                if let Err(error) = self.settings.save(SETTINGS_PATH) {
+                    eprintln!("Settings save error: {error}");
+                }
             }
+        }
+    }
 
-            let stomp_threshold = enemy_rect.y + 6.0;
-            if self.player.vel.y > 0.0 && player_bottom <= stomp_threshold {
-                stomped_index = Some(idx);
-            } else if self.player.is_invulnerable() {
-                // Ignore side hits while invulnerable.
-            } else if self.player.is_powered() {
-                let player_center_x = player_rect.x + player_rect.w * 0.5;
-                let enemy_center_x = enemy_rect.x + enemy_rect.w * 0.5;
-                let dir = if enemy_center_x < player_center_x {
-                    1.0
-                } else {
-                    -1.0
-                };
-                power_down_dir = Some(dir);
-            } else {
-                died = true;
+    /// Applies whatever differs between `settings` and the current ones. A
+    /// starting level that fails to load is reported and left unchanged.
+    fn apply_settings(&mut self, mut settings: Settings) {
+        if settings.master_volume != self.sfx.master_volume() {
+            self.sfx.set_master_volume(settings.master_volume);
+        }
+        if settings.fullscreen != self.settings.fullscreen {
+            set_fullscreen(settings.fullscreen);
+        }
+        if settings.player_palette != self.settings.player_palette {
+            self.apply_player_palette(settings.player_palette);
+        }
+        if settings.level != self.settings.level {
+            if let Err(error) = self.load_level(&settings.level) {
+                eprintln!("{error}");
+                settings.level = self.settings.level.clone();
             }
-            break;
         }
+        self.settings = settings;
+    }
 
-        if let Some(idx) = stomped_index {
-            if let Some(enemy) = self.enemies.get_mut(idx) {
-                enemy.alive = false;
-            }
-            self.player.vel.y = -self.config.stomp_bounce;
-            self.add_score(100);
-            self.sfx.play_stomp();
+    /// Recolors the player sprites; headless games have none to recolor.
+    fn apply_player_palette(&mut self, palette: usize) {
+        if let Some(sprites) = &mut self.sprites {
+            let palette = PLAYER_PALETTES[palette];
+            sprites.set_player_colors(palette.shirt, palette.overalls);
+        }
+    }
+
+    fn open_level_select(&mut self) {
+        self.level_select = Some(LevelSelect::new(self.levels.clone(), &self.settings.level));
+        self.state = GameState::LevelSelect;
+    }
+
+    fn update_level_select(&mut self) {
+        let Some(menu) = &mut self.level_select else {
+            self.state = GameState::Title;
+            return;
+        };
+
+        match menu.update() {
+            LevelSelectAction::None => {}
+            LevelSelectAction::Play(level) => {
+                self.level_select = None;
+                self.pending_level = Some(level);
+            }
+            LevelSelectAction::Exit => {
+                self.level_select = None;
+                self.state = GameState::Title;
+                self.input = InputState::default();
+            }
+        }
+    }
+
+    /// Loads the level picked on the stage menu, if any, and starts a new
+    /// run on it. It also becomes the starting level in the settings, and the
+    /// run is saved so it can be continued from there.
+    pub async fn load_pending_level(&mut self) {
+        let Some(level) = self.pending_level.take() else {
+            return;
+        };
+        let world = World::load(&level, &self.config).await;
+        self.replace_world(world);
+        self.settings.level = level;
+        if let Err(error) = self.settings.save(SETTINGS_PATH) {
+            eprintln!("Settings save error: {error}");
+        }
+        self.input = InputState::default();
+        self.state = GameState::Playing;
+        self.restart_run();
+        self.save_run();
+        self.sfx.start_music_for(self.world.meta.theme);
+        self.fade_in();
+    }
+
+    /// Reads `level` (relative to the assets folder) and swaps it in.
+    fn load_level(&mut self, level: &str) -> Result<(), String> {
+        let path = format!("{ASSETS_DIR}/{level}");
+        let world = std::fs::read_to_string(&path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| World::from_ascii(&text, &self.config))
+            .map_err(|error| format!("Level load error for {path}: {error}"))?;
+        self.replace_world(world);
+        Ok(())
+    }
+
+    /// Picks up the run saved when the player last quit to the title.
+    fn continue_saved_run(&mut self) {
+        let Some(save) = self.saved_run.clone() else {
+            return;
+        };
+        if save.level != self.settings.level {
+            if let Err(error) = self.load_level(&save.level) {
+                eprintln!("{error}");
+                return;
+            }
+            self.settings.level = save.level.clone();
+        }
+
+        self.restart_run();
+        self.score = save.score;
+        self.high_score = self.high_score.max(save.score);
+        self.lives = save.lives;
+        self.coins = save.coins;
+        self.input = InputState::default();
+        self.state = GameState::Playing;
+        self.sfx.start_music_for(self.world.meta.theme);
+        self.fade_in();
+    }
+
+    /// Remembers the current run so it can be continued after relaunching.
+    fn save_run(&mut self) {
+        let save = SaveState {
+            level: self.settings.level.clone(),
+            score: self.score,
+            lives: self.lives,
+            coins: self.coins,
+        };
+        if let Err(error) = save.save(SAVE_PATH) {
+            eprintln!("Save error: {error}");
+        }
+        self.saved_run = Some(save);
+    }
+
+    fn clear_saved_run(&mut self) {
+        if self.saved_run.take().is_some() {
+            if let Err(error) = SaveState::clear(SAVE_PATH) {
+                eprintln!("Save clear error: {error}");
+            }
+        }
+    }
+
+    /// Swaps in a freshly built level and rebuilds everything spawned from it.
+    fn replace_world(&mut self, world: World) {
+        self.config = level_config(&self.base_config, self.difficulty, &world);
+        self.enemies = spawn_enemies(&world, &self.config);
+        self.coin_spawns = world.coins.clone();
+        self.star_spawns = world.stars.clone();
+        self.mushroom_spawns = world.mushrooms.clone();
+        self.brick_spawns = world.bricks.clone();
+        self.platform_spawns = world.platforms.clone();
+        self.world = world;
+        self.active_checkpoint = None;
+        self.checkpoint_coins.clear();
+        self.checkpoint_enemies_alive.clear();
+        self.checkpoint_blocks_used.clear();
+        self.checkpoint_bricks.clear();
+        self.checkpoint_mushrooms.clear();
+        self.checkpoint_stars.clear();
+        self.reset_level();
+    }
+
+    fn return_to_title(&mut self) {
+        self.sfx.stop_music();
+        self.state = GameState::Title;
+        self.input = InputState::default();
+        self.accumulator = 0.0;
+        self.playback = None;
+    }
+
+    /// Restarts gameplay randomness from `seed`.
+    pub fn reseed(&mut self, seed: u32) {
+        self.rng.reseed(seed);
+    }
+
+    fn player_died(&mut self) {
+        // Several hazards can land in the same step; only the first counts.
+        if self.transition.is_some() {
+            return;
+        }
+        self.sfx.play_hurt();
+        self.add_shake(SHAKE_DEATH);
+        self.begin_transition(TransitionSwap::Death);
+    }
+
+    /// Puts the player back at the last checkpoint (or the spawn) with a
+    /// short invulnerability flash, leaving lives, coins and enemies as they
+    /// are.
+    fn practice_respawn(&mut self) {
+        let spawn = self.active_checkpoint.unwrap_or(self.world.player_spawn);
+        self.player.reset(spawn, &self.config);
+        self.player
+            .start_invulnerability(self.config.hurt_invuln_time);
+        self.camera
+            .snap(self.player.center(), self.player.facing_dir(), &self.config);
+        self.stomp_combo = 0;
+        self.sfx.set_star_music(false);
+    }
+
+    fn add_shake(&mut self, amount: f32) {
+        let amount = amount * self.config.shake_intensity;
+        self.screen_shake = self.screen_shake.max(amount);
+    }
+
+    fn update_shake(&mut self, dt: f32) {
+        self.screen_shake *= (-self.config.shake_decay * dt).exp();
+        if self.screen_shake < 0.1 {
+            self.screen_shake = 0.0;
+            self.shake_offset = Vec2::ZERO;
+            return;
+        }
+
+        self.shake_offset =
+            vec2(self.rng.range(-1.0, 1.0), self.rng.range(-1.0, 1.0)) * self.screen_shake;
+    }
+
+    fn add_score(&mut self, points: u32) {
+        self.score = self.score.saturating_add(points);
+        self.stats.score = self.stats.score.saturating_add(points);
+        if !self.practice_mode {
+            self.high_score = self.high_score.max(self.score);
+        }
+    }
+
+    /// Like `add_score`, but also floats a "+points" popup up from `pos`.
+    fn add_score_at(&mut self, points: u32, pos: Vec2) {
+        self.add_score(points);
+        floating_text::spawn_score(&mut self.floating_texts, pos, points);
+    }
+
+    /// Drifts coins within `Config::magnet_radius` toward the player while
+    /// the coin magnet is enabled and a star is active.
+    fn attract_coins(&mut self) {
+        if !self.config.coin_magnet || !self.player.has_star() {
+            return;
+        }
+
+        let target = self.player.center();
+        let max_step = self.config.magnet_speed * self.config.fixed_dt;
+        let radius = self.config.magnet_radius;
+        for coin in &mut self.world.coins {
+            let offset = target - *coin;
+            let distance = offset.length();
+            if distance <= radius && distance > 0.0 {
+                *coin += offset * (max_step / distance).min(1.0);
+            }
+        }
+    }
+
+    fn collect_coins(&mut self) -> u32 {
+        let player_rect = self.player.rect();
+        let config = self.config;
+        let mut collected = 0u32;
+        let particles = &mut self.particles;
+        let floating_texts = &mut self.floating_texts;
+        let max_particles = self.config.max_particles;
+
+        self.world.coins.retain(|coin| {
+            let hit = physics::rects_intersect(player_rect, coin_rect(*coin, &config));
+            if hit {
+                collected += 1;
+                particles::spawn_burst(particles, max_particles, *coin, COIN_COLOR, 8, 120.0);
+                floating_text::spawn_score(floating_texts, *coin, 200);
+            }
+            !hit
+        });
+
+        if collected > 0 {
+            self.add_score(collected * 200);
+            self.add_coins(collected);
+        }
+
+        collected
+    }
+
+    fn add_coins(&mut self, count: u32) {
+        let per_life = self.config.coins_per_life.max(1);
+        let before = self.coins / per_life;
+        self.coins = self.coins.saturating_add(count);
+        self.stats.coins = self.stats.coins.saturating_add(count);
+        let was_locked = !self.goal_unlocked();
+        self.level_coins = self.level_coins.saturating_add(count);
+        if was_locked && self.goal_unlocked() {
+            self.sfx.play_unlock();
+        }
+        let extra_lives = self.coins / per_life - before;
+        if extra_lives > 0 {
+            self.lives = self.lives.saturating_add(extra_lives);
+            self.sfx.play_one_up();
+        }
+    }
+
+    fn collect_mushrooms(&mut self) -> u32 {
+        let player_rect = self.player.rect();
+        let config = self.config;
+        let mut collected = 0u32;
+
+        self.world.mushrooms.retain(|mushroom| {
+            let hit = physics::rects_intersect(player_rect, mushroom.rect(&config));
+            if hit {
+                collected += 1;
+            }
+            !hit
+        });
+
+        if collected > 0 {
+            self.player.set_powered(true);
+            let popup_pos = vec2(player_rect.center().x, player_rect.y);
+            self.add_score_at(collected * 1000, popup_pos);
+        }
+
+        collected
+    }
+
+    fn collect_stars(&mut self) -> u32 {
+        let player_rect = self.player.rect();
+        let config = self.config;
+        let mut collected = 0u32;
+
+        self.world.stars.retain(|star| {
+            let hit = physics::rects_intersect(player_rect, star_rect(*star, &config));
+            if hit {
+                collected += 1;
+            }
+            !hit
+        });
+
+        if collected > 0 {
+            self.player.start_star(self.config.star_duration);
+            let popup_pos = vec2(player_rect.center().x, player_rect.y);
+            self.add_score_at(collected * 1000, popup_pos);
+        }
+
+        collected
+    }
+
+    fn throw_fireball(&mut self) {
+        if !self.player.is_powered() || self.fireballs.len() >= self.config.max_fireballs {
+            return;
+        }
+
+        self.fireballs.push(Fireball::new(
+            self.player.rect(),
+            self.player.facing_dir(),
+            &self.config,
+        ));
+        self.sfx.play_fireball();
+    }
+
+    fn update_fireballs(&mut self) {
+        let world = &self.world;
+        let config = &self.config;
+        self.fireballs
+            .retain_mut(|fireball| fireball.update(world, config, config.fixed_dt));
+
+        let mut idx = 0;
+        while idx < self.fireballs.len() {
+            let fireball_rect = self.fireballs[idx].rect(&self.config);
+            let hit = self
+                .enemies
+                .iter_mut()
+                .find(|enemy| enemy.alive && physics::rects_intersect(fireball_rect, enemy.rect()));
+
+            let Some(enemy) = hit else {
+                idx += 1;
+                continue;
+            };
+
+            let landed = enemy.take_hit(&self.config);
+            let enemy_center = enemy.rect().center();
+            self.fireballs.swap_remove(idx);
+            if !landed {
+                continue;
+            }
+            particles::spawn_burst(
+                &mut self.particles,
+                self.config.max_particles,
+                enemy_center,
+                STOMP_COLOR,
+                10,
+                150.0,
+            );
+            self.add_score_at(100, enemy_center);
+            self.sfx.play_stomp(enemy_center.x, &self.camera);
+        }
+    }
+
+    fn fire_turrets(&mut self) {
+        let target_x = self.player.center().x;
+        for enemy in &mut self.enemies {
+            if let Some(dir) = enemy.take_shot(target_x, &self.config) {
+                self.enemy_projectiles
+                    .push(EnemyProjectile::new(enemy.rect(), dir, &self.config));
+            }
+        }
+    }
+
+    /// Moves turret shots and applies any that hit the player like a side
+    /// hit from an enemy. A star just destroys the shot.
+    fn update_enemy_projectiles(&mut self) {
+        let world = &self.world;
+        let config = &self.config;
+        self.enemy_projectiles
+            .retain_mut(|projectile| projectile.update(world, config, config.fixed_dt));
+
+        let player_rect = self.player.rect();
+        let Some(idx) = self.enemy_projectiles.iter().position(|projectile| {
+            physics::rects_intersect(player_rect, projectile.rect(&self.config))
+        }) else {
+            return;
+        };
+        let dir = self.enemy_projectiles.swap_remove(idx).dir();
+
+        if self.player.has_star() || self.player.is_invulnerable() || self.player.is_dashing() {
+            return;
+        }
+        if self.player.is_powered() {
+            self.power_down(dir);
+        } else {
+            self.player_died();
+        }
+    }
+
+    fn handle_head_bonk(&mut self) {
+        let player_rect = self.player.rect();
+        let probe_y = if self.config.gravity_dir < 0.0 {
+            player_rect.y + player_rect.h
+        } else {
+            player_rect.y - 1.0
+        };
+        let probe = Rect::new(player_rect.x, probe_y, player_rect.w, 1.0);
+
+        match self.world.bonk_target(probe) {
+            Some(BonkTarget::Brick(idx)) => {
+                if self.player.is_powered() {
+                    let brick = self.world.break_brick(idx, &self.config);
+                    particles::spawn_brick_debris(
+                        &mut self.particles,
+                        self.config.max_particles,
+                        brick,
+                    );
+                    self.add_score_at(50, brick.center());
+                    self.sfx.play_brick();
+                } else {
+                    self.sfx.play_bump();
+                }
+            }
+            Some(BonkTarget::QuestionBlock(idx)) => {
+                let block = &mut self.world.question_blocks[idx];
+                if block.used {
+                    self.sfx.play_bump();
+                    return;
+                }
+                block.used = true;
+                let block_rect = block.rect;
+
+                match block.payload {
+                    BlockPayload::Coin => {
+                        particles::spawn_burst(
+                            &mut self.particles,
+                            self.config.max_particles,
+                            vec2(block_rect.center().x, block_rect.y),
+                            COIN_COLOR,
+                            8,
+                            120.0,
+                        );
+                        self.add_score_at(200, vec2(block_rect.center().x, block_rect.y));
+                        self.add_coins(1);
+                        self.sfx.play_coin(block_rect.center().x, &self.camera);
+                    }
+                    BlockPayload::Mushroom => {
+                        self.world
+                            .mushrooms
+                            .push(Mushroom::emerging(block_rect, &self.config));
+                        self.sfx.play_powerup();
+                    }
+                }
+            }
+            None => {}
+        }
+    }
+
+    fn handle_player_enemy_collisions(&mut self) {
+        let g = self.config.gravity_dir;
+        // Stomps are judged with "down" along gravity, so with gravity
+        // pointing up the player's top edge is their feet.
+        let feet = |rect: Rect| {
+            if g < 0.0 {
+                -rect.y
+            } else {
+                rect.y + rect.h
+            }
+        };
+        let head = |rect: Rect| {
+            if g < 0.0 {
+                -(rect.y + rect.h)
+            } else {
+                rect.y
+            }
+        };
+        let player_rect = self.player.rect();
+        let player_feet = feet(player_rect);
+        let player_center_x = player_rect.x + player_rect.w * 0.5;
+        // A fast fall can carry the feet well past an enemy's top in one
+        // step, so judge stomps by where the feet were before the move too.
+        let prev_rect = self.player.prev_rect();
+        let prev_feet = feet(prev_rect);
+        let descending = self.player.vel.y * g > 0.0 || player_feet > prev_feet;
+        let mut stomped_index = None;
+        let mut kicked = None;
+        let mut power_down_dir = None;
+        let mut died = false;
+        let mut star_kills = Vec::new();
+
+        for (idx, enemy) in self.enemies.iter().enumerate() {
+            if !enemy.alive {
+                continue;
+            }
+
+            let enemy_rect = enemy.rect();
+            if !physics::rects_intersect(player_rect, enemy_rect) {
+                continue;
+            }
+            if self.player.has_star() {
+                star_kills.push(idx);
+                continue;
+            }
+
+            let enemy_center_x = enemy_rect.x + enemy_rect.w * 0.5;
+            let away_dir = if enemy_center_x < player_center_x {
+                -1.0
+            } else {
+                1.0
+            };
+            let stomp_threshold = head(enemy_rect) + STOMP_TOLERANCE;
+            if enemy.ignores_player() {
+                continue;
+            } else if enemy.is_idle_shell() {
+                kicked = Some((idx, away_dir));
+            } else if descending
+                && prev_feet.min(player_feet) <= stomp_threshold
+                && !enemy.is_spiky()
+            {
+                stomped_index = Some(idx);
+            } else if self.player.is_invulnerable() || self.player.is_dashing() {
+                // Ignore side hits while invulnerable or dashing.
+            } else if self.player.is_powered() {
+                power_down_dir = Some(-away_dir);
+            } else {
+                died = true;
+            }
+            break;
+        }
+
+        for idx in star_kills {
+            let enemy = &mut self.enemies[idx];
+            if !enemy.take_hit(&self.config) {
+                continue;
+            }
+            let enemy_center = enemy.rect().center();
+            particles::spawn_burst(
+                &mut self.particles,
+                self.config.max_particles,
+                enemy_center,
+                STOMP_COLOR,
+                10,
+                150.0,
+            );
+            self.add_score_at(100, enemy_center);
+            self.sfx.play_stomp(enemy_center.x, &self.camera);
+        }
+
+        if let Some((idx, dir)) = kicked {
+            if let Some(enemy) = self.enemies.get_mut(idx) {
+                enemy.kick(dir, &self.config);
+            }
+            self.add_shake(SHAKE_STOMP);
+            self.sfx.play_stomp(player_rect.center().x, &self.camera);
+        } else if let Some(idx) = stomped_index {
+            let enemy = &mut self.enemies[idx];
+            // A boss still flashing from the last hit doesn't bounce the
+            // player or pay out again.
+            if !enemy.stomp(&self.config) {
+                return;
+            }
+            let stomp_pos = enemy.rect().center();
+            particles::spawn_burst(
+                &mut self.particles,
+                self.config.max_particles,
+                stomp_pos,
+                STOMP_COLOR,
+                10,
+                150.0,
+            );
+            self.player.vel.y = -self.config.stomp_bounce * g;
+            self.stomp_combo += 1;
+            self.stats.stomps += 1;
+            self.add_score_at(combo_score(self.stomp_combo), stomp_pos);
+            self.add_shake(SHAKE_STOMP);
+            self.sfx
+                .play_stomp_combo(self.stomp_combo, stomp_pos.x, &self.camera);
         } else if let Some(dir) = power_down_dir {
-            self.player.set_powered(false);
-            self.player
-                .start_invulnerability(self.config.hurt_invuln_time);
-            self.player.vel.x = dir * self.config.hurt_knockback_x;
-            self.player.vel.y = -self.config.hurt_knockback_y;
-            self.player.pos.x += dir * 4.0;
-            self.player.on_ground = false;
-            self.sfx.play_hurt();
+            self.power_down(dir);
         } else if died {
             self.player_died();
         }
     }
 
+    /// Turns enemies around when they walk into each other. Only pairs whose
+    /// horizontal velocities point toward each other react, so enemies that
+    /// are stacked or already separating don't jitter back and forth.
+    fn handle_enemy_bumps(&mut self) {
+        for (a_idx, b_idx) in enemy::overlapping_pairs(&self.enemies) {
+            let (head, tail) = self.enemies.split_at_mut(b_idx);
+            let (a, b) = (&mut head[a_idx], &mut tail[0]);
+
+            let a_is_left = a.rect().center().x <= b.rect().center().x;
+            let (left, right) = if a_is_left { (a, b) } else { (b, a) };
+            if left.vel.x > 0.0 && right.vel.x < 0.0 {
+                left.turn_around(-1.0);
+                right.turn_around(1.0);
+            }
+        }
+    }
+
+    /// Lets sliding shells knock out any other enemy they run into, scoring
+    /// more for each enemy taken out by the same kick.
+    fn handle_shell_hits(&mut self) {
+        for shell_idx in 0..self.enemies.len() {
+            if !self.enemies[shell_idx].is_sliding_shell() {
+                continue;
+            }
+
+            let shell_rect = self.enemies[shell_idx].rect();
+            for target_idx in 0..self.enemies.len() {
+                if target_idx == shell_idx {
+                    continue;
+                }
+                let target = &mut self.enemies[target_idx];
+                if !target.alive || !physics::rects_intersect(shell_rect, target.rect()) {
+                    continue;
+                }
+
+                if !target.take_hit(&self.config) {
+                    continue;
+                }
+                let target_center = target.rect().center();
+                let chain = self.enemies[shell_idx].bump_chain();
+                particles::spawn_burst(
+                    &mut self.particles,
+                    self.config.max_particles,
+                    target_center,
+                    STOMP_COLOR,
+                    10,
+                    150.0,
+                );
+                self.add_score_at(combo_score(chain), target_center);
+                self.sfx.play_stomp(target_center.x, &self.camera);
+            }
+        }
+    }
+
+    fn power_down(&mut self, dir: f32) {
+        self.player.set_powered(false);
+        self.player
+            .start_invulnerability(self.config.hurt_invuln_time);
+        self.player.vel.x = dir * self.config.hurt_knockback_x;
+        self.player.vel.y = -self.config.hurt_knockback_y * self.config.gravity_dir;
+        self.player.pos.x += dir * 4.0;
+        self.player.on_ground = false;
+        self.sfx.play_hurt();
+    }
+
+    fn check_hazards(&mut self) {
+        if self.player.is_invulnerable() || self.player.has_star() {
+            return;
+        }
+
+        let player_rect = self.player.rect();
+        let touching = self
+            .world
+            .hazards
+            .iter()
+            .any(|hazard| physics::rects_intersect(player_rect, *hazard));
+        if !touching {
+            return;
+        }
+
+        if self.player.is_powered() {
+            self.power_down(-self.player.facing_dir());
+        } else {
+            self.player_died();
+        }
+    }
+
+    fn check_checkpoints(&mut self) {
+        let player_rect = self.player.rect();
+        let tile = self.config.tile_size;
+        let reached = self.world.checkpoints.iter().copied().find(|checkpoint| {
+            Some(*checkpoint) != self.active_checkpoint
+                && physics::rects_intersect(
+                    player_rect,
+                    Rect::new(checkpoint.x, checkpoint.y, tile, tile),
+                )
+        });
+
+        if let Some(checkpoint) = reached {
+            // Remember what was already cleared so it stays cleared on respawn.
+            self.active_checkpoint = Some(checkpoint);
+            self.checkpoint_coins = self.world.coins.clone();
+            self.checkpoint_level_coins = self.level_coins;
+            self.checkpoint_enemies_alive = self.enemies.iter().map(|enemy| enemy.alive).collect();
+            self.checkpoint_blocks_used = self
+                .world
+                .question_blocks
+                .iter()
+                .map(|block| block.used)
+                .collect();
+            self.checkpoint_bricks = self.world.bricks.clone();
+            self.checkpoint_mushrooms = self.world.mushrooms.clone();
+            self.checkpoint_stars = self.world.stars.clone();
+            self.sfx.play_checkpoint();
+        }
+    }
+
+    /// Completes the level once every boss in it has been defeated.
+    fn check_boss_defeated(&mut self) {
+        let mut bosses = self
+            .enemies
+            .iter()
+            .filter(|enemy| enemy.is_boss())
+            .peekable();
+        if bosses.peek().is_none() || bosses.any(|boss| boss.alive) {
+            return;
+        }
+
+        self.finish_speedrun();
+        self.add_score_at(BOSS_BONUS, self.player.center());
+        self.add_shake(SHAKE_LANDING);
+        self.sfx.stop_music();
+        self.sfx.play_win();
+        self.complete_level();
+    }
+
+    /// Shows the results screen, which counts the time bonus into the score.
+    fn complete_level(&mut self) {
+        self.stats.finish(self.config.fixed_dt);
+        // There is no next level yet, so finishing one ends the run.
+        self.clear_saved_run();
+        self.state = GameState::LevelComplete;
+    }
+
+    /// Stops the clock and, in speedrun mode, keeps the run's time and ghost
+    /// if they are new bests.
+    fn finish_speedrun(&mut self) {
+        if self.practice_mode {
+            return;
+        }
+        if self.speedrun.finish() {
+            if let Err(error) = self.speedrun.save_best(BEST_TIME_PATH) {
+                eprintln!("Best time save error: {error}");
+            }
+        }
+        if self.speedrun.enabled {
+            self.record_ghost();
+        }
+    }
+
+    /// Whether the player has collected the level's `COINS_REQUIRED`.
+    fn goal_unlocked(&self) -> bool {
+        self.level_coins >= self.world.meta.coins_required
+    }
+
+    /// "collected/required" above the locked flagpole.
+    fn draw_goal_progress(&self) {
+        let pole = self.world.goal_trigger_rect(&self.config);
+        let text = format!("{}/{}", self.level_coins, self.world.meta.coins_required);
+        let font_size = 24.0;
+        let dim = measure_text(&text, None, font_size as u16, 1.0);
+        draw_text(
+            &text,
+            pole.center().x - dim.width * 0.5,
+            pole.y - 8.0,
+            font_size,
+            self.world.meta.theme.palette().hud_text,
+        );
+    }
+
+    /// Grabs the flagpole on contact: scores by grab height, snaps the player
+    /// onto the pole and starts the slide down.
     fn check_goal(&mut self) {
+        // The player already died this step and is fading out.
+        if self.transition.is_some() || !self.goal_unlocked() {
+            return;
+        }
         let goal_rect = self.world.goal_trigger_rect(&self.config);
-        if physics::rects_intersect(self.player.rect(), goal_rect) {
-            self.add_score(500);
-            self.sfx.stop_music();
-            self.sfx.play_win();
-            self.state = GameState::LevelComplete;
+        let player_rect = self.player.rect();
+        if !physics::rects_intersect(player_rect, goal_rect) {
+            return;
+        }
+
+        self.finish_speedrun();
+
+        let bonus = flagpole_bonus(player_rect.y, goal_rect);
+        self.add_score_at(bonus, vec2(goal_rect.center().x, player_rect.y));
+        self.sfx.stop_music();
+        self.sfx.play_win();
+
+        let size = self.player.size();
+        let to_y = goal_rect.bottom() - size.y;
+        self.player.pos = vec2(
+            goal_rect.center().x - size.x,
+            self.player.pos.y.clamp(goal_rect.y, to_y),
+        );
+        self.player.vel = Vec2::ZERO;
+        self.goal_slide = GoalSlide {
+            timer: 0.0,
+            from_y: self.player.pos.y,
+            to_y,
+        };
+        self.state = GameState::GoalSequence;
+    }
+
+    /// Slides the player down the pole, ignoring input, then completes the
+    /// level.
+    fn update_goal_slide(&mut self) {
+        let dt = self.config.fixed_dt;
+        self.world_time += dt;
+        self.world.update_platforms(dt);
+
+        self.goal_slide.timer += dt;
+        let t = (self.goal_slide.timer / self.config.goal_slide_time).min(1.0);
+        self.player.pos.y =
+            self.goal_slide.from_y + (self.goal_slide.to_y - self.goal_slide.from_y) * t;
+
+        particles::update(&mut self.particles, self.config.gravity, dt);
+        floating_text::update(&mut self.floating_texts, dt);
+        self.update_shake(dt);
+        self.camera.update(
+            self.player.center(),
+            self.player.facing_dir(),
+            true,
+            &self.config,
+            dt,
+        );
+
+        if t >= 1.0 {
+            self.complete_level();
+        }
+    }
+
+    /// Starts a warp if the player is standing on a pipe, returning whether
+    /// one started.
+    fn try_enter_pipe(&mut self) -> bool {
+        if !self.player.on_ground {
+            return false;
+        }
+        let rect = self.player.rect();
+        let center_x = rect.center().x;
+        let Some(pipe) = self.world.pipes.iter().find(|pipe| {
+            (rect.bottom() - pipe.rect.y).abs() < 1.0
+                && center_x >= pipe.rect.x
+                && center_x <= pipe.rect.right()
+        }) else {
+            return false;
+        };
+
+        self.player.pos.x = pipe.rect.center().x - rect.w * 0.5;
+        self.player.vel = Vec2::ZERO;
+        self.warp = WarpTransition {
+            timer: 0.0,
+            dest: pipe.dest,
+            from_y: self.player.pos.y,
+            arrived: false,
+        };
+        self.sfx.play_warp();
+        self.state = GameState::Warping;
+        true
+    }
+
+    /// Sinks the player into the pipe, moves them to the linked one once the
+    /// screen is dark, then fades back in. Physics stays paused throughout.
+    fn update_warp(&mut self) {
+        let dt = self.config.fixed_dt;
+        self.speedrun.tick();
+        self.stats.steps += 1;
+        self.ghost_trail.push(self.player.pos);
+        self.warp.timer += dt;
+
+        if !self.warp.arrived {
+            let t = (self.warp.timer / self.config.warp_sink_time).min(1.0);
+            self.player.pos.y = self.warp.from_y + self.player.size().y * t;
+            if t >= 1.0 {
+                let dest = self.world.pipes[self.warp.dest].rect;
+                let size = self.player.size();
+                self.player.pos = vec2(dest.center().x - size.x * 0.5, dest.y - size.y);
+                self.player.vel = Vec2::ZERO;
+                self.camera
+                    .snap(self.player.center(), self.player.facing_dir(), &self.config);
+                self.warp.arrived = true;
+                self.warp.timer = 0.0;
+            }
+        } else if self.warp.timer >= self.config.warp_fade_in_time {
+            self.state = GameState::Playing;
+        }
+
+        particles::update(&mut self.particles, self.config.gravity, dt);
+        floating_text::update(&mut self.floating_texts, dt);
+    }
+
+    /// Opacity of the black overlay drawn while warping.
+    fn warp_fade(&self) -> f32 {
+        let t = if self.warp.arrived {
+            1.0 - self.warp.timer / self.config.warp_fade_in_time
+        } else {
+            self.warp.timer / self.config.warp_sink_time
+        };
+        t.clamp(0.0, 1.0)
+    }
+
+    /// Launches the player and any enemies standing on a spring.
+    fn bounce_on_springs(&mut self) {
+        let g = self.config.gravity_dir;
+        if self.player.on_ground {
+            if let Some(idx) = self.world.spring_under(self.player.rect(), g) {
+                self.world.bounce_spring(idx, &self.config);
+                self.player.vel.y = -self.config.spring_speed * g;
+                self.player.on_ground = false;
+                self.sfx.play_boing(self.player.center().x, &self.camera);
+            }
+        }
+        for enemy in &mut self.enemies {
+            if !enemy.is_on_ground() {
+                continue;
+            }
+            if let Some(idx) = self.world.spring_under(enemy.rect(), g) {
+                self.world.bounce_spring(idx, &self.config);
+                enemy.launch(self.config.spring_speed * g);
+                self.sfx.play_boing(enemy.rect().center().x, &self.camera);
+            }
         }
     }
 
     fn check_fall_off(&mut self) {
-        let fall_limit = self.world.height as f32 * self.config.tile_size + 200.0;
-        if self.player.pos.y > fall_limit {
+        let margin = 200.0;
+        let fell_out = if self.config.gravity_dir < 0.0 {
+            self.player.pos.y + self.player.size().y < -margin
+        } else {
+            self.player.pos.y > self.world.height as f32 * self.config.tile_size + margin
+        };
+        if fell_out {
             self.player_died();
         }
     }
 
     fn capture_input(&mut self) {
+        if is_key_pressed(KeyCode::Minus) || is_key_pressed(KeyCode::KpSubtract) {
+            self.sfx
+                .set_master_volume(self.sfx.master_volume() - VOLUME_STEP);
+        }
+        if is_key_pressed(KeyCode::Equal) || is_key_pressed(KeyCode::KpAdd) {
+            self.sfx
+                .set_master_volume(self.sfx.master_volume() + VOLUME_STEP);
+        }
+        if is_key_pressed(KeyCode::M) {
+            self.sfx.toggle_mute();
+        }
+        if is_key_pressed(KeyCode::LeftBracket) {
+            self.time_scale = (self.time_scale - TIME_SCALE_STEP).max(0.0);
+        }
+        if is_key_pressed(KeyCode::RightBracket) {
+            self.time_scale = (self.time_scale + TIME_SCALE_STEP).min(MAX_TIME_SCALE);
+        }
+        // Title keys wait until a fade that's leaving the title finishes.
+        let on_title = self.state == GameState::Title && self.transition.is_none();
+        if on_title && is_key_pressed(KeyCode::E) {
+            self.open_editor();
+        }
+        if on_title && is_key_pressed(KeyCode::O) {
+            self.open_settings();
+        }
+        if on_title && is_key_pressed(KeyCode::L) {
+            self.open_level_select();
+        }
+        if on_title && is_key_pressed(KeyCode::T) {
+            self.speedrun.enabled = !self.speedrun.enabled;
+        }
+        if on_title && is_key_pressed(KeyCode::P) {
+            self.practice_mode = !self.practice_mode;
+        }
+        if on_title && is_key_pressed(KeyCode::C) {
+            self.continue_saved_run();
+        }
+        if is_key_pressed(KeyCode::F3) {
+            self.debug_overlay = !self.debug_overlay;
+        }
+        if is_key_pressed(KeyCode::F4) {
+            self.show_colliders = !self.show_colliders;
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            self.show_minimap = !self.show_minimap;
+        }
+        if is_key_pressed(KeyCode::F5) {
+            if self.record_inputs {
+                self.stop_recording();
+                if let Err(error) = self.save_recording(replay::REPLAY_PATH) {
+                    eprintln!("Replay save error: {error}");
+                }
+            } else {
+                self.start_recording();
+            }
+        }
+        if is_key_pressed(KeyCode::F8) {
+            if let Err(error) = self.load_replay(replay::REPLAY_PATH) {
+                eprintln!("Replay load error: {error}");
+            }
+        }
+
         self.input.move_x = read_move_x();
+        self.input.crouch_held = read_crouch_held();
+        self.input.up_held = read_up_held();
+        self.input.run_held = is_key_down(KeyCode::Z);
         self.input.jump_pressed |= read_jump_pressed();
         self.input.jump_released |= read_jump_released();
+        self.input.dash_pressed |= read_dash_pressed();
+        self.input.fire_pressed |= is_key_pressed(KeyCode::X);
         self.input.start_pressed |= is_key_pressed(KeyCode::Enter);
         self.input.restart_pressed |= is_key_pressed(KeyCode::R);
         self.input.quit_pressed |= is_key_pressed(KeyCode::Escape);
+        if self.touch_controls {
+            self.capture_touch_input();
+        }
+        self.capture_gamepad_input();
+        self.input.menu_up_pressed |= is_key_pressed(KeyCode::Up) || is_key_pressed(KeyCode::W);
+        self.input.menu_down_pressed |= is_key_pressed(KeyCode::Down) || is_key_pressed(KeyCode::S);
+    }
+
+    /// Adds the on-screen buttons to the keyboard input. Held arrow keys win
+    /// over the buttons, and jump works from either.
+    fn capture_touch_input(&mut self) {
+        let zones = if self.state == GameState::Playing {
+            let screen = vec2(screen_width(), screen_height());
+            touch::pressed_zones(&touch::pointer_positions(), screen)
+        } else {
+            TouchZones::default()
+        };
+        if self.input.move_x == 0.0 {
+            self.input.move_x = zones.move_x();
+        }
+        self.input.jump_pressed |= zones.jump && !self.touch_zones.jump;
+        self.input.jump_released |= !zones.jump && self.touch_zones.jump;
+        self.touch_zones = zones;
+    }
+
+    /// ORs the first gamepad into the keyboard input: the d-pad or left
+    /// stick moves when no arrow key is held, south jumps, start confirms
+    /// and select quits.
+    fn capture_gamepad_input(&mut self) {
+        let (pad, last) = self.gamepads.poll();
+        if self.input.move_x == 0.0 {
+            self.input.move_x = pad.move_x;
+        }
+        self.input.jump_pressed |= pad.jump && !last.jump;
+        self.input.jump_released |= !pad.jump && last.jump;
+        self.input.start_pressed |= pad.start && !last.start;
+        self.input.quit_pressed |= pad.back && !last.back;
     }
 
     fn consume_fixed_input(&mut self) -> InputState {
         let snapshot = self.input;
         self.input.jump_pressed = false;
         self.input.jump_released = false;
+        self.input.dash_pressed = false;
+        self.input.fire_pressed = false;
         self.input.start_pressed = false;
         self.input.restart_pressed = false;
         self.input.quit_pressed = false;
+        self.input.menu_up_pressed = false;
+        self.input.menu_down_pressed = false;
         snapshot
     }
 }
 
-fn draw_title() {
+/// `base` scaled for `difficulty`, with `world`'s header overrides on top.
+fn level_config(base: &Config, difficulty: Difficulty, world: &World) -> Config {
+    let mut config = difficulty.apply(base);
+    if let Some(gravity) = world.meta.gravity {
+        config.gravity = gravity;
+    }
+    if let Some(speed) = world.meta.terminal_velocity {
+        config.terminal_velocity = speed;
+    }
+    if let Some(speed) = world.meta.jump_speed {
+        config.jump_speed = speed;
+    }
+    if let Some(gravity_dir) = world.meta.gravity_dir {
+        config.gravity_dir = gravity_dir;
+    }
+    config
+}
+
+/// Whether `enemy` is close enough to the camera to move and be drawn.
+/// Enemies outside `Config::active_radius` stay frozen where they are.
+fn is_active(enemy: &Enemy, focus: Vec2, config: &Config) -> bool {
+    enemy.rect().center().distance_squared(focus) <= config.active_radius.powi(2)
+}
+
+fn spawn_enemies(world: &World, config: &Config) -> Vec<Enemy> {
+    world
+        .enemy_spawns
+        .iter()
+        .copied()
+        .map(|spawn| Enemy::new(spawn, world, config))
+        .collect()
+}
+
+/// Pickup box for a coin centered at `coin`.
+fn coin_rect(coin: Vec2, config: &Config) -> Rect {
+    let radius = config.tile_size * 0.2;
+    Rect::new(coin.x - radius, coin.y - radius, radius * 2.0, radius * 2.0)
+}
+
+/// Pickup box for a star centered at `star`.
+fn star_rect(star: Vec2, config: &Config) -> Rect {
+    let radius = config.tile_size * 0.35;
+    Rect::new(star.x - radius, star.y - radius, radius * 2.0, radius * 2.0)
+}
+
+/// Points for the `chain`-th kill in a combo: 100, 200, 400, ... capped.
+fn combo_score(chain: u32) -> u32 {
+    100 << chain.saturating_sub(1).min(MAX_COMBO_STEP)
+}
+
+/// Draws `texture` centered on the window at the largest integer scale that
+/// fits, with black bars filling the rest.
+fn draw_letterboxed(texture: &Texture2D) {
+    let size = texture.size();
+    let scale = (screen_width() / size.x)
+        .min(screen_height() / size.y)
+        .floor()
+        .max(1.0);
+    let dest = size * scale;
+    clear_background(BLACK);
+    draw_texture_ex(
+        texture,
+        ((screen_width() - dest.x) * 0.5).floor(),
+        ((screen_height() - dest.y) * 0.5).floor(),
+        WHITE,
+        DrawTextureParams {
+            dest_size: Some(dest),
+            ..Default::default()
+        },
+    );
+}
+
+/// Points for grabbing `pole` at height `contact_y`: the higher, the more.
+fn flagpole_bonus(contact_y: f32, pole: Rect) -> u32 {
+    let height = ((pole.bottom() - contact_y) / pole.h).clamp(0.0, 1.0);
+    FLAGPOLE_BONUSES
+        .iter()
+        .find(|(min_height, _)| height >= *min_height)
+        .map_or(FLAGPOLE_MIN_BONUS, |(_, points)| *points)
+}
+
+fn draw_title(selection: usize, can_continue: bool, speedrun_timer: bool, practice_mode: bool) {
     let title = "Rusty Platformer";
-    let subtitle = "Press Enter to Start";
-
     let title_size = 56;
-    let subtitle_size = 28;
     let title_dim = measure_text(title, None, title_size, 1.0);
-    let subtitle_dim = measure_text(subtitle, None, subtitle_size, 1.0);
-
     let center_x = screen_width() * 0.5;
     let center_y = screen_height() * 0.5;
-
     draw_text(
         title,
         center_x - title_dim.width * 0.5,
-        center_y - 20.0,
+        center_y - 100.0,
         title_size as f32,
         BLACK,
     );
 
-    draw_text(
-        subtitle,
-        center_x - subtitle_dim.width * 0.5,
-        center_y + 30.0,
-        subtitle_size as f32,
-        DARKGRAY,
+    let option_size = 32;
+    for (idx, option) in TITLE_OPTIONS.iter().enumerate() {
+        let selected = idx == selection;
+        let label = if selected {
+            format!("> {} <", option.label())
+        } else {
+            option.label().to_string()
+        };
+        let dim = measure_text(&label, None, option_size, 1.0);
+        draw_text(
+            &label,
+            center_x - dim.width * 0.5,
+            center_y - 40.0 + idx as f32 * 36.0,
+            option_size as f32,
+            if selected { MAROON } else { DARKGRAY },
+        );
+    }
+
+    let on_off = |on: bool| if on { "on" } else { "off" };
+    let mut hints = vec![
+        "Up/Down to choose, Enter to confirm, E to edit the level".to_string(),
+        format!(
+            "Press T to toggle the speedrun timer ({})",
+            on_off(speedrun_timer)
+        ),
+        format!(
+            "Press P to toggle practice mode ({})",
+            on_off(practice_mode)
+        ),
+    ];
+    if can_continue {
+        hints.insert(0, "Press C to Continue your saved run".to_string());
+    }
+    let hint_size = 20;
+    for (idx, hint) in hints.iter().enumerate() {
+        let hint_dim = measure_text(hint, None, hint_size, 1.0);
+        draw_text(
+            hint,
+            center_x - hint_dim.width * 0.5,
+            center_y + 130.0 + idx as f32 * 26.0,
+            hint_size as f32,
+            DARKGRAY,
+        );
+    }
+}
+
+fn draw_boss_health(health: u32, max_health: u32, color: Color) {
+    let width = 320.0;
+    let height = 14.0;
+    let x = (screen_width() - width) * 0.5;
+    let y = 64.0;
+    let fill = health as f32 / max_health.max(1) as f32;
+    draw_text("BOSS", x, y - 6.0, 22.0, color);
+    draw_rectangle(x, y, width, height, Color::new(0.0, 0.0, 0.0, 0.5));
+    draw_rectangle(
+        x,
+        y,
+        width * fill,
+        height,
+        Color::new(0.85, 0.15, 0.15, 1.0),
     );
+    draw_rectangle_lines(x, y, width, height, 2.0, color);
 }
 
-fn draw_hud(high_score: u32, score: u32) {
+fn draw_hud(high_score: u32, score: u32, coins: u32, lives: u32, color: Color) {
     let size = 26.0;
     draw_text(
         &format!("High Score: {high_score}"),
         16.0,
         30.0,
         size,
-        BLACK,
+        color,
+    );
+    draw_text(&format!("Score: {score}"), 16.0, 58.0, size, color);
+
+    draw_circle(24.0, 78.0, 7.0, COIN_COLOR);
+    draw_text(&format!("x {coins}"), 38.0, 86.0, size, color);
+    draw_text(&format!("Lives: {lives}"), 16.0, 114.0, size, color);
+}
+
+fn draw_level_name(name: &str, color: Color) {
+    if name.is_empty() {
+        return;
+    }
+    let size = 24.0;
+    let dims = measure_text(name, None, size as u16, 1.0);
+    draw_text(name, (screen_width() - dims.width) * 0.5, 30.0, size, color);
+}
+
+fn draw_volume(master_volume: f32, muted: bool, color: Color) {
+    let label = if muted {
+        "Muted".to_string()
+    } else {
+        format!("Volume: {}%", (master_volume * 100.0).round() as i32)
+    };
+    let size = 22.0;
+    let dims = measure_text(&label, None, size as u16, 1.0);
+    draw_text(
+        &label,
+        screen_width() - dims.width - 16.0,
+        30.0,
+        size,
+        color,
     );
-    draw_text(&format!("Score: {score}"), 16.0, 58.0, size, BLACK);
 }
 
-fn draw_centered_text(text: &str, font_size: f32, color: Color) {
-    let dims = measure_text(text, None, font_size as u16, 1.0);
+fn draw_time_scale(time_scale: f32, color: Color) {
+    let label = format!("Speed x{time_scale:.2}");
+    let size = 22.0;
+    let dims = measure_text(&label, None, size as u16, 1.0);
     draw_text(
-        text,
+        &label,
         (screen_width() - dims.width) * 0.5,
-        screen_height() * 0.5,
-        font_size,
+        56.0,
+        size,
         color,
     );
 }
 
+fn draw_speedrun_timer(elapsed: f64, best: Option<f64>, color: Color) {
+    let size = 24.0;
+    let mut lines = vec![speedrun::format_time(elapsed)];
+    if let Some(best) = best {
+        lines.push(format!("Best {}", speedrun::format_time(best)));
+    }
+    for (idx, line) in lines.iter().enumerate() {
+        let dims = measure_text(line, None, size as u16, 1.0);
+        draw_text(
+            line,
+            screen_width() - dims.width - 16.0,
+            58.0 + idx as f32 * 26.0,
+            size,
+            color,
+        );
+    }
+}
+
 fn read_move_x() -> f32 {
     let mut move_x = 0.0;
     if is_key_down(KeyCode::Left) || is_key_down(KeyCode::A) {
@@ -494,3 +2947,1054 @@ fn read_jump_pressed() -> bool {
 fn read_jump_released() -> bool {
     is_key_released(KeyCode::Space) || is_key_released(KeyCode::Up) || is_key_released(KeyCode::W)
 }
+
+fn read_dash_pressed() -> bool {
+    is_key_pressed(KeyCode::LeftShift) || is_key_pressed(KeyCode::RightShift)
+}
+
+fn read_crouch_held() -> bool {
+    is_key_down(KeyCode::Down) || is_key_down(KeyCode::S)
+}
+
+fn read_up_held() -> bool {
+    is_key_down(KeyCode::Up) || is_key_down(KeyCode::W)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LEVEL: &str = "\
+........................................
+..........C.......C.....................
+..P..C......C...C......C.............G..
+########################################
+";
+
+    /// Leaves the title screen and waits out the fade into the level.
+    fn start(game: &mut Game) {
+        game.step(InputState {
+            start_pressed: true,
+            ..Default::default()
+        });
+        while game.transition.is_some() {
+            game.step(InputState::default());
+        }
+    }
+
+    fn scripted_input(frame: usize) -> InputState {
+        InputState {
+            move_x: 1.0,
+            jump_pressed: frame % 45 == 10,
+            jump_released: frame % 45 == 25,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn title_menu_wraps_and_opens_the_chosen_screen() {
+        let mut game = Game::headless(LEVEL, Config::default()).expect("test level is valid");
+        let up = InputState {
+            menu_up_pressed: true,
+            ..Default::default()
+        };
+        let down = InputState {
+            menu_down_pressed: true,
+            ..Default::default()
+        };
+        let enter = InputState {
+            start_pressed: true,
+            ..Default::default()
+        };
+
+        game.step(up);
+        assert_eq!(TITLE_OPTIONS[game.title_selection], TitleOption::Quit);
+        game.step(down);
+        game.step(down);
+        game.step(down);
+        assert_eq!(TITLE_OPTIONS[game.title_selection], TitleOption::Settings);
+        game.step(enter);
+        assert_eq!(game.state, GameState::Settings);
+
+        game.state = GameState::Title;
+        game.step(up);
+        game.step(enter);
+        assert_eq!(game.state, GameState::LevelSelect);
+
+        game.state = GameState::Title;
+        game.step(up);
+        game.step(up);
+        assert!(!game.should_quit());
+        game.step(enter);
+        assert!(game.should_quit());
+        assert_eq!(game.state, GameState::Title);
+    }
+
+    #[test]
+    fn level_header_overrides_gravity() {
+        let level = format!("NAME=Low Gravity\nGRAVITY=500\nTHEME=night\n\n{LEVEL}");
+        let game = Game::headless(&level, Config::default()).expect("test level is valid");
+
+        assert_eq!(game.config.gravity, 500.0);
+        assert_eq!(game.world.meta.name, "Low Gravity");
+        assert_eq!(game.world.meta.theme, theme::LevelTheme::Night);
+        assert_eq!(game.world.height, 4);
+    }
+
+    #[test]
+    fn level_header_overrides_fall_and_jump_speed() {
+        let level = format!("TERMINAL_VELOCITY=150\nJUMP_SPEED=300.5\n\n{LEVEL}");
+        let game = Game::headless(&level, Config::default()).expect("test level is valid");
+        assert_eq!(game.config.terminal_velocity, 150.0);
+        assert_eq!(game.config.jump_speed, 300.5);
+        assert_eq!(
+            world::LevelMeta::parse(&level).0.to_header(),
+            "TERMINAL_VELOCITY=150\nJUMP_SPEED=300.5\n\n"
+        );
+
+        let level = format!("TERMINAL_VELOCITY=-5\nJUMP_SPEED=fast\n\n{LEVEL}");
+        let game = Game::headless(&level, Config::default()).expect("test level is valid");
+        let defaults = Config::default();
+        assert_eq!(game.config.terminal_velocity, defaults.terminal_velocity);
+        assert_eq!(game.config.jump_speed, defaults.jump_speed);
+    }
+
+    #[test]
+    fn holding_right_walks_past_x_400() {
+        let mut game = Game::headless(LEVEL, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let hold_right = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..120 {
+            game.step(hold_right);
+        }
+
+        assert!(
+            game.player.pos.x > 400.0,
+            "player at x={}",
+            game.player.pos.x
+        );
+    }
+
+    #[test]
+    fn climbing_a_tall_level_moves_the_camera_up() {
+        let mut rows = vec![".".repeat(12); 6];
+        for row in 6..15 {
+            let step = 17 - row;
+            rows.push(format!("{}{}", ".".repeat(step), "#".repeat(12 - step)));
+        }
+        rows[14].replace_range(0..2, "GP");
+        rows.push("#".repeat(12));
+        let level = rows.join("\n") + "\n";
+        let mut game = Game::headless(&level, Config::default()).expect("test level is valid");
+        assert!(game.world.height > game.world.width);
+        start(&mut game);
+
+        let start_y = game.player.pos.y;
+        let start_focus = game.camera.focus().y;
+        for frame in 0..900 {
+            game.step(scripted_input(frame));
+        }
+
+        let tile = game.config.tile_size;
+        assert!(
+            game.player.pos.y < start_y - tile * 6.0,
+            "player only climbed from y={start_y} to y={}",
+            game.player.pos.y
+        );
+        assert!(
+            game.camera.focus().y < start_focus - tile * 5.0,
+            "camera focus only moved from y={start_focus} to y={}",
+            game.camera.focus().y
+        );
+    }
+
+    #[test]
+    fn falling_down_a_tall_shaft_lands_instead_of_dying() {
+        let mut level = String::from("......\n.P....\n");
+        level += &"#....#\n".repeat(30);
+        level += "#..G.#\n######\n";
+        let mut game = Game::headless(&level, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let start_focus = game.camera.focus().y;
+        for _ in 0..300 {
+            game.step(InputState::default());
+        }
+
+        let tile = game.config.tile_size;
+        assert!(game.player.on_ground);
+        assert!(game.player.pos.y > tile * 30.0);
+        assert_eq!(game.lives, game.config.starting_lives);
+        assert!(
+            game.transition.is_none(),
+            "the fall shouldn't count as dying"
+        );
+        assert!(game.camera.focus().y > start_focus + tile * 20.0);
+    }
+
+    #[test]
+    fn climbing_a_ladder_stands_the_player_on_its_top() {
+        let level = "\
+..........
+....##H###
+......H...
+.....PH..G
+##########
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let mut steps = 0;
+        while !game.player.on_ladder {
+            game.step(InputState {
+                move_x: 1.0,
+                up_held: true,
+                ..Default::default()
+            });
+            steps += 1;
+            assert!(steps < 60, "never grabbed the ladder");
+        }
+        for _ in 0..90 {
+            game.step(InputState {
+                up_held: true,
+                ..Default::default()
+            });
+        }
+
+        let tile = game.config.tile_size;
+        assert!(!game.player.on_ladder);
+        assert!(game.player.on_ground);
+        assert_eq!(game.player.rect().bottom(), tile);
+
+        for _ in 0..30 {
+            game.step(InputState::default());
+        }
+        assert!(game.player.on_ground, "fell through the ladder top");
+        assert_eq!(game.player.rect().bottom(), tile);
+    }
+
+    #[test]
+    fn springs_launch_on_landing_but_not_from_the_side() {
+        let level = "\
+..........
+..........
+..P.......
+..........
+..........
+..........
+..J.....G.
+##########
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let spring_top = 6.0 * game.config.tile_size;
+        let jump_height = game.config.jump_speed_max.powi(2) / (2.0 * game.config.gravity);
+
+        let mut highest = f32::MAX;
+        let mut launched = false;
+        for _ in 0..120 {
+            game.step(InputState::default());
+            launched |= game.player.vel.y <= -game.config.spring_speed * 0.9;
+            if launched {
+                highest = highest.min(game.player.rect().bottom());
+            }
+        }
+        assert!(launched, "never launched");
+        assert!(
+            spring_top - highest > jump_height * 1.5,
+            "only rose {} px",
+            spring_top - highest
+        );
+
+        let side = "\
+..........
+.P.J....G.
+##########
+";
+        let mut game = Game::headless(side, Config::default()).expect("test level is valid");
+        start(&mut game);
+        for _ in 0..60 {
+            game.step(InputState {
+                move_x: 1.0,
+                ..Default::default()
+            });
+            assert!(game.player.vel.y >= 0.0, "brushing the side launched");
+        }
+    }
+
+    #[test]
+    fn upside_down_level_walks_on_the_ceiling() {
+        let level = "\
+GRAVITY_DIR=up
+
+############
+.P........G.
+.....E......
+............
+............
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        assert_eq!(game.config.gravity_dir, -1.0);
+        start(&mut game);
+        let tile = game.config.tile_size;
+        assert_eq!(game.enemies[0].rect().y, tile, "enemy not on the ceiling");
+
+        for _ in 0..10 {
+            game.step(InputState::default());
+        }
+        assert!(game.player.on_ground);
+        assert_eq!(game.player.rect().y, tile);
+
+        game.step(InputState {
+            jump_pressed: true,
+            ..Default::default()
+        });
+        let mut lowest = game.player.pos.y;
+        let mut landed_again = false;
+        for _ in 0..90 {
+            game.step(InputState::default());
+            lowest = lowest.max(game.player.pos.y);
+            landed_again |= game.player.on_ground;
+        }
+        assert!(lowest > tile * 2.0, "jump only reached y={lowest}");
+        assert!(landed_again);
+        assert_eq!(game.player.rect().y, tile);
+    }
+
+    #[test]
+    fn upside_down_springs_and_ladders_push_toward_the_floor() {
+        let level = "\
+GRAVITY_DIR=up
+
+##########
+..J..H..G.
+.....H....
+..P..H....
+..........
+..........
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let mut launched = false;
+        for _ in 0..60 {
+            game.step(InputState::default());
+            launched |= game.player.vel.y >= game.config.spring_speed * 0.9;
+        }
+        assert!(launched, "spring never launched the player away from it");
+
+        let tile = game.config.tile_size;
+        game.player.pos = vec2(5.0 * tile, 2.0 * tile);
+        game.player.vel = Vec2::ZERO;
+        game.step(InputState {
+            up_held: true,
+            ..Default::default()
+        });
+        assert!(game.player.on_ladder);
+        game.step(InputState {
+            jump_pressed: true,
+            ..Default::default()
+        });
+        assert!(!game.player.on_ladder);
+        assert!(
+            game.player.vel.y > 0.0,
+            "jumped off the ladder toward the ceiling"
+        );
+    }
+
+    #[test]
+    fn deaths_fade_out_before_respawning_and_enter_skips_the_fade() {
+        let level = "\
+..........
+.P....G...
+###..#####
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let lives = game.lives;
+        let hold_right = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        let fall_into_pit = |game: &mut Game| {
+            for _ in 0..240 {
+                game.step(hold_right);
+                if game.transition.is_some() {
+                    return;
+                }
+            }
+            panic!("never fell into the pit");
+        };
+
+        fall_into_pit(&mut game);
+        let half = (game.config.fade_time / game.config.fixed_dt).round() as usize;
+        for _ in 0..half - 1 {
+            game.step(InputState::default());
+        }
+        assert_eq!(game.lives, lives, "respawned before the screen went black");
+        for _ in 0..2 {
+            game.step(InputState::default());
+        }
+        assert_eq!(game.lives, lives - 1);
+        while game.transition.is_some() {
+            game.step(InputState::default());
+        }
+
+        fall_into_pit(&mut game);
+        game.step(InputState {
+            start_pressed: true,
+            ..Default::default()
+        });
+        assert!(game.transition.is_none());
+        assert_eq!(game.lives, lives - 2);
+    }
+
+    #[test]
+    fn practice_mode_deaths_keep_lives_and_coins() {
+        let level = "\
+..........
+.P.C..G...
+####..####
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        game.practice_mode = true;
+        start(&mut game);
+        let lives = game.lives;
+
+        let hold_right = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        let mut respawned = false;
+        for _ in 0..240 {
+            let before = game.player.pos.x;
+            game.step(hold_right);
+            respawned |= game.player.pos.x < before - game.config.tile_size;
+        }
+
+        assert!(respawned, "never fell into the pit");
+        assert_eq!(game.lives, lives);
+        assert_eq!(game.coins, 1);
+        assert!(game.world.coins.is_empty());
+    }
+
+    #[test]
+    fn respawning_at_a_checkpoint_keeps_blocks_and_power_ups_used() {
+        let level = "\
+........................
+.....?..B...............
+..P...M.*....~.......G..
+########################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let config = game.config;
+        game.world.question_blocks[0].used = true;
+        game.world.break_brick(0, &config);
+        game.world.mushrooms.clear();
+        game.world.stars.clear();
+
+        game.player.pos = game.world.checkpoints[0];
+        game.step(InputState::default());
+        assert!(game.active_checkpoint.is_some());
+
+        game.player_died();
+        while game.transition.is_some() {
+            game.step(InputState::default());
+        }
+        let checkpoint_x = game.world.checkpoints[0].x;
+        assert!((game.player.pos.x - checkpoint_x).abs() < config.tile_size);
+        assert!(game.world.question_blocks[0].used);
+        assert!(game.world.bricks.is_empty());
+        assert!(game.world.mushrooms.is_empty());
+        assert!(game.world.stars.is_empty());
+    }
+
+    #[test]
+    fn practice_runs_leave_the_high_score_alone() {
+        let level = "\
+..........
+.P.C..G...
+##########
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        game.practice_mode = true;
+        start(&mut game);
+        let high_score = game.high_score;
+
+        let hold_right = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..60 {
+            game.step(hold_right);
+        }
+
+        assert!(game.score > high_score, "never scored");
+        assert_eq!(game.high_score, high_score);
+    }
+
+    #[test]
+    fn enemies_respawn_on_revisit_only_when_the_level_asks() {
+        let rows = format!(
+            "{}\n.P..E{}G\n{}\n",
+            ".".repeat(40),
+            ".".repeat(34),
+            "#".repeat(40)
+        );
+        for respawn in [false, true] {
+            let header = if respawn {
+                "RESPAWN_ENEMIES=on\n\n"
+            } else {
+                ""
+            };
+            let level = format!("{header}{rows}");
+            let mut game = Game::headless(&level, Config::default()).expect("test level is valid");
+            start(&mut game);
+            let config = game.config;
+            game.enemies[0].kill(&config);
+            for _ in 0..60 {
+                game.step(InputState::default());
+            }
+            assert!(!game.enemies[0].alive, "respawned with the player nearby");
+
+            game.player.pos.x = 36.0 * config.tile_size;
+            game.step(InputState::default());
+            assert_eq!(game.enemies[0].alive, respawn, "RESPAWN_ENEMIES={respawn}");
+        }
+    }
+
+    #[test]
+    fn goal_hint_shows_after_idling_and_hides_on_movement() {
+        let level = "\
+HINTS=on
+
+..........
+.P......G.
+##########
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let idle_steps = (game.config.hint_idle_time / game.config.fixed_dt).ceil() as usize;
+        for _ in 0..idle_steps - 5 {
+            game.step(InputState::default());
+        }
+        assert!(!game.shows_goal_hint());
+        for _ in 0..10 {
+            game.step(InputState::default());
+        }
+        assert!(game.shows_goal_hint());
+
+        game.step(InputState {
+            move_x: 1.0,
+            ..Default::default()
+        });
+        assert!(!game.shows_goal_hint());
+    }
+
+    #[test]
+    fn far_enemies_stay_frozen_on_a_crowded_level() {
+        let width = 1000;
+        let enemies: String = (0..width - 4)
+            .map(|col| if col % 2 == 0 { 'E' } else { '.' })
+            .collect();
+        let level = format!(
+            "{}\n.P{enemies}.G\n{}\n",
+            ".".repeat(width),
+            "#".repeat(width)
+        );
+        let mut game = Game::headless(&level, Config::default()).expect("test level is valid");
+        assert_eq!(game.enemies.len(), 498);
+        start(&mut game);
+        let far = game.enemies.len() - 1;
+        let far_pos = game.enemies[far].pos;
+
+        let started = std::time::Instant::now();
+        for frame in 0..600 {
+            game.step(scripted_input(frame));
+        }
+        let elapsed = started.elapsed();
+
+        assert_eq!(game.enemies[far].pos, far_pos);
+        assert!(
+            elapsed < std::time::Duration::from_secs(10),
+            "600 steps took {elapsed:?}"
+        );
+    }
+
+    /// Benchmark-style guard against accidental O(n²) work per step: a long
+    /// generated level full of enemies, coins and blocks must run thousands
+    /// of steps within a budget far above what it needs today. Run with
+    /// `--nocapture` to see the step rate.
+    #[test]
+    fn long_crowded_level_steps_within_budget() {
+        let width = 1500;
+        let mut top = String::new();
+        let mut blocks = String::new();
+        let mut floor = String::new();
+        for col in 0..width {
+            top.push(if col % 7 == 3 { 'C' } else { '.' });
+            blocks.push(if col % 12 == 6 {
+                'B'
+            } else if col % 12 == 7 {
+                '?'
+            } else {
+                '.'
+            });
+            floor.push(match col {
+                1 => 'P',
+                _ if col == width - 2 => 'G',
+                // Every third enemy slot holds a flyer instead of a walker.
+                _ if col % 30 == 15 => 'F',
+                _ if col % 10 == 5 => 'E',
+                _ => '.',
+            });
+        }
+        let ground = "#".repeat(width);
+        let level = format!("{top}\n{blocks}\n{top}\n{floor}\n{ground}\n");
+        let mut game = Game::headless(&level, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let steps = 3000;
+        let started = std::time::Instant::now();
+        for frame in 0..steps {
+            game.step(scripted_input(frame));
+        }
+        let elapsed = started.elapsed();
+        eprintln!(
+            "{steps} steps in {elapsed:?} ({:.0} steps/s)",
+            steps as f64 / elapsed.as_secs_f64()
+        );
+        assert!(
+            elapsed < std::time::Duration::from_secs(10),
+            "{steps} steps took {elapsed:?}"
+        );
+    }
+
+    #[test]
+    fn pressing_down_on_a_pipe_warps_to_its_partner() {
+        let level = "\
+..............................
+..P...........................
+..O.....................O...G.
+##############################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        for _ in 0..30 {
+            game.step(InputState::default());
+        }
+        assert!(game.player.on_ground);
+
+        let crouch = InputState {
+            crouch_held: true,
+            ..Default::default()
+        };
+        game.step(crouch);
+        assert_eq!(game.state, GameState::Warping);
+
+        let warp_steps = ((game.config.warp_sink_time + game.config.warp_fade_in_time)
+            / game.config.fixed_dt)
+            .ceil() as usize;
+        for _ in 0..warp_steps + 2 {
+            game.step(crouch);
+        }
+
+        let dest = game.world.pipes[1].rect;
+        assert_eq!(game.state, GameState::Playing);
+        assert!(
+            (game.player.rect().center().x - dest.center().x).abs() < 1.0,
+            "player at x={} after warping",
+            game.player.pos.x
+        );
+        assert!((game.player.rect().bottom() - dest.y).abs() < 1.0);
+    }
+
+    #[test]
+    fn turret_shots_hit_the_player() {
+        let level = "\
+..................
+..P.........T...G.
+##################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let lives = game.lives;
+
+        let steps = ((game.config.turret_fire_interval + game.config.turret_shot_lifetime)
+            / game.config.fixed_dt) as usize;
+        for _ in 0..steps {
+            game.step(InputState::default());
+            if game.lives < lives {
+                break;
+            }
+        }
+
+        assert_eq!(game.lives, lives - 1);
+        assert!(game.enemy_projectiles.is_empty());
+    }
+
+    #[test]
+    fn defeating_the_boss_completes_the_level() {
+        let level = "\
+........................................
+..P.........................Z.........G.
+########################################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let boss = game
+            .enemies
+            .iter()
+            .position(Enemy::is_boss)
+            .expect("level has a boss");
+        let hurt_steps = (game.config.boss_hurt_time / game.config.fixed_dt).ceil() as usize + 1;
+        let stomp = |game: &mut Game| {
+            let boss_rect = game.enemies[boss].rect();
+            let size = game.player.size();
+            game.player.pos = vec2(
+                boss_rect.center().x - size.x * 0.5,
+                boss_rect.y - size.y - 2.0,
+            );
+            game.player.vel.y = game.config.terminal_velocity;
+            game.step(InputState::default());
+        };
+        let send_home = |game: &mut Game| {
+            game.player.pos = game.world.player_spawn;
+            game.player.vel = Vec2::ZERO;
+        };
+
+        for hit in 1..game.config.boss_health {
+            stomp(&mut game);
+            assert_eq!(game.enemies[boss].health(), game.config.boss_health - hit);
+            stomp(&mut game);
+            assert_eq!(
+                game.enemies[boss].health(),
+                game.config.boss_health - hit,
+                "boss is briefly immune"
+            );
+
+            if hit == 1 {
+                // Shrugging off hits doesn't make the boss harmless.
+                game.player.set_powered(true);
+                let boss_rect = game.enemies[boss].rect();
+                game.player.pos = vec2(
+                    boss_rect.x - game.player.size().x + 2.0,
+                    boss_rect.bottom() - game.player.size().y,
+                );
+                game.player.vel = Vec2::ZERO;
+                game.step(InputState::default());
+                assert!(!game.player.is_powered(), "touching a hurt boss hurts");
+            }
+
+            send_home(&mut game);
+            for _ in 0..hurt_steps {
+                game.step(InputState::default());
+            }
+            assert_eq!(game.state, GameState::Playing);
+        }
+
+        stomp(&mut game);
+        assert!(!game.enemies[boss].alive);
+        assert_eq!(game.state, GameState::LevelComplete);
+    }
+
+    #[test]
+    fn stomping_a_flashing_boss_again_pays_nothing() {
+        let level = "\
+........................................
+..P.........................Z.........G.
+########################################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let boss = game
+            .enemies
+            .iter()
+            .position(Enemy::is_boss)
+            .expect("level has a boss");
+        let stomp = |game: &mut Game| {
+            let boss_rect = game.enemies[boss].rect();
+            let size = game.player.size();
+            game.player.pos = vec2(
+                boss_rect.center().x - size.x * 0.5,
+                boss_rect.y - size.y - 2.0,
+            );
+            game.player.vel.y = game.config.terminal_velocity;
+            game.step(InputState::default());
+        };
+
+        stomp(&mut game);
+        assert!(game.player.vel.y < 0.0, "first stomp bounces");
+        let (score, combo, stomps) = (game.score, game.stomp_combo, game.stats.stomps);
+
+        stomp(&mut game);
+        assert!(game.enemies[boss].alive);
+        assert!(game.player.vel.y > 0.0, "ignored stomp doesn't bounce");
+        assert_eq!(game.score, score);
+        assert_eq!(game.stomp_combo, combo);
+        assert_eq!(game.stats.stomps, stomps);
+    }
+
+    #[test]
+    fn goal_stays_locked_until_enough_coins_are_collected() {
+        let level = "\
+COINS_REQUIRED=1
+
+..........
+.P..G..C..
+##########
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        assert!(!game.goal_unlocked());
+
+        let hold = |move_x| InputState {
+            move_x,
+            ..Default::default()
+        };
+        for _ in 0..240 {
+            game.step(hold(1.0));
+            assert_eq!(game.state, GameState::Playing, "goal opened early");
+            if game.goal_unlocked() {
+                break;
+            }
+        }
+        assert!(game.world.coins.is_empty());
+        assert!(game.goal_unlocked());
+
+        for _ in 0..240 {
+            game.step(hold(-1.0));
+            if game.state == GameState::GoalSequence {
+                break;
+            }
+        }
+        assert_eq!(game.state, GameState::GoalSequence);
+    }
+
+    #[test]
+    fn touching_the_goal_slides_down_the_pole_before_completing() {
+        let mut game = Game::headless(LEVEL, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let hold_right = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..600 {
+            if game.state == GameState::GoalSequence {
+                break;
+            }
+            game.step(hold_right);
+        }
+        assert_eq!(game.state, GameState::GoalSequence);
+
+        let grab_x = game.player.pos.x;
+        let slide_steps = (game.config.goal_slide_time / game.config.fixed_dt).round() as usize;
+        for _ in 0..slide_steps - 5 {
+            game.step(hold_right);
+        }
+        assert_eq!(game.state, GameState::GoalSequence);
+        assert_eq!(game.player.pos.x, grab_x, "input is ignored while sliding");
+
+        for _ in 0..10 {
+            game.step(hold_right);
+        }
+        assert_eq!(game.state, GameState::LevelComplete);
+        let pole = game.world.goal_trigger_rect(&game.config);
+        assert_eq!(game.player.rect().bottom(), pole.bottom());
+    }
+
+    #[test]
+    fn results_screen_counts_the_time_bonus_into_the_score() {
+        let level = "\
+..............
+..............
+.P..C....E..G.
+##############
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+        let enemy_rect = game.enemies[0].rect();
+        let size = game.player.size();
+        game.player.pos = vec2(
+            enemy_rect.center().x - size.x * 0.5,
+            enemy_rect.y - size.y - 2.0,
+        );
+        game.player.vel.y = game.config.terminal_velocity;
+        game.step(InputState::default());
+        let coin = game.world.coins[0];
+        game.player.pos.x = coin.x - size.x * 0.5;
+
+        let mut steps = 0;
+        while game.state != GameState::LevelComplete {
+            game.step(InputState {
+                move_x: 1.0,
+                ..Default::default()
+            });
+            steps += 1;
+            assert!(steps < 1200, "never reached the results screen");
+        }
+        assert_eq!(game.stats.coins, 1);
+        assert_eq!(game.stats.stomps, 1);
+        assert!(game.stats.steps > 0);
+
+        let score_before_bonus = game.score;
+        assert_eq!(game.stats.score, score_before_bonus);
+        for _ in 0..120 {
+            game.step(InputState::default());
+        }
+        let bonus = game.score - score_before_bonus;
+        assert!(bonus > 0);
+        assert_eq!(game.stats.score, game.score);
+        assert_eq!(game.stats.take_all_bonus(), 0);
+    }
+
+    #[test]
+    fn flagpole_bonus_grows_with_grab_height() {
+        let pole = Rect::new(0.0, 0.0, 6.0, 96.0);
+        assert_eq!(flagpole_bonus(pole.bottom(), pole), FLAGPOLE_MIN_BONUS);
+        assert_eq!(flagpole_bonus(pole.y - 10.0, pole), 5000);
+        assert!(flagpole_bonus(30.0, pole) > flagpole_bonus(60.0, pole));
+    }
+
+    #[test]
+    fn star_power_kills_enemies_on_contact() {
+        let level = "\
+....................
+..P*.....E........G.
+####################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let hold_right = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..90 {
+            game.step(hold_right);
+        }
+
+        assert!(game.world.stars.is_empty());
+        assert!(game.player.has_star());
+        assert!(!game.enemies[0].alive);
+        assert_eq!(game.lives, game.config.starting_lives);
+        assert!(game.player.pos.x > 9.0 * game.config.tile_size);
+    }
+
+    #[test]
+    fn coin_magnet_pulls_nearby_coins_during_a_star() {
+        let level = "\
+....................
+.........C.......C..
+..P...............G.
+####################
+";
+        let config = Config {
+            coin_magnet: true,
+            ..Config::default()
+        };
+        let mut game = Game::headless(level, config).expect("test level is valid");
+        start(&mut game);
+        let (near, far) = (game.world.coins[0], game.world.coins[1]);
+
+        game.player.start_star(game.config.star_duration);
+        game.player.pos.x = near.x - game.config.magnet_radius * 0.5;
+        game.step(InputState::default());
+
+        assert!(
+            game.world.coins[0].distance(game.player.center())
+                < near.distance(game.player.center())
+        );
+        assert_eq!(game.world.coins[1], far);
+    }
+
+    #[test]
+    fn fast_fall_onto_enemy_stomps_it() {
+        let level = "\
+....................
+....................
+....................
+..P......E........G.
+####################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let enemy_rect = game.enemies[0].rect();
+        let size = game.player.size();
+        game.player.pos = vec2(
+            enemy_rect.center().x - size.x * 0.5,
+            enemy_rect.y - size.y - 2.0,
+        );
+        game.player.vel.y = game.config.terminal_velocity;
+        game.step(InputState::default());
+
+        assert!(!game.enemies[0].alive, "enemy should be stomped");
+        assert_eq!(game.lives, game.config.starting_lives);
+        assert!(game.player.vel.y < 0.0, "stomp should bounce the player");
+    }
+
+    #[test]
+    fn landing_on_a_spiky_enemy_hurts_the_player() {
+        let level = "\
+....................
+....................
+....................
+..P......S........G.
+####################
+";
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        start(&mut game);
+
+        let enemy_rect = game.enemies[0].rect();
+        let size = game.player.size();
+        game.player.pos = vec2(
+            enemy_rect.center().x - size.x * 0.5,
+            enemy_rect.y - size.y - 2.0,
+        );
+        game.player.vel.y = game.config.terminal_velocity;
+        game.step(InputState::default());
+
+        assert!(game.enemies[0].alive, "spiky enemies can't be stomped");
+        assert_eq!(game.stats.stomps, 0);
+        assert!(game.transition.is_some(), "landing on spikes should kill");
+    }
+
+    #[test]
+    fn replaying_a_run_reproduces_the_speedrun_time() {
+        let mut game = Game::headless(LEVEL, Config::default()).expect("test level is valid");
+        game.speedrun.enabled = true;
+        game.start_recording();
+        start(&mut game);
+        for frame in 0..600 {
+            game.step(scripted_input(frame));
+            if game.state == GameState::LevelComplete {
+                break;
+            }
+        }
+        assert_eq!(game.state, GameState::LevelComplete);
+        let recorded = game.stop_recording().to_vec();
+        let fixed_dt = game.config.fixed_dt;
+        let time = game.speedrun.elapsed(fixed_dt);
+        assert!(time > 1.0, "timer read {time}");
+        assert_eq!(game.speedrun.best(fixed_dt), Some(time));
+
+        let mut replayed = Game::headless(LEVEL, Config::default()).expect("test level is valid");
+        replayed.speedrun.enabled = true;
+        replayed.start_playback(recorded.clone());
+        for _ in 0..recorded.len() {
+            replayed.step(InputState::default());
+        }
+
+        assert_eq!(replayed.state, GameState::LevelComplete);
+        assert_eq!(replayed.score, game.score);
+        assert_eq!(replayed.player.pos, game.player.pos);
+        assert_eq!(replayed.speedrun.elapsed(fixed_dt), time);
+        assert_eq!(replayed.speedrun.best(fixed_dt), Some(time));
+    }
+}
@@ -0,0 +1,155 @@
+use macroquad::prelude::*;
+
+use super::{physics, world::World, Config};
+
+/// How fast a mushroom rises out of its question block, in pixels per second.
+const EMERGE_SPEED: f32 = 40.0;
+
+/// A power-up mushroom. It slides along the ground, turns around at walls and
+/// drops off ledges, unless `Config::static_mushrooms` keeps it in place.
+#[derive(Clone, Copy, Debug)]
+pub struct Mushroom {
+    pub pos: Vec2,
+    vel: Vec2,
+    dir: f32,
+    /// While rising out of a block: the `pos.y` at which it starts moving.
+    emerge_to: Option<f32>,
+}
+
+impl Mushroom {
+    /// A mushroom already out in the level, about to start moving.
+    pub fn new(pos: Vec2) -> Self {
+        Self {
+            pos,
+            vel: Vec2::ZERO,
+            dir: 1.0,
+            emerge_to: None,
+        }
+    }
+
+    /// A mushroom that rises out of `block` before it starts moving.
+    pub fn emerging(block: Rect, config: &Config) -> Self {
+        let size = config.mushroom_size;
+        let x = block.x + (block.w - size.x) * 0.5;
+        Self {
+            emerge_to: Some(block.y - size.y),
+            ..Self::new(vec2(x, block.y + block.h - size.y))
+        }
+    }
+
+    /// Advances the mushroom, returning `false` once it has fallen out of the
+    /// level.
+    pub fn update(&mut self, world: &World, config: &Config, dt: f32) -> bool {
+        if let Some(target_y) = self.emerge_to {
+            self.pos.y = (self.pos.y - EMERGE_SPEED * dt).max(target_y);
+            if self.pos.y <= target_y {
+                self.emerge_to = None;
+            }
+            return true;
+        }
+        if config.static_mushrooms {
+            return true;
+        }
+
+        self.vel.y = physics::apply_gravity(
+            self.vel.y,
+            config.gravity,
+            config.terminal_velocity,
+            config.gravity_dir,
+            dt,
+        );
+        self.vel.x = config.mushroom_speed * self.dir;
+
+        let result = physics::move_with_gravity(
+            self.pos,
+            config.mushroom_size,
+            self.vel,
+            world,
+            dt,
+            0.0,
+            config.gravity_dir,
+        );
+        self.pos = result.pos;
+        self.vel = result.vel;
+        if result.touching_left || result.touching_right {
+            self.dir *= -1.0;
+        }
+
+        let world_w = world.width as f32 * config.tile_size;
+        if self.pos.x <= 0.0 {
+            self.pos.x = 0.0;
+            self.dir = 1.0;
+        } else if self.pos.x + config.mushroom_size.x >= world_w {
+            self.pos.x = world_w - config.mushroom_size.x;
+            self.dir = -1.0;
+        }
+
+        self.pos.y < world.height as f32 * config.tile_size
+            && self.pos.y + config.mushroom_size.y > 0.0
+    }
+
+    pub fn rect(&self, config: &Config) -> Rect {
+        physics::rect_at(self.pos, config.mushroom_size)
+    }
+
+    pub fn draw(&self, config: &Config) {
+        let (pos, size) = (self.pos, config.mushroom_size);
+        let stem_w = size.x * 0.35;
+        let stem_h = size.y * 0.45;
+        let stem_x = pos.x + (size.x - stem_w) * 0.5;
+        let stem_y = pos.y + size.y - stem_h;
+        draw_rectangle(
+            stem_x,
+            stem_y,
+            stem_w,
+            stem_h,
+            Color::new(0.95, 0.9, 0.75, 1.0),
+        );
+
+        let cap_h = size.y * 0.6;
+        draw_rectangle(
+            pos.x,
+            pos.y,
+            size.x,
+            cap_h,
+            Color::new(0.85, 0.15, 0.55, 1.0),
+        );
+        draw_rectangle(
+            pos.x + size.x * 0.15,
+            pos.y + cap_h * 0.25,
+            size.x * 0.2,
+            cap_h * 0.35,
+            WHITE,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mushroom_turns_at_walls_and_drops_off_ledges() {
+        let config = Config::default();
+        let level = "\
+..........
+#.P.M...G#
+#.########
+#.........
+##########
+";
+        let world = World::from_ascii(level, &config).expect("test level is valid");
+        let mut mushroom = world.mushrooms[0];
+        let start_y = mushroom.pos.y;
+
+        for _ in 0..600 {
+            assert!(mushroom.update(&world, &config, config.fixed_dt));
+        }
+
+        assert!(
+            mushroom.pos.y > start_y + config.tile_size,
+            "mushroom still at y={} after bouncing off the right wall",
+            mushroom.pos.y
+        );
+    }
+}
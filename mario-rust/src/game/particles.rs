@@ -0,0 +1,84 @@
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy)]
+pub struct Particle {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub lifetime: f32,
+    pub size: f32,
+    pub color: Color,
+}
+
+pub fn update(particles: &mut Vec<Particle>, gravity: f32, dt: f32) {
+    for particle in particles.iter_mut() {
+        particle.vel.y += gravity * dt;
+        particle.pos += particle.vel * dt;
+        particle.lifetime -= dt;
+    }
+    particles.retain(|particle| particle.lifetime > 0.0);
+}
+
+pub fn draw(particles: &[Particle]) {
+    for particle in particles {
+        let half = particle.size * 0.5;
+        draw_rectangle(
+            particle.pos.x - half,
+            particle.pos.y - half,
+            particle.size,
+            particle.size,
+            particle.color,
+        );
+    }
+}
+
+/// Adds `particle` unless the pool is already at `cap`; the vector is reused
+/// between bursts so steady-state play doesn't allocate.
+fn push_capped(particles: &mut Vec<Particle>, cap: usize, particle: Particle) {
+    if particles.len() < cap {
+        particles.push(particle);
+    }
+}
+
+/// An evenly spaced ring of sparks thrown upward from `origin`.
+pub fn spawn_burst(
+    particles: &mut Vec<Particle>,
+    cap: usize,
+    origin: Vec2,
+    color: Color,
+    count: usize,
+    speed: f32,
+) {
+    for i in 0..count {
+        let angle = std::f32::consts::TAU * i as f32 / count.max(1) as f32;
+        push_capped(
+            particles,
+            cap,
+            Particle {
+                pos: origin,
+                vel: vec2(angle.cos(), angle.sin()) * speed - vec2(0.0, speed * 0.5),
+                lifetime: 0.45,
+                size: 3.0,
+                color,
+            },
+        );
+    }
+}
+
+/// Four chunks flung up and out from the corners of a broken brick.
+pub fn spawn_brick_debris(particles: &mut Vec<Particle>, cap: usize, brick: Rect) {
+    let color = Color::new(0.72, 0.36, 0.2, 1.0);
+    let center = brick.center();
+    for (dx, dy) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+        push_capped(
+            particles,
+            cap,
+            Particle {
+                pos: center + vec2(dx * brick.w * 0.25, dy * brick.h * 0.25),
+                vel: vec2(dx * 90.0, -300.0 + dy * 80.0),
+                lifetime: 0.9,
+                size: brick.w * 0.3,
+                color,
+            },
+        );
+    }
+}
@@ -1,5 +1,10 @@
 use macroquad::prelude::*;
 
+/// Longest distance a body moves per collision pass, on either axis. Faster
+/// moves are split into sub-steps so they can't skip over a thin floor or
+/// wall in one go.
+const MAX_SUBSTEP: f32 = 16.0;
+
 pub(crate) fn rect_at(pos: Vec2, size: Vec2) -> Rect {
     Rect::new(pos.x, pos.y, size.x, size.y)
 }
@@ -16,16 +21,137 @@ pub(crate) fn approach(value: f32, target: f32, delta: f32) -> f32 {
     }
 }
 
+/// Resolved position/velocity after a move, plus which sides hit a solid.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct CollisionResult {
+    pub pos: Vec2,
+    pub vel: Vec2,
+    pub touching_ground: bool,
+    pub touching_ceiling: bool,
+    pub touching_left: bool,
+    pub touching_right: bool,
+}
+
+/// Broadphase for the move functions: whatever a body can collide with.
+pub(crate) trait Solids {
+    /// Appends every solid overlapping `area` to `out`. Results only match
+    /// a brute-force scan if they come in the same order every time.
+    fn solids_near(&self, area: Rect, out: &mut Vec<Rect>);
+}
+
+/// A plain list, scanned in full.
+impl Solids for [Rect] {
+    fn solids_near(&self, area: Rect, out: &mut Vec<Rect>) {
+        out.extend(self.iter().filter(|solid| rects_intersect(area, **solid)));
+    }
+}
+
+/// `vel_y` after `dt` of `gravity` pulling toward `gravity_dir` (`1.0` down,
+/// `-1.0` up), with the fall speed capped at `max_fall`.
+pub(crate) fn apply_gravity(
+    vel_y: f32,
+    gravity: f32,
+    max_fall: f32,
+    gravity_dir: f32,
+    dt: f32,
+) -> f32 {
+    (vel_y * gravity_dir + gravity * dt).min(max_fall) * gravity_dir
+}
+
 pub(crate) fn move_with_collisions(
+    pos: Vec2,
+    size: Vec2,
+    vel: Vec2,
+    solids: &(impl Solids + ?Sized),
+    dt: f32,
+) -> CollisionResult {
+    move_with_corner_correction(pos, size, vel, solids, dt, 0.0)
+}
+
+/// Like `move_with_collisions`, but a body moving up that only clips a
+/// solid's corner by at most `max_nudge` pixels is slid sideways past it
+/// instead of bonking its head.
+pub(crate) fn move_with_corner_correction(
+    pos: Vec2,
+    size: Vec2,
+    vel: Vec2,
+    solids: &(impl Solids + ?Sized),
+    dt: f32,
+    max_nudge: f32,
+) -> CollisionResult {
+    move_with_gravity(pos, size, vel, solids, dt, max_nudge, 1.0)
+}
+
+/// `move_with_corner_correction` for a body pulled toward `gravity_dir`.
+/// Contacts on that side count as ground and the opposite side as ceiling,
+/// and corner correction applies when moving against gravity.
+pub(crate) fn move_with_gravity(
+    pos: Vec2,
+    size: Vec2,
+    vel: Vec2,
+    solids: &(impl Solids + ?Sized),
+    dt: f32,
+    max_nudge: f32,
+    gravity_dir: f32,
+) -> CollisionResult {
+    let distance = (vel * dt).abs().max_element();
+    let steps = (distance / MAX_SUBSTEP).ceil().max(1.0) as usize;
+    let sub_dt = dt / steps as f32;
+
+    let mut result = CollisionResult {
+        pos,
+        vel,
+        ..Default::default()
+    };
+    let mut nearby = Vec::new();
+    for _ in 0..steps {
+        // Everything the body could touch this sub-step: the span it sweeps,
+        // widened by the furthest a corner nudge can shift it.
+        let start = rect_at(result.pos, size);
+        let end = rect_at(result.pos + result.vel * sub_dt, size);
+        let swept = start.combine_with(end);
+        let area = Rect::new(
+            swept.x - max_nudge,
+            swept.y,
+            swept.w + max_nudge * 2.0,
+            swept.h,
+        );
+        nearby.clear();
+        solids.solids_near(area, &mut nearby);
+
+        let step = move_step(
+            result.pos,
+            size,
+            result.vel,
+            &nearby,
+            sub_dt,
+            max_nudge,
+            gravity_dir,
+        );
+        result.pos = step.pos;
+        result.vel = step.vel;
+        result.touching_ground |= step.touching_ground;
+        result.touching_ceiling |= step.touching_ceiling;
+        result.touching_left |= step.touching_left;
+        result.touching_right |= step.touching_right;
+    }
+    result
+}
+
+/// One move-then-resolve pass: each axis moves its full distance, then backs
+/// out of any solid it ends up overlapping.
+fn move_step(
     pos: Vec2,
     size: Vec2,
     vel: Vec2,
     solids: &[Rect],
     dt: f32,
-) -> (Vec2, Vec2, bool) {
+    max_nudge: f32,
+    gravity_dir: f32,
+) -> CollisionResult {
     let mut pos = pos;
     let mut vel = vel;
-    let mut on_ground = false;
+    let mut result = CollisionResult::default();
 
     pos.x += vel.x * dt;
     let mut rect = rect_at(pos, size);
@@ -33,8 +159,10 @@ pub(crate) fn move_with_collisions(
         if rects_intersect(rect, *solid) {
             if vel.x > 0.0 {
                 pos.x = solid.x - size.x;
+                result.touching_right = true;
             } else if vel.x < 0.0 {
                 pos.x = solid.x + solid.w;
+                result.touching_left = true;
             }
             vel.x = 0.0;
             rect.x = pos.x;
@@ -43,18 +171,252 @@ pub(crate) fn move_with_collisions(
 
     pos.y += vel.y * dt;
     rect.y = pos.y;
+    if vel.y * gravity_dir < 0.0 {
+        if let Some(dx) = corner_nudge(rect, solids, max_nudge) {
+            pos.x += dx;
+            rect.x = pos.x;
+        }
+    }
     for solid in solids {
         if rects_intersect(rect, *solid) {
             if vel.y > 0.0 {
                 pos.y = solid.y - size.y;
-                on_ground = true;
             } else if vel.y < 0.0 {
                 pos.y = solid.y + solid.h;
             }
+            if vel.y * gravity_dir > 0.0 {
+                result.touching_ground = true;
+            } else if vel.y * gravity_dir < 0.0 {
+                result.touching_ceiling = true;
+            }
             vel.y = 0.0;
             rect.y = pos.y;
         }
     }
 
-    (pos, vel, on_ground)
+    result.pos = pos;
+    result.vel = vel;
+    result
+}
+
+/// Sideways shift of at most `max_nudge` that frees `rect` from the solid it
+/// overlaps, if one exists and doesn't run into another solid.
+fn corner_nudge(rect: Rect, solids: &[Rect], max_nudge: f32) -> Option<f32> {
+    if max_nudge <= 0.0 {
+        return None;
+    }
+    let hit = solids.iter().find(|solid| rects_intersect(rect, **solid))?;
+    let to_left = hit.x - (rect.x + rect.w);
+    let to_right = hit.x + hit.w - rect.x;
+    let dx = if to_left.abs() <= to_right.abs() {
+        to_left
+    } else {
+        to_right
+    };
+    if dx.abs() > max_nudge {
+        return None;
+    }
+
+    let nudged = Rect::new(rect.x + dx, rect.y, rect.w, rect.h);
+    let blocked = solids.iter().any(|solid| rects_intersect(nudged, *solid));
+    (!blocked).then_some(dx)
+}
+
+/// Which way a 45-degree slope tile rises.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum SlopeDir {
+    /// `/`: low on the left, high on the right.
+    Up,
+    /// `\`: high on the left, low on the right.
+    Down,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Slope {
+    pub rect: Rect,
+    pub dir: SlopeDir,
+}
+
+impl Slope {
+    pub fn surface_y(&self, x: f32) -> f32 {
+        let t = ((x - self.rect.x) / self.rect.w).clamp(0.0, 1.0);
+        match self.dir {
+            SlopeDir::Up => self.rect.y + self.rect.h * (1.0 - t),
+            SlopeDir::Down => self.rect.y + self.rect.h * t,
+        }
+    }
+
+    /// Highest surface point under the span `left..right`, if it overlaps.
+    /// Standing on the highest point (rather than the center) keeps the feet
+    /// level with neighbouring flat tiles at the top of the slope.
+    fn highest_surface(&self, left: f32, right: f32) -> Option<f32> {
+        if right <= self.rect.x || left >= self.rect.x + self.rect.w {
+            return None;
+        }
+        let x = match self.dir {
+            SlopeDir::Up => right.min(self.rect.x + self.rect.w),
+            SlopeDir::Down => left.max(self.rect.x),
+        };
+        Some(self.surface_y(x))
+    }
+}
+
+/// Lifts a grounded body onto the slope surface it is about to walk up, so the
+/// horizontal pass of `move_with_collisions` doesn't catch the flat tile at the
+/// top of the slope on the body's leading corner.
+pub(crate) fn climb_slopes(pos: Vec2, size: Vec2, vel: Vec2, slopes: &[Slope], dt: f32) -> Vec2 {
+    let dx = vel.x * dt;
+    let left = pos.x + dx;
+    let right = left + size.x;
+    let bottom = pos.y + size.y;
+    let step = dx.abs() + 1.0;
+
+    let mut pos = pos;
+    for slope in slopes {
+        if let Some(surface) = slope.highest_surface(left, right) {
+            if surface < bottom && bottom - surface <= step {
+                pos.y = pos.y.min(surface - size.y);
+            }
+        }
+    }
+    pos
+}
+
+/// Second pass after `move_with_collisions` that stands a falling or walking
+/// body on slope surfaces. Slopes only collide from above; `prev_pos` is
+/// where the body started the step, and `was_grounded` lets it stick to a
+/// slope while walking downhill instead of hopping off it.
+pub(crate) fn resolve_slopes(
+    result: &mut CollisionResult,
+    prev_pos: Vec2,
+    size: Vec2,
+    was_grounded: bool,
+    slopes: &[Slope],
+) {
+    if result.vel.y < 0.0 {
+        return;
+    }
+
+    let left = result.pos.x;
+    let right = left + size.x;
+    let bottom = result.pos.y + size.y;
+    let prev_bottom = prev_pos.y + size.y;
+    // On a 45-degree slope the surface moves one pixel per pixel walked.
+    let step = (result.pos.x - prev_pos.x).abs() + 1.0;
+
+    let mut surface_top: Option<f32> = None;
+    for slope in slopes {
+        let Some(surface) = slope.highest_surface(left, right) else {
+            continue;
+        };
+        let landing = bottom >= surface && prev_bottom <= surface + step;
+        let sticking = was_grounded
+            && !result.touching_ground
+            && surface >= bottom
+            && surface - bottom <= step + 1.0;
+        if (landing || sticking) && surface_top.is_none_or(|top| surface < top) {
+            surface_top = Some(surface);
+        }
+    }
+
+    if let Some(surface) = surface_top {
+        result.pos.y = surface - size.y;
+        result.vel.y = 0.0;
+        result.touching_ground = true;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ceiling_contact_zeroes_upward_velocity() {
+        let ceiling = Rect::new(0.0, 0.0, 32.0, 32.0);
+        let result = move_with_collisions(
+            vec2(4.0, 34.0),
+            vec2(22.0, 28.0),
+            vec2(0.0, -420.0),
+            &[ceiling][..],
+            1.0 / 60.0,
+        );
+
+        assert!(result.touching_ceiling);
+        assert!(!result.touching_ground);
+        assert_eq!(result.vel.y, 0.0);
+        assert_eq!(result.pos.y, ceiling.y + ceiling.h);
+    }
+
+    fn jump_under_corner(overlap: f32, max_nudge: f32) -> CollisionResult {
+        let block = Rect::new(100.0, 0.0, 32.0, 32.0);
+        let size = vec2(22.0, 28.0);
+        // The body's right edge sits `overlap` pixels under the block's left edge.
+        let pos = vec2(block.x + overlap - size.x, block.y + block.h + 2.0);
+        move_with_corner_correction(
+            pos,
+            size,
+            vec2(0.0, -420.0),
+            &[block][..],
+            1.0 / 60.0,
+            max_nudge,
+        )
+    }
+
+    #[test]
+    fn corner_correction_slips_past_a_clipped_corner() {
+        let result = jump_under_corner(3.0, 4.0);
+
+        assert!(!result.touching_ceiling);
+        assert_eq!(result.vel.y, -420.0);
+        assert_eq!(result.pos.x, 100.0 - 22.0);
+    }
+
+    #[test]
+    fn corner_correction_leaves_real_bonks_alone() {
+        assert!(jump_under_corner(8.0, 4.0).touching_ceiling);
+        assert!(jump_under_corner(3.0, 0.0).touching_ceiling);
+    }
+
+    #[test]
+    fn fast_fall_lands_instead_of_tunneling() {
+        let floor = Rect::new(0.0, 100.0, 64.0, 32.0);
+        let size = vec2(22.0, 28.0);
+        let dt = 1.0 / 60.0;
+        // 100 px in one step: more than the body and floor heights combined.
+        let vel = vec2(0.0, 6000.0);
+        assert!(vel.y * dt > size.y + floor.h + 5.0);
+
+        let result = move_with_collisions(
+            vec2(4.0, floor.y - size.y - 5.0),
+            size,
+            vel,
+            &[floor][..],
+            dt,
+        );
+
+        assert!(result.touching_ground);
+        assert_eq!(result.vel.y, 0.0);
+        assert_eq!(result.pos.y, floor.y - size.y);
+    }
+
+    #[test]
+    fn fast_horizontal_move_stops_at_thin_wall() {
+        let wall = Rect::new(100.0, 0.0, 8.0, 64.0);
+        let size = vec2(22.0, 28.0);
+        let dt = 1.0 / 60.0;
+        let vel = vec2(6000.0, 0.0);
+        assert!(vel.x * dt > size.x + wall.w + 2.0);
+
+        let result = move_with_collisions(
+            vec2(wall.x - size.x - 2.0, 10.0),
+            size,
+            vel,
+            &[wall][..],
+            dt,
+        );
+
+        assert!(result.touching_right);
+        assert_eq!(result.vel.x, 0.0);
+        assert_eq!(result.pos.x, wall.x - size.x);
+    }
 }
@@ -0,0 +1,113 @@
+use macroquad::prelude::*;
+
+use super::physics;
+
+/// A solid platform that shuttles back and forth between two points.
+#[derive(Clone, Debug)]
+pub struct MovingPlatform {
+    pub rect: Rect,
+    pub vel: Vec2,
+    start: Vec2,
+    end: Vec2,
+    delta: Vec2,
+}
+
+impl MovingPlatform {
+    pub fn new(start: Vec2, end: Vec2, size: Vec2, speed: f32) -> Self {
+        let dir = (end - start).normalize_or_zero();
+        Self {
+            rect: physics::rect_at(start, size),
+            vel: dir * speed,
+            start,
+            end,
+            delta: Vec2::ZERO,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        let before = self.rect.point();
+        let mut pos = before + self.vel * dt;
+
+        let span = self.end - self.start;
+        let span_len_sq = span.length_squared();
+        if span_len_sq > f32::EPSILON {
+            let t = (pos - self.start).dot(span) / span_len_sq;
+            if t >= 1.0 {
+                pos = self.end;
+                self.vel = -self.vel;
+            } else if t <= 0.0 {
+                pos = self.start;
+                self.vel = -self.vel;
+            }
+        }
+
+        self.rect.move_to(pos);
+        self.delta = pos - before;
+    }
+
+    /// How far this platform moved a rider standing on it during the last
+    /// update, or `None` if `rider` wasn't standing on its previous top edge.
+    pub fn carry(&self, rider: Rect) -> Option<Vec2> {
+        let prev_top = self.rect.y - self.delta.y;
+        let prev_x = self.rect.x - self.delta.x;
+        let bottom = rider.y + rider.h;
+        let overlaps_x = rider.x < prev_x + self.rect.w && rider.x + rider.w > prev_x;
+        if overlaps_x && (bottom - prev_top).abs() <= 1.0 {
+            Some(self.delta)
+        } else {
+            None
+        }
+    }
+
+    pub fn draw(&self) {
+        let rect = self.rect;
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::new(0.78, 0.6, 0.36, 1.0),
+        );
+        draw_rectangle_lines(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            2.0,
+            Color::new(0.45, 0.3, 0.16, 1.0),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{player::Player, world::World, Config, InputState};
+
+    #[test]
+    fn platform_moving_right_carries_grounded_player() {
+        let config = Config::default();
+        let level = "\
+..........G\n\
+.[......]..\n\
+.P.........\n\
+###########\n";
+        let mut world = World::from_ascii(level, &config).expect("test level is valid");
+        let mut player = Player::new(world.platforms[0].rect.point(), &config);
+        player.pos.y = world.platforms[0].rect.y - player.size().y;
+        player.on_ground = true;
+
+        let input = InputState::default();
+        let platform_start = world.platforms[0].rect.x;
+        let player_start = player.pos.x;
+        for _ in 0..30 {
+            world.update_platforms(config.fixed_dt);
+            player.pos += world.platform_carry(player.rect());
+            player.update(&input, &world, &config, config.fixed_dt);
+        }
+
+        let platform_moved = world.platforms[0].rect.x - platform_start;
+        assert!(platform_moved > 0.0);
+        assert!(player.on_ground);
+        assert!((player.pos.x - player_start - platform_moved).abs() < 0.01);
+    }
+}
@@ -1,106 +1,338 @@
 use macroquad::prelude::*;
 
-use super::{physics, world::World, Config, InputState};
+use super::{physics, sprites::PlayerMotion, world::World, Config, InputState};
 
 pub struct Player {
     pub pos: Vec2,
     pub vel: Vec2,
     pub on_ground: bool,
+    /// Climbing a ladder: gravity is off and up/down move at a fixed speed.
+    pub on_ladder: bool,
+    /// Hitbox at the start of the last update, for swept contact checks.
+    prev_rect: Rect,
     size: Vec2,
     facing: f32,
     coyote_timer: f32,
-    jump_buffer_timer: f32,
+    jump_buffer: ActionBuffer,
+    /// Lets a dash pressed just before the cooldown ends still go off.
+    dash_buffer: ActionBuffer,
+    air_jumps_left: u32,
     powered: bool,
     invuln_timer: f32,
+    star_timer: f32,
+    dash_timer: f32,
+    dash_cooldown_timer: f32,
+    wall_dir: f32,
+    wall_jump_lock_timer: f32,
+    hit_ceiling: bool,
+    landing_speed: f32,
+    /// Seconds left of the sprite's squash after a hard landing.
+    squash_timer: f32,
+    /// Seconds left of the sprite's stretch after a jump.
+    stretch_timer: f32,
+    /// `Config::gravity_dir` as of the last spawn; decides which end of the
+    /// hitbox counts as the feet.
+    gravity_dir: f32,
+    crouching: bool,
+    in_water: bool,
+    splashed: bool,
+    anim_time: f32,
 }
 
 impl Player {
     pub fn new(spawn: Vec2, config: &Config) -> Self {
         let size = config.player_size;
-        let pos = spawn + vec2((config.tile_size - size.x) * 0.5, config.tile_size - size.y);
+        let pos = spawn_pos(spawn, size, config);
 
         Self {
             pos,
             vel: Vec2::ZERO,
             on_ground: false,
+            on_ladder: false,
+            prev_rect: physics::rect_at(pos, size),
             size,
             facing: 1.0,
             coyote_timer: 0.0,
-            jump_buffer_timer: 0.0,
+            jump_buffer: ActionBuffer::default(),
+            dash_buffer: ActionBuffer::default(),
+            air_jumps_left: config.max_air_jumps,
             powered: false,
             invuln_timer: 0.0,
+            star_timer: 0.0,
+            dash_timer: 0.0,
+            dash_cooldown_timer: 0.0,
+            wall_dir: 0.0,
+            wall_jump_lock_timer: 0.0,
+            hit_ceiling: false,
+            landing_speed: 0.0,
+            squash_timer: 0.0,
+            stretch_timer: 0.0,
+            gravity_dir: config.gravity_dir,
+            crouching: false,
+            in_water: false,
+            splashed: false,
+            anim_time: 0.0,
         }
     }
 
     pub fn reset(&mut self, spawn: Vec2, config: &Config) {
         let size = config.player_size;
-        self.pos = spawn + vec2((config.tile_size - size.x) * 0.5, config.tile_size - size.y);
+        self.pos = spawn_pos(spawn, size, config);
         self.vel = Vec2::ZERO;
         self.on_ground = false;
+        self.on_ladder = false;
         self.facing = 1.0;
         self.coyote_timer = 0.0;
-        self.jump_buffer_timer = 0.0;
+        self.jump_buffer.clear();
+        self.dash_buffer.clear();
+        self.air_jumps_left = config.max_air_jumps;
         self.powered = false;
         self.invuln_timer = 0.0;
+        self.star_timer = 0.0;
+        self.dash_timer = 0.0;
+        self.dash_cooldown_timer = 0.0;
+        self.wall_dir = 0.0;
+        self.wall_jump_lock_timer = 0.0;
+        self.hit_ceiling = false;
+        self.landing_speed = 0.0;
+        self.squash_timer = 0.0;
+        self.stretch_timer = 0.0;
+        self.gravity_dir = config.gravity_dir;
+        self.crouching = false;
+        self.in_water = false;
+        self.splashed = false;
+        self.anim_time = 0.0;
         self.size = size;
+        self.prev_rect = self.rect();
     }
 
     pub fn update(&mut self, input: &InputState, world: &World, config: &Config, dt: f32) -> bool {
+        self.prev_rect = self.rect();
         self.invuln_timer = (self.invuln_timer - dt).max(0.0);
+        self.star_timer = (self.star_timer - dt).max(0.0);
+        self.dash_timer = (self.dash_timer - dt).max(0.0);
+        self.dash_cooldown_timer = (self.dash_cooldown_timer - dt).max(0.0);
+        self.wall_jump_lock_timer = (self.wall_jump_lock_timer - dt).max(0.0);
+        self.squash_timer = (self.squash_timer - dt).max(0.0);
+        self.stretch_timer = (self.stretch_timer - dt).max(0.0);
         let mut jumped = false;
-        if input.jump_pressed {
-            self.jump_buffer_timer = config.jump_buffer_time;
-        } else {
-            self.jump_buffer_timer = (self.jump_buffer_timer - dt).max(0.0);
+
+        let in_water = world.is_in_water(self.center());
+        self.splashed = in_water != self.in_water;
+        self.in_water = in_water;
+
+        if !self.on_ladder {
+            if let Some(ladder) = self.ladder_to_grab(input, world) {
+                self.grab_ladder(ladder, config);
+            }
         }
+        if self.on_ladder {
+            return self.climb(input, world, config, dt);
+        }
+
+        self.jump_buffer
+            .update(input.jump_pressed, config.jump_buffer_time, dt);
+        self.dash_buffer
+            .update(input.dash_pressed, config.dash_buffer_time, dt);
 
-        if input.jump_released && self.vel.y < 0.0 {
+        let g = config.gravity_dir;
+        if input.jump_released && self.vel.y * g < 0.0 {
             self.vel.y *= config.jump_cut_multiplier;
         }
 
         if self.on_ground {
             self.coyote_timer = config.coyote_time;
+            self.air_jumps_left = config.max_air_jumps;
         } else {
             self.coyote_timer = (self.coyote_timer - dt).max(0.0);
         }
 
+        let wants_crouch = input.crouch_held && (self.on_ground || self.crouching);
+        if wants_crouch && !self.crouching {
+            self.set_crouching(true, config);
+        } else if !wants_crouch && self.crouching {
+            // Only stand back up if the full-height hitbox fits.
+            let standing = self.resized_rect(self.standing_size(config));
+            if world.is_rect_free(standing) {
+                self.set_crouching(false, config);
+            }
+        }
+        if !self.crouching {
+            self.match_standing_size(world, config);
+        }
+
         if input.move_x.abs() > f32::EPSILON {
             self.facing = input.move_x.signum();
         }
 
-        let target_speed = input.move_x * config.move_speed;
-        let accel = if input.move_x.abs() > f32::EPSILON {
-            config.move_accel
-        } else {
-            config.move_decel
-        };
-        self.vel.x = physics::approach(self.vel.x, target_speed, accel * dt);
+        if self.dash_cooldown_timer <= 0.0 && self.dash_buffer.consume() {
+            self.dash_timer = config.dash_duration;
+            self.dash_cooldown_timer = config.dash_duration + config.dash_cooldown;
+        }
 
-        if self.jump_buffer_timer > 0.0 && self.coyote_timer > 0.0 {
-            self.vel.y = -config.jump_speed;
+        if self.is_dashing() {
+            self.vel.x = self.facing * config.dash_speed;
+        } else if self.wall_jump_lock_timer <= 0.0 {
+            let on_ice = self.on_ground && world.is_on_ice(self.rect(), config);
+            let mut speed_scale = 1.0;
+            if self.crouching {
+                speed_scale *= config.crouch_speed_scale;
+            }
+            if self.in_water {
+                speed_scale *= config.water_move_scale;
+            }
+            let (top_speed, move_accel, air_accel) = if input.run_held {
+                (config.run_speed, config.run_accel, config.air_run_accel)
+            } else {
+                (config.move_speed, config.move_accel, config.air_accel)
+            };
+            let target_speed = input.move_x * top_speed * speed_scale;
+            let moving = input.move_x.abs() > f32::EPSILON;
+            let mut accel = match (moving, self.on_ground, on_ice) {
+                (true, false, _) => air_accel,
+                (false, false, _) => config.air_decel,
+                (true, true, false) => move_accel,
+                (true, true, true) => config.ice_accel,
+                (false, true, false) => config.move_decel,
+                (false, true, true) => config.ice_decel,
+            };
+            let over_top_speed =
+                self.vel.x * target_speed > 0.0 && self.vel.x.abs() > target_speed.abs();
+            if over_top_speed && !on_ice {
+                // Letting go of run mid-stride eases back down to walk speed.
+                accel = accel.min(config.run_release_decel);
+            }
+            if self.in_water {
+                accel *= config.water_move_scale;
+            }
+            self.vel.x = physics::approach(self.vel.x, target_speed, accel * dt);
+        }
+
+        if self.jump_buffer.is_buffered() && self.coyote_timer > 0.0 {
+            self.vel.y = -self.takeoff_speed(config) * g;
             self.on_ground = false;
             self.coyote_timer = 0.0;
-            self.jump_buffer_timer = 0.0;
+            self.jump_buffer.clear();
+            jumped = true;
+        } else if self.jump_buffer.is_buffered() && self.is_wall_sliding() {
+            self.vel.x = -self.wall_dir * config.wall_jump_speed.x;
+            self.vel.y = -config.wall_jump_speed.y * g;
+            self.facing = -self.wall_dir;
+            self.wall_dir = 0.0;
+            self.wall_jump_lock_timer = config.wall_jump_lock_time;
+            self.jump_buffer.clear();
             jumped = true;
+        } else if self.jump_buffer.is_buffered() && self.in_water {
+            // Swim strokes work mid-water, no ground needed.
+            self.vel.y = -config.swim_stroke_speed * g;
+            self.jump_buffer.clear();
+            jumped = true;
+        } else if input.jump_pressed
+            && !self.on_ground
+            && self.coyote_timer <= 0.0
+            && self.air_jumps_left > 0
+        {
+            // Only a fresh press once coyote time is over counts as an air
+            // jump, so a buffered or late ground jump never spends one.
+            self.vel.y = -config.air_jump_speed * g;
+            self.air_jumps_left -= 1;
+            self.jump_buffer.clear();
+            jumped = true;
+        }
+
+        let (gravity, max_fall) = if self.in_water {
+            (
+                config.gravity * config.water_gravity_scale,
+                config.water_sink_speed,
+            )
+        } else {
+            (config.gravity, config.terminal_velocity)
+        };
+        self.vel.y = physics::apply_gravity(self.vel.y, gravity, max_fall, g, dt);
+
+        // Keep sliding only while still pushing into the wall we touched last step.
+        if self.wall_dir != 0.0 && input.move_x * self.wall_dir <= 0.0 {
+            self.wall_dir = 0.0;
+        }
+        if self.is_wall_sliding() {
+            self.vel.y = (self.vel.y * g).min(config.wall_slide_speed) * g;
         }
 
-        self.vel.y = (self.vel.y + config.gravity * dt).min(config.terminal_velocity);
+        let was_on_ground = self.on_ground;
+        let fall_speed = self.vel.y * g;
+        // Slopes and ladder tops only work with gravity pointing down.
+        let upright = g > 0.0;
+        if was_on_ground && upright {
+            self.pos = physics::climb_slopes(self.pos, self.size, self.vel, &world.slopes, dt);
+        }
+        let mut result = physics::move_with_gravity(
+            self.pos,
+            self.size,
+            self.vel,
+            world,
+            dt,
+            config.corner_correction,
+            g,
+        );
+        if upright {
+            physics::resolve_slopes(
+                &mut result,
+                self.pos,
+                self.size,
+                was_on_ground,
+                &world.slopes,
+            );
+        }
+        if upright && !result.touching_ground && result.vel.y >= 0.0 {
+            let rect = physics::rect_at(result.pos, self.size);
+            if let Some(top) = world.ladder_top_crossed(self.prev_rect.bottom(), rect) {
+                result.pos.y = top - self.size.y;
+                result.vel.y = 0.0;
+                result.touching_ground = true;
+            }
+        }
 
-        let (pos, vel, on_ground) =
-            physics::move_with_collisions(self.pos, self.size, self.vel, &world.solids, dt);
+        self.landing_speed = if result.touching_ground && !was_on_ground {
+            fall_speed
+        } else {
+            0.0
+        };
+        if self.landing_speed >= config.hard_landing_speed {
+            self.squash_timer = config.squash_stretch_time;
+        }
+        self.pos = result.pos;
+        self.vel = result.vel;
+        self.on_ground = result.touching_ground;
+        self.hit_ceiling = result.touching_ceiling;
 
-        self.pos = pos;
-        self.vel = vel;
-        self.on_ground = on_ground;
+        let pushing_left = result.touching_left && input.move_x < 0.0;
+        let pushing_right = result.touching_right && input.move_x > 0.0;
+        if self.on_ground {
+            self.wall_dir = 0.0;
+        } else if pushing_left {
+            self.wall_dir = -1.0;
+        } else if pushing_right {
+            self.wall_dir = 1.0;
+        }
+
+        if self.on_ground && self.vel.x.abs() > f32::EPSILON {
+            self.anim_time += dt * self.vel.x.abs() / config.move_speed;
+        } else {
+            self.anim_time = 0.0;
+        }
 
-        if self.jump_buffer_timer > 0.0 && self.on_ground {
-            self.vel.y = -config.jump_speed;
+        if self.jump_buffer.is_buffered() && self.on_ground {
+            self.vel.y = -self.takeoff_speed(config) * g;
             self.on_ground = false;
             self.coyote_timer = 0.0;
-            self.jump_buffer_timer = 0.0;
+            self.jump_buffer.clear();
             jumped = true;
         }
 
+        if jumped {
+            self.stretch_timer = config.squash_stretch_time;
+            self.squash_timer = 0.0;
+        }
         jumped
     }
 
@@ -116,6 +348,150 @@ impl Player {
         physics::rect_at(self.pos, self.size)
     }
 
+    /// Where the hitbox was before the last update moved it.
+    pub fn prev_rect(&self) -> Rect {
+        self.prev_rect
+    }
+
+    pub fn motion(&self) -> PlayerMotion {
+        PlayerMotion {
+            on_ground: self.on_ground,
+            crouching: self.crouching,
+            speed_x: self.vel.x,
+        }
+    }
+
+    /// Up grabs a ladder the player overlaps; down grabs one right below
+    /// their feet while standing on its top.
+    fn ladder_to_grab(&self, input: &InputState, world: &World) -> Option<Rect> {
+        if input.up_held {
+            return world.ladder_at(self.rect());
+        }
+        if input.crouch_held && self.on_ground {
+            let rect = self.rect();
+            return world.ladder_at(Rect::new(rect.x, rect.y + rect.h, rect.w, 1.0));
+        }
+        None
+    }
+
+    fn grab_ladder(&mut self, ladder: Rect, config: &Config) {
+        if self.crouching {
+            self.set_crouching(false, config);
+        }
+        self.on_ladder = true;
+        self.on_ground = false;
+        self.vel = Vec2::ZERO;
+        self.dash_timer = 0.0;
+        self.wall_dir = 0.0;
+        self.pos.x = ladder.x + (ladder.w - self.size.x) * 0.5;
+    }
+
+    /// One step on a ladder: no gravity, fixed-speed climbing and slowed
+    /// sideways movement. Climbing past the top stands the player on it,
+    /// reaching the floor or stepping off the side lets go. Returns `true` if
+    /// the player jumped off.
+    fn climb(&mut self, input: &InputState, world: &World, config: &Config, dt: f32) -> bool {
+        self.landing_speed = 0.0;
+        self.anim_time = 0.0;
+        self.jump_buffer.clear();
+        self.dash_buffer.clear();
+        // Up doubles as jump, so only a jump without up held lets go.
+        if input.jump_pressed && !input.up_held {
+            self.on_ladder = false;
+            self.vel = vec2(
+                input.move_x * config.move_speed,
+                -config.jump_speed * config.gravity_dir,
+            );
+            return true;
+        }
+        let Some(ladder) = world.ladder_at(self.rect()) else {
+            self.on_ladder = false;
+            return false;
+        };
+        let top = world.ladder_top(ladder);
+
+        let climb = f32::from(u8::from(input.crouch_held)) - f32::from(u8::from(input.up_held));
+        let vel = vec2(
+            input.move_x * config.move_speed * config.ladder_move_scale,
+            climb * config.climb_speed,
+        );
+        let result = physics::move_with_collisions(self.pos, self.size, vel, world, dt);
+        self.pos = result.pos;
+        self.vel = result.vel;
+        self.hit_ceiling = result.touching_ceiling;
+        self.on_ground = false;
+
+        if self.pos.y + self.size.y <= top {
+            self.pos.y = top - self.size.y;
+            self.vel.y = 0.0;
+            self.on_ground = true;
+            self.on_ladder = false;
+        } else if result.touching_ground && climb > 0.0 {
+            self.on_ground = true;
+            self.on_ladder = false;
+        } else if world.ladder_at(self.rect()).is_none() {
+            self.on_ladder = false;
+        }
+        false
+    }
+
+    fn set_crouching(&mut self, crouching: bool, config: &Config) {
+        let size = if crouching {
+            vec2(config.player_size.x, config.crouch_height)
+        } else {
+            self.standing_size(config)
+        };
+        self.pos = self.resized_rect(size).point();
+        self.size = size;
+        self.crouching = crouching;
+    }
+
+    /// Ground jump speed, rising from `jump_speed` when standing still to
+    /// `jump_speed_max` at full run speed.
+    fn takeoff_speed(&self, config: &Config) -> f32 {
+        let run = (self.vel.x.abs() / config.move_speed).clamp(0.0, 1.0);
+        config.jump_speed + (config.jump_speed_max - config.jump_speed).max(0.0) * run
+    }
+
+    /// Full standing hitbox, taller while powered.
+    fn standing_size(&self, config: &Config) -> Vec2 {
+        if self.powered {
+            vec2(config.player_size.x, config.powered_height)
+        } else {
+            config.player_size
+        }
+    }
+
+    /// Grows or shrinks the hitbox to the standing size, anchored at the feet.
+    /// Shrinking always happens; growing waits until there's headroom so the
+    /// player is never pushed into a ceiling.
+    fn match_standing_size(&mut self, world: &World, config: &Config) {
+        let target = self.standing_size(config);
+        if self.size == target {
+            return;
+        }
+        let rect = self.resized_rect(target);
+        if target.y < self.size.y || world.is_rect_free(rect) {
+            self.pos = rect.point();
+            self.size = target;
+        }
+    }
+
+    /// This player's rect at `size`, keeping the feet and horizontal center put.
+    fn resized_rect(&self, size: Vec2) -> Rect {
+        let center_x = self.pos.x + self.size.x * 0.5;
+        let y = if self.gravity_dir < 0.0 {
+            self.pos.y
+        } else {
+            self.pos.y + self.size.y - size.y
+        };
+        Rect::new(center_x - size.x * 0.5, y, size.x, size.y)
+    }
+
+    pub fn anim_time(&self) -> f32 {
+        self.anim_time
+    }
+
     pub fn facing_dir(&self) -> f32 {
         self.facing
     }
@@ -124,6 +500,8 @@ impl Player {
         self.powered
     }
 
+    /// Changes power state; the hitbox follows on the next update once
+    /// there's room (see `match_standing_size`).
     pub fn set_powered(&mut self, powered: bool) {
         self.powered = powered;
     }
@@ -132,7 +510,384 @@ impl Player {
         self.invuln_timer > 0.0
     }
 
+    /// True if the player's head hit a solid during the last update.
+    pub fn hit_ceiling(&self) -> bool {
+        self.hit_ceiling
+    }
+
+    pub fn in_water(&self) -> bool {
+        self.in_water
+    }
+
+    /// True on the update where the player entered or left water.
+    pub fn splashed(&self) -> bool {
+        self.splashed
+    }
+
+    /// Downward speed at the moment of landing this update, or zero.
+    pub fn landing_speed(&self) -> f32 {
+        self.landing_speed
+    }
+
+    /// Scale for the sprite, not the hitbox: wide and short right after a
+    /// hard landing, tall and thin right after a jump, easing back to 1.
+    pub fn squash_stretch(&self, config: &Config) -> Vec2 {
+        if config.squash_stretch_time <= 0.0 {
+            return Vec2::ONE;
+        }
+        let ease = |timer: f32| (timer / config.squash_stretch_time).powi(2);
+        let amount = (ease(self.stretch_timer) - ease(self.squash_timer)) * config.squash_stretch;
+        vec2(1.0 - amount, 1.0 + amount)
+    }
+
+    pub fn is_wall_sliding(&self) -> bool {
+        self.wall_dir != 0.0 && !self.on_ground
+    }
+
+    /// Seconds left in which a jump still counts as leaving the ground.
+    pub fn coyote_time_left(&self) -> f32 {
+        self.coyote_timer
+    }
+
+    /// Seconds a jump press is still remembered for.
+    pub fn jump_buffer_left(&self) -> f32 {
+        self.jump_buffer.time_left()
+    }
+
+    pub fn is_dashing(&self) -> bool {
+        self.dash_timer > 0.0
+    }
+
     pub fn start_invulnerability(&mut self, duration: f32) {
         self.invuln_timer = duration.max(0.0);
     }
+
+    /// True while a star makes the player kill enemies on contact and shrug
+    /// off hazards.
+    pub fn has_star(&self) -> bool {
+        self.star_timer > 0.0
+    }
+
+    pub fn star_time_left(&self) -> f32 {
+        self.star_timer
+    }
+
+    pub fn start_star(&mut self, duration: f32) {
+        self.star_timer = duration.max(0.0);
+    }
+}
+
+/// Top-left corner for a hitbox of `size` standing in the `spawn` tile, feet
+/// against whichever side of the tile gravity pulls toward.
+fn spawn_pos(spawn: Vec2, size: Vec2, config: &Config) -> Vec2 {
+    let y = if config.gravity_dir < 0.0 {
+        0.0
+    } else {
+        config.tile_size - size.y
+    };
+    spawn + vec2((config.tile_size - size.x) * 0.5, y)
+}
+
+/// Remembers a button press for a short window, so an action pressed a
+/// moment before it becomes possible (a jump just before landing, a dash
+/// just before the cooldown ends) still happens.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ActionBuffer {
+    timer: f32,
+}
+
+impl ActionBuffer {
+    /// Call once per step: a press restarts the window, otherwise it runs
+    /// down.
+    pub fn update(&mut self, pressed: bool, window: f32, dt: f32) {
+        if pressed {
+            self.timer = window;
+        } else {
+            self.timer = (self.timer - dt).max(0.0);
+        }
+    }
+
+    pub fn is_buffered(&self) -> bool {
+        self.timer > 0.0
+    }
+
+    /// Uses up a buffered press, returning whether there was one.
+    pub fn consume(&mut self) -> bool {
+        let buffered = self.is_buffered();
+        self.clear();
+        buffered
+    }
+
+    pub fn clear(&mut self) {
+        self.timer = 0.0;
+    }
+
+    pub fn time_left(&self) -> f32 {
+        self.timer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slide_distance(floor: char) -> f32 {
+        let config = Config::default();
+        let floor_row: String = std::iter::repeat_n(floor, 40).collect();
+        let level = format!("{}G\n.P{}\n{floor_row}\n", ".".repeat(39), ".".repeat(38));
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+
+        let mut input = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        for _ in 0..60 {
+            player.update(&input, &world, &config, config.fixed_dt);
+        }
+
+        input.move_x = 0.0;
+        let release_x = player.pos.x;
+        for _ in 0..240 {
+            player.update(&input, &world, &config, config.fixed_dt);
+        }
+        player.pos.x - release_x
+    }
+
+    fn jump_apex_height(move_x: f32) -> f32 {
+        let config = Config::default();
+        let level = format!(
+            "{}G\n.P{}\n{}\n",
+            ".".repeat(79),
+            ".".repeat(78),
+            "#".repeat(80)
+        );
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+
+        let mut input = InputState {
+            move_x,
+            ..Default::default()
+        };
+        for _ in 0..60 {
+            player.update(&input, &world, &config, config.fixed_dt);
+        }
+        assert!(player.on_ground);
+
+        let ground_y = player.pos.y;
+        let mut apex_y = ground_y;
+        input.jump_pressed = true;
+        for _ in 0..90 {
+            player.update(&input, &world, &config, config.fixed_dt);
+            input.jump_pressed = false;
+            apex_y = apex_y.min(player.pos.y);
+        }
+        ground_y - apex_y
+    }
+
+    #[test]
+    fn running_jump_goes_higher_than_standing_jump() {
+        let standing = jump_apex_height(0.0);
+        let running = jump_apex_height(1.0);
+        assert!(standing > 0.0);
+        assert!(
+            running > standing + 4.0,
+            "running apex {running} vs standing apex {standing}"
+        );
+    }
+
+    fn count_jumps(max_air_jumps: u32) -> usize {
+        let config = Config {
+            max_air_jumps,
+            ..Config::default()
+        };
+        let level = format!(
+            "{}G\n.P{}\n{}\n",
+            ".".repeat(39),
+            ".".repeat(38),
+            "#".repeat(40)
+        );
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+
+        let idle = InputState::default();
+        for _ in 0..30 {
+            player.update(&idle, &world, &config, config.fixed_dt);
+        }
+        assert!(player.on_ground);
+
+        // Press jump three times, 10 steps apart, without landing in between.
+        let press = InputState {
+            jump_pressed: true,
+            ..Default::default()
+        };
+        let mut jumps = 0;
+        for step in 0..30 {
+            let input = if step % 10 == 0 { &press } else { &idle };
+            if player.update(input, &world, &config, config.fixed_dt) {
+                jumps += 1;
+            }
+            assert!(step == 0 || !player.on_ground, "landed at step {step}");
+        }
+        jumps
+    }
+
+    #[test]
+    fn hard_landing_squashes_and_jumping_stretches_only_the_sprite() {
+        let config = Config::default();
+        let level = format!(".P..G\n{}#####\n", ".....\n".repeat(10));
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+
+        let idle = InputState::default();
+        for _ in 0..120 {
+            player.update(&idle, &world, &config, config.fixed_dt);
+            if player.on_ground {
+                break;
+            }
+        }
+        assert!(player.landing_speed() >= config.hard_landing_speed);
+        let squash = player.squash_stretch(&config);
+        assert!(squash.x > 1.0 && squash.y < 1.0, "{squash}");
+        assert_eq!(player.size(), config.player_size);
+
+        for _ in 0..30 {
+            player.update(&idle, &world, &config, config.fixed_dt);
+        }
+        assert_eq!(player.squash_stretch(&config), Vec2::ONE);
+
+        let jump = InputState {
+            jump_pressed: true,
+            ..Default::default()
+        };
+        assert!(player.update(&jump, &world, &config, config.fixed_dt));
+        let stretch = player.squash_stretch(&config);
+        assert!(stretch.x < 1.0 && stretch.y > 1.0, "{stretch}");
+        assert_eq!(player.size(), config.player_size);
+    }
+
+    #[test]
+    fn air_jumps_allow_extra_jumps_per_airtime() {
+        assert_eq!(count_jumps(0), 1);
+        assert_eq!(count_jumps(1), 2);
+        assert_eq!(count_jumps(2), 3);
+    }
+
+    #[test]
+    fn airborne_acceleration_uses_air_accel() {
+        let config = Config {
+            air_accel: 300.0,
+            ..Config::default()
+        };
+        let level = format!(
+            ".P{}G\n{}\n{}\n{}\n",
+            ".".repeat(37),
+            ".".repeat(40),
+            ".".repeat(40),
+            "#".repeat(40)
+        );
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+
+        let input = InputState {
+            move_x: 1.0,
+            ..Default::default()
+        };
+        let steps = 5;
+        for _ in 0..steps {
+            player.update(&input, &world, &config, config.fixed_dt);
+            assert!(!player.on_ground);
+        }
+        let expected = config.air_accel * config.fixed_dt * steps as f32;
+        assert!(
+            (player.vel.x - expected).abs() < 0.01,
+            "airborne speed {} vs expected {expected}",
+            player.vel.x
+        );
+    }
+
+    #[test]
+    fn default_running_jump_accelerates_like_running() {
+        let config = Config::default();
+        let level = format!(
+            ".P{}G\n{}\n{}\n{}\n",
+            ".".repeat(37),
+            ".".repeat(40),
+            ".".repeat(40),
+            "#".repeat(40)
+        );
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+
+        let input = InputState {
+            move_x: 1.0,
+            run_held: true,
+            ..Default::default()
+        };
+        let steps = 5;
+        for _ in 0..steps {
+            player.update(&input, &world, &config, config.fixed_dt);
+            assert!(!player.on_ground);
+        }
+        let expected = config.run_accel * config.fixed_dt * steps as f32;
+        assert!(
+            (player.vel.x - expected).abs() < 0.01,
+            "airborne run speed {} vs expected {expected}",
+            player.vel.x
+        );
+    }
+
+    /// Dashes once, then presses dash again when `early` seconds of the
+    /// cooldown are left. Returns whether the second dash went off.
+    fn dash_again_with_cooldown_left(early: f32) -> bool {
+        let config = Config::default();
+        let level = format!(
+            "{}G\n.P{}\n{}\n",
+            ".".repeat(79),
+            ".".repeat(78),
+            "#".repeat(80)
+        );
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let mut player = Player::new(world.player_spawn, &config);
+        let dash = InputState {
+            dash_pressed: true,
+            ..Default::default()
+        };
+        let idle = InputState::default();
+
+        player.update(&dash, &world, &config, config.fixed_dt);
+        assert!(player.is_dashing());
+        while player.dash_cooldown_timer > early {
+            player.update(&idle, &world, &config, config.fixed_dt);
+        }
+        player.update(&dash, &world, &config, config.fixed_dt);
+        assert!(!player.is_dashing(), "dashed during the cooldown");
+        for _ in 0..(config.dash_buffer_time / config.fixed_dt).ceil() as usize {
+            if player.is_dashing() {
+                return true;
+            }
+            player.update(&idle, &world, &config, config.fixed_dt);
+        }
+        player.is_dashing()
+    }
+
+    #[test]
+    fn dash_pressed_just_before_the_cooldown_ends_still_fires() {
+        let config = Config::default();
+        assert!(dash_again_with_cooldown_left(config.dash_buffer_time * 0.5));
+        assert!(!dash_again_with_cooldown_left(
+            config.dash_buffer_time * 3.0
+        ));
+    }
+
+    #[test]
+    fn player_slides_further_on_ice() {
+        let ground = slide_distance('#');
+        let ice = slide_distance('I');
+        assert!(ground > 0.0);
+        assert!(
+            ice > ground * 2.0,
+            "ice slide {ice} vs ground slide {ground}"
+        );
+    }
 }
@@ -0,0 +1,63 @@
+use macroquad::prelude::*;
+
+use super::{physics, world::World, Config};
+
+/// A turret's shot. It flies in a straight line, ignoring gravity, until it
+/// hits a solid or runs out of lifetime.
+pub struct EnemyProjectile {
+    pub pos: Vec2,
+    vel: Vec2,
+    lifetime: f32,
+}
+
+impl EnemyProjectile {
+    /// Fires from the side of `shooter` facing `dir`.
+    pub fn new(shooter: Rect, dir: f32, config: &Config) -> Self {
+        let size = config.turret_shot_size;
+        let x = if dir >= 0.0 {
+            shooter.x + shooter.w
+        } else {
+            shooter.x - size
+        };
+        Self {
+            pos: vec2(x, shooter.y + (shooter.h - size) * 0.5),
+            vel: vec2(dir * config.turret_shot_speed, 0.0),
+            lifetime: config.turret_shot_lifetime,
+        }
+    }
+
+    /// Advances the shot, returning `false` once it should despawn.
+    pub fn update(&mut self, world: &World, config: &Config, dt: f32) -> bool {
+        self.lifetime -= dt;
+        let size = vec2(config.turret_shot_size, config.turret_shot_size);
+        let result = physics::move_with_collisions(self.pos, size, self.vel, world, dt);
+        self.pos = result.pos;
+
+        let hit_wall = result.touching_left || result.touching_right;
+        self.lifetime > 0.0 && !hit_wall
+    }
+
+    /// Direction of travel, for knocking the player back.
+    pub fn dir(&self) -> f32 {
+        self.vel.x.signum()
+    }
+
+    pub fn rect(&self, config: &Config) -> Rect {
+        physics::rect_at(
+            self.pos,
+            vec2(config.turret_shot_size, config.turret_shot_size),
+        )
+    }
+
+    pub fn draw(&self, config: &Config) {
+        let radius = config.turret_shot_size * 0.5;
+        let center = self.pos + vec2(radius, radius);
+        draw_circle(center.x, center.y, radius, Color::new(0.2, 0.2, 0.25, 1.0));
+        draw_circle(
+            center.x - radius * 0.3,
+            center.y - radius * 0.3,
+            radius * 0.35,
+            Color::new(0.6, 0.6, 0.7, 1.0),
+        );
+    }
+}
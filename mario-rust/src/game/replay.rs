@@ -0,0 +1,173 @@
+//! Plain-text replay files: one line per fixed step, holding `move_x`
+//! followed by a `0`/`1` flag for each edge-triggered or held button.
+//! New buttons are appended at the end, and flags missing from files
+//! written before they existed read as `0`.
+
+use super::InputState;
+
+pub const REPLAY_PATH: &str = "replay.txt";
+
+/// Number of button flags on each line.
+const FLAG_COUNT: usize = 12;
+
+pub fn encode(inputs: &[InputState]) -> String {
+    let mut out = String::with_capacity(inputs.len() * 12);
+    for input in inputs {
+        let flags: [bool; FLAG_COUNT] = [
+            input.jump_pressed,
+            input.jump_released,
+            input.dash_pressed,
+            input.fire_pressed,
+            input.crouch_held,
+            input.up_held,
+            input.run_held,
+            input.start_pressed,
+            input.restart_pressed,
+            input.quit_pressed,
+            input.menu_up_pressed,
+            input.menu_down_pressed,
+        ];
+        out.push_str(&input.move_x.to_string());
+        out.push(' ');
+        out.extend(flags.iter().map(|&flag| if flag { '1' } else { '0' }));
+        out.push('\n');
+    }
+    out
+}
+
+pub fn decode(contents: &str) -> Result<Vec<InputState>, String> {
+    contents
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(idx, line)| {
+            decode_line(line).ok_or_else(|| format!("Bad replay line {}: '{line}'", idx + 1))
+        })
+        .collect()
+}
+
+fn decode_line(line: &str) -> Option<InputState> {
+    let (move_x, flags) = line.trim().split_once(' ')?;
+    let mut flags: Vec<bool> = flags
+        .chars()
+        .map(|ch| match ch {
+            '0' => Some(false),
+            '1' => Some(true),
+            _ => None,
+        })
+        .collect::<Option<_>>()?;
+    if flags.len() > FLAG_COUNT {
+        return None;
+    }
+    flags.resize(FLAG_COUNT, false);
+    let [jump_pressed, jump_released, dash_pressed, fire_pressed, crouch_held, up_held, run_held, start_pressed, restart_pressed, quit_pressed, menu_up_pressed, menu_down_pressed] =
+        flags[..]
+    else {
+        return None;
+    };
+
+    Some(InputState {
+        move_x: move_x.parse().ok()?,
+        jump_pressed,
+        jump_released,
+        dash_pressed,
+        fire_pressed,
+        crouch_held,
+        up_held,
+        run_held,
+        start_pressed,
+        restart_pressed,
+        quit_pressed,
+        menu_up_pressed,
+        menu_down_pressed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{Config, Game};
+    use super::*;
+
+    #[test]
+    fn encode_then_decode_round_trips() {
+        let inputs = vec![
+            InputState {
+                move_x: 1.0,
+                jump_pressed: true,
+                crouch_held: true,
+                run_held: true,
+                ..Default::default()
+            },
+            InputState {
+                move_x: -0.5,
+                quit_pressed: true,
+                ..Default::default()
+            },
+        ];
+
+        let decoded = decode(&encode(&inputs)).expect("replay should parse");
+        assert_eq!(decoded, inputs);
+    }
+
+    #[test]
+    fn older_replays_without_newer_flags_still_load() {
+        let decoded = decode("1 1000000\n-1 01\n").expect("old replay should parse");
+        assert_eq!(
+            decoded,
+            vec![
+                InputState {
+                    move_x: 1.0,
+                    jump_pressed: true,
+                    ..Default::default()
+                },
+                InputState {
+                    move_x: -1.0,
+                    jump_released: true,
+                    ..Default::default()
+                },
+            ]
+        );
+        assert!(decode("0 0000000000000\n").is_err(), "too many flags");
+    }
+
+    #[test]
+    fn saved_recording_replays_to_the_same_position() {
+        let level = "\
+..............................
+..........##..C.......#.......
+..P..C.C..............#.....G.
+##############################
+";
+        let steps = 240;
+        let mut game = Game::headless(level, Config::default()).expect("test level is valid");
+        game.start_recording();
+        for frame in 0..steps {
+            // Fed through `consume_fixed_input` like the frame loop does.
+            game.input = InputState {
+                move_x: if frame < 180 { 1.0 } else { -0.5 },
+                jump_pressed: frame % 45 == 10,
+                jump_released: frame % 45 == 25,
+                start_pressed: frame == 0,
+                ..Default::default()
+            };
+            let input = game.consume_fixed_input();
+            game.step(input);
+        }
+        let saved = encode(game.stop_recording());
+        assert!(game.score > 0, "never scored");
+        assert!(
+            game.player.pos.x > game.world.player_spawn.x + 100.0,
+            "player at {}",
+            game.player.pos
+        );
+
+        let mut replayed = Game::headless(level, Config::default()).expect("test level is valid");
+        replayed.start_playback(decode(&saved).expect("replay should parse"));
+        for _ in 0..steps {
+            replayed.step(InputState::default());
+        }
+
+        assert_eq!(replayed.score, game.score);
+        assert_eq!(replayed.player.pos, game.player.pos);
+    }
+}
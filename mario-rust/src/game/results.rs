@@ -0,0 +1,110 @@
+use macroquad::prelude::*;
+
+use super::speedrun::format_time;
+
+/// Finishing a level faster than this earns a time bonus.
+const PAR_TIME: f64 = 300.0;
+/// Bonus points for every whole second under `PAR_TIME`.
+const BONUS_PER_SECOND: u32 = 50;
+/// Seconds the results screen takes to count the time bonus into the score.
+const BONUS_COUNT_TIME: f32 = 1.5;
+
+/// Per-level tallies for the results screen, reset when a run starts.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LevelStats {
+    /// Fixed steps spent in the level, warps included.
+    pub steps: u32,
+    pub coins: u32,
+    pub stomps: u32,
+    /// Points earned in the level, including the part of the time bonus
+    /// counted in so far.
+    pub score: u32,
+    time_bonus: u32,
+    /// Part of `time_bonus` not yet added to the score.
+    bonus_left: u32,
+}
+
+impl LevelStats {
+    pub fn seconds(&self, fixed_dt: f32) -> f64 {
+        self.steps as f64 * fixed_dt as f64
+    }
+
+    /// Works out the time bonus for finishing now, ready to be counted in.
+    pub fn finish(&mut self, fixed_dt: f32) {
+        self.time_bonus = time_bonus(self.seconds(fixed_dt));
+        self.bonus_left = self.time_bonus;
+    }
+
+    /// Bonus points to add to the score this step, so the whole bonus
+    /// counts in over `BONUS_COUNT_TIME`.
+    pub fn take_bonus(&mut self, fixed_dt: f32) -> u32 {
+        let per_step = (self.time_bonus as f32 * fixed_dt / BONUS_COUNT_TIME).ceil() as u32;
+        let paid = per_step.max(1).min(self.bonus_left);
+        self.bonus_left -= paid;
+        paid
+    }
+
+    /// Whatever is left of the bonus, for leaving the screen early.
+    pub fn take_all_bonus(&mut self) -> u32 {
+        std::mem::take(&mut self.bonus_left)
+    }
+}
+
+fn time_bonus(seconds: f64) -> u32 {
+    (PAR_TIME - seconds).max(0.0) as u32 * BONUS_PER_SECOND
+}
+
+/// The results screen: the level's stats with the time bonus counting down
+/// into the level score.
+pub fn draw(stats: &LevelStats, fixed_dt: f32, color: Color) {
+    let lines = [
+        format!("Time: {}", format_time(stats.seconds(fixed_dt))),
+        format!("Coins: {}", stats.coins),
+        format!("Enemies stomped: {}", stats.stomps),
+        format!("Time bonus: {}", stats.bonus_left),
+        format!("Level score: {}", stats.score),
+    ];
+
+    let center_x = screen_width() * 0.5;
+    let top = screen_height() * 0.3;
+    let title = "Course Complete!";
+    let title_dim = measure_text(title, None, 48, 1.0);
+    draw_text(title, center_x - title_dim.width * 0.5, top, 48.0, color);
+
+    let line_h = 34.0;
+    let left = center_x - 150.0;
+    for (idx, line) in lines.iter().enumerate() {
+        let y = top + 56.0 + line_h * idx as f32;
+        draw_text(line, left, y, 28.0, color);
+    }
+
+    let hint = "Press R to restart.";
+    let hint_dim = measure_text(hint, None, 24, 1.0);
+    let y = top + 56.0 + line_h * (lines.len() as f32 + 0.5);
+    draw_text(hint, center_x - hint_dim.width * 0.5, y, 24.0, color);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_bonus_counts_in_over_the_count_time() {
+        assert_eq!(time_bonus(PAR_TIME + 10.0), 0);
+        assert_eq!(time_bonus(PAR_TIME - 10.5), 10 * BONUS_PER_SECOND);
+
+        let fixed_dt = 1.0 / 60.0;
+        let mut stats = LevelStats {
+            steps: 60 * 40 + 30,
+            ..Default::default()
+        };
+        stats.finish(fixed_dt);
+        let bonus = time_bonus(40.5);
+        assert_eq!(stats.bonus_left, bonus);
+
+        let steps = (BONUS_COUNT_TIME / fixed_dt).round() as usize;
+        let paid: u32 = (0..steps).map(|_| stats.take_bonus(fixed_dt)).sum();
+        assert_eq!(paid, bonus);
+        assert_eq!(stats.take_bonus(fixed_dt), 0);
+    }
+}
@@ -0,0 +1,98 @@
+pub const SAVE_PATH: &str = "save.txt";
+
+/// A run in progress, written when a level is loaded from the stage menu and
+/// when quitting to the title, so it can be continued on the next launch.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SaveState {
+    /// Level file, relative to the assets folder.
+    pub level: String,
+    pub score: u32,
+    pub lives: u32,
+    pub coins: u32,
+}
+
+impl SaveState {
+    /// Reads `path`, or `None` if there is no usable save there.
+    pub fn load(path: &str) -> Option<Self> {
+        let text = std::fs::read_to_string(path).ok()?;
+        match Self::parse(&text) {
+            Ok(save) => Some(save),
+            Err(error) => {
+                eprintln!("Save file {path} ignored: {error}");
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    /// Deletes the save at `path`; a missing file is not an error.
+    pub fn clear(path: &str) -> std::io::Result<()> {
+        match std::fs::remove_file(path) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(error),
+            _ => Ok(()),
+        }
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut level = None;
+        let mut score = None;
+        let mut lives = None;
+        let mut coins = None;
+
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| format!("malformed line '{line}'"))?;
+            let (key, value) = (key.trim(), value.trim());
+            let number = || {
+                value
+                    .parse::<u32>()
+                    .map_err(|_| format!("invalid {key} '{value}'"))
+            };
+            match key.to_ascii_uppercase().as_str() {
+                "LEVEL" => level = Some(value.to_string()),
+                "SCORE" => score = Some(number()?),
+                "LIVES" => lives = Some(number()?),
+                "COINS" => coins = Some(number()?),
+                _ => return Err(format!("unknown key '{key}'")),
+            }
+        }
+
+        match (level, score, lives, coins) {
+            (Some(level), Some(score), Some(lives), Some(coins)) if lives > 0 => Ok(Self {
+                level,
+                score,
+                lives,
+                coins,
+            }),
+            _ => Err("missing or empty fields".to_string()),
+        }
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "LEVEL={}\nSCORE={}\nLIVES={}\nCOINS={}\n",
+            self.level, self.score, self.lives, self.coins
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_state_round_trips_and_rejects_partial_files() {
+        let save = SaveState {
+            level: "levels/level1.txt".to_string(),
+            score: 12_300,
+            lives: 2,
+            coins: 41,
+        };
+        assert_eq!(SaveState::parse(&save.to_text()), Ok(save));
+        assert!(SaveState::parse("LEVEL=levels/level1.txt\nSCORE=5\n").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use macroquad::prelude::*;
+
+use super::{
+    difficulty::Difficulty,
+    level_select::MANIFEST_PATH,
+    sprites::{PlayerPalette, PLAYER_PALETTES},
+    ASSETS_DIR, LEVEL_PATH, VOLUME_STEP,
+};
+
+pub const SETTINGS_PATH: &str = "settings.txt";
+
+/// Player preferences kept between runs in `SETTINGS_PATH`, one `KEY=VALUE`
+/// line each.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub master_volume: f32,
+    pub fullscreen: bool,
+    /// Applied from the next run started on the title screen.
+    pub difficulty: Difficulty,
+    /// Level file to start on, relative to the assets folder.
+    pub level: String,
+    /// Index into `PLAYER_PALETTES`.
+    pub player_palette: usize,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            master_volume: 1.0,
+            fullscreen: false,
+            difficulty: Difficulty::default(),
+            level: LEVEL_PATH.to_string(),
+            player_palette: 0,
+        }
+    }
+}
+
+impl Settings {
+    /// Reads `path`, falling back to the defaults if it doesn't exist.
+    pub fn load(path: &str) -> Self {
+        match std::fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(_) => Self::default(),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> std::io::Result<()> {
+        std::fs::write(path, self.to_text())
+    }
+
+    /// Unknown keys and bad values are reported and ignored.
+    fn parse(text: &str) -> Self {
+        let mut settings = Self::default();
+        for line in text.lines().map(str::trim).filter(|line| !line.is_empty()) {
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!("Settings: ignoring line '{line}'.");
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+            match key.to_ascii_uppercase().as_str() {
+                "VOLUME" => match value.parse::<f32>() {
+                    Ok(volume) if volume.is_finite() => {
+                        settings.master_volume = volume.clamp(0.0, 1.0)
+                    }
+                    _ => eprintln!("Settings: invalid VOLUME '{value}', using default."),
+                },
+                "FULLSCREEN" => match value.parse::<bool>() {
+                    Ok(fullscreen) => settings.fullscreen = fullscreen,
+                    Err(_) => eprintln!("Settings: invalid FULLSCREEN '{value}', using default."),
+                },
+                "DIFFICULTY" => match Difficulty::parse(value) {
+                    Some(difficulty) => settings.difficulty = difficulty,
+                    None => eprintln!("Settings: unknown DIFFICULTY '{value}', using normal."),
+                },
+                "LEVEL" if !value.is_empty() => settings.level = value.to_string(),
+                "PLAYER_COLORS" => match PlayerPalette::find(value) {
+                    Some(palette) => settings.player_palette = palette,
+                    None => eprintln!("Settings: unknown PLAYER_COLORS '{value}', using classic."),
+                },
+                _ => eprintln!("Settings: ignoring unknown key '{key}'."),
+            }
+        }
+        settings
+    }
+
+    fn to_text(&self) -> String {
+        format!(
+            "VOLUME={}\nFULLSCREEN={}\nDIFFICULTY={}\nLEVEL={}\nPLAYER_COLORS={}\n",
+            self.master_volume,
+            self.fullscreen,
+            self.difficulty.name(),
+            self.level,
+            PLAYER_PALETTES[self.player_palette].name
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Row {
+    Volume,
+    Fullscreen,
+    Difficulty,
+    Level,
+    PlayerColors,
+}
+
+const ROWS: [Row; 5] = [
+    Row::Volume,
+    Row::Fullscreen,
+    Row::Difficulty,
+    Row::Level,
+    Row::PlayerColors,
+];
+
+/// What the game should do after a settings menu frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsAction {
+    None,
+    /// A value changed and should be applied right away.
+    Changed,
+    Exit,
+}
+
+/// Options screen: up/down picks a row, left/right changes its value.
+pub struct SettingsMenu {
+    settings: Settings,
+    levels: Vec<String>,
+    selected: usize,
+}
+
+impl SettingsMenu {
+    pub fn new(settings: Settings) -> Self {
+        let mut levels = find_levels();
+        if !levels.contains(&settings.level) {
+            levels.push(settings.level.clone());
+            levels.sort();
+        }
+        Self {
+            settings,
+            levels,
+            selected: 0,
+        }
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn update(&mut self) -> SettingsAction {
+        if is_key_pressed(KeyCode::Escape) {
+            return SettingsAction::Exit;
+        }
+        if is_key_pressed(KeyCode::Up) {
+            self.selected = (self.selected + ROWS.len() - 1) % ROWS.len();
+        }
+        if is_key_pressed(KeyCode::Down) {
+            self.selected = (self.selected + 1) % ROWS.len();
+        }
+
+        let step: isize = if is_key_pressed(KeyCode::Right) {
+            1
+        } else if is_key_pressed(KeyCode::Left) {
+            -1
+        } else {
+            return SettingsAction::None;
+        };
+
+        match ROWS[self.selected] {
+            Row::Volume => {
+                let volume = self.settings.master_volume + VOLUME_STEP * step as f32;
+                // Snap to whole steps so repeated presses don't drift.
+                self.settings.master_volume =
+                    ((volume / VOLUME_STEP).round() * VOLUME_STEP).clamp(0.0, 1.0);
+            }
+            Row::Fullscreen => self.settings.fullscreen = !self.settings.fullscreen,
+            Row::Difficulty => {
+                let all = Difficulty::ALL;
+                let current = all
+                    .iter()
+                    .position(|difficulty| *difficulty == self.settings.difficulty)
+                    .unwrap_or(0) as isize;
+                let next = (current + step).rem_euclid(all.len() as isize) as usize;
+                self.settings.difficulty = all[next];
+            }
+            Row::Level => {
+                let current = self
+                    .levels
+                    .iter()
+                    .position(|level| *level == self.settings.level)
+                    .unwrap_or(0) as isize;
+                let next = (current + step).rem_euclid(self.levels.len() as isize) as usize;
+                self.settings.level = self.levels[next].clone();
+            }
+            Row::PlayerColors => {
+                let len = PLAYER_PALETTES.len() as isize;
+                let next = (self.settings.player_palette as isize + step).rem_euclid(len);
+                self.settings.player_palette = next as usize;
+            }
+        }
+        SettingsAction::Changed
+    }
+
+    pub fn draw(&self) {
+        let title = "Options";
+        let title_dim = measure_text(title, None, 48, 1.0);
+        let center_x = screen_width() * 0.5;
+        let top = screen_height() * 0.3;
+        draw_text(title, center_x - title_dim.width * 0.5, top, 48.0, BLACK);
+
+        for (idx, row) in ROWS.iter().enumerate() {
+            let (label, value) = match row {
+                Row::Volume => (
+                    "Volume",
+                    format!("{:.0}%", self.settings.master_volume * 100.0),
+                ),
+                Row::Fullscreen => (
+                    "Fullscreen",
+                    if self.settings.fullscreen {
+                        "On"
+                    } else {
+                        "Off"
+                    }
+                    .to_string(),
+                ),
+                Row::Difficulty => ("Difficulty", self.settings.difficulty.name().to_string()),
+                Row::Level => ("Starting level", self.settings.level.clone()),
+                Row::PlayerColors => (
+                    "Player colors",
+                    PLAYER_PALETTES[self.settings.player_palette]
+                        .name
+                        .to_string(),
+                ),
+            };
+            let y = top + 60.0 + idx as f32 * 40.0;
+            let color = if idx == self.selected {
+                MAROON
+            } else {
+                DARKGRAY
+            };
+            if idx == self.selected {
+                draw_text(">", center_x - 260.0, y, 32.0, color);
+            }
+            draw_text(label, center_x - 230.0, y, 32.0, color);
+            draw_text(&format!("< {value} >"), center_x + 20.0, y, 32.0, color);
+        }
+
+        let hint = "Up/Down: select, Left/Right: change, Esc: back";
+        let hint_dim = measure_text(hint, None, 20, 1.0);
+        draw_text(
+            hint,
+            center_x - hint_dim.width * 0.5,
+            top + 60.0 + ROWS.len() as f32 * 40.0 + 20.0,
+            20.0,
+            DARKGRAY,
+        );
+    }
+}
+
+/// Every `.txt` level under `assets/levels`, as paths relative to the assets
+/// folder, sorted by name. Empty where directories can't be listed.
+pub fn find_levels() -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(format!("{ASSETS_DIR}/levels")) else {
+        return Vec::new();
+    };
+    let mut levels: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".txt"))
+        .map(|name| format!("levels/{name}"))
+        .filter(|path| path != MANIFEST_PATH)
+        .collect();
+    levels.sort();
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_round_trip_through_text() {
+        let settings = Settings {
+            master_volume: 0.6,
+            fullscreen: true,
+            difficulty: Difficulty::Hard,
+            level: "levels/level2.txt".to_string(),
+            player_palette: 2,
+        };
+        assert_eq!(Settings::parse(&settings.to_text()), settings);
+        assert_eq!(
+            Settings::parse("VOLUME=loud\nBOGUS=1\nPLAYER_COLORS=plaid\n"),
+            Settings::default()
+        );
+    }
+}
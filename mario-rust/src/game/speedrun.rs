@@ -0,0 +1,88 @@
+pub const BEST_TIME_PATH: &str = "best_time.txt";
+
+/// In-game speedrun timer. It counts fixed simulation steps rather than wall
+/// clock time, so a replay of the same inputs always shows the same time.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SpeedrunTimer {
+    pub enabled: bool,
+    steps: u32,
+    running: bool,
+    /// Fewest steps any finished run has taken.
+    best_steps: Option<u32>,
+}
+
+impl SpeedrunTimer {
+    /// A disabled timer that remembers the best time stored at `path`.
+    pub fn load(path: &str) -> Self {
+        let best_steps = std::fs::read_to_string(path)
+            .ok()
+            .and_then(|text| text.trim().parse::<u32>().ok());
+        Self {
+            best_steps,
+            ..Self::default()
+        }
+    }
+
+    /// Zeroes the clock and starts it if the timer is enabled.
+    pub fn restart(&mut self) {
+        self.steps = 0;
+        self.running = self.enabled;
+    }
+
+    pub fn tick(&mut self) {
+        if self.running {
+            self.steps = self.steps.saturating_add(1);
+        }
+    }
+
+    /// Freezes the clock. Returns `true` if this run set a new best.
+    pub fn finish(&mut self) -> bool {
+        if !self.running {
+            return false;
+        }
+        self.running = false;
+        let is_best = self.best_steps.is_none_or(|best| self.steps < best);
+        if is_best {
+            self.best_steps = Some(self.steps);
+        }
+        is_best
+    }
+
+    pub fn save_best(&self, path: &str) -> std::io::Result<()> {
+        match self.best_steps {
+            Some(steps) => std::fs::write(path, format!("{steps}\n")),
+            None => Ok(()),
+        }
+    }
+
+    pub fn elapsed(&self, fixed_dt: f32) -> f64 {
+        self.steps as f64 * fixed_dt as f64
+    }
+
+    pub fn best(&self, fixed_dt: f32) -> Option<f64> {
+        self.best_steps.map(|steps| steps as f64 * fixed_dt as f64)
+    }
+}
+
+/// `seconds` as `mm:ss.mmm`.
+pub fn format_time(seconds: f64) -> String {
+    let millis = (seconds.max(0.0) * 1000.0).round() as u64;
+    format!(
+        "{:02}:{:02}.{:03}",
+        millis / 60_000,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_time_pads_minutes_seconds_and_millis() {
+        assert_eq!(format_time(0.0), "00:00.000");
+        assert_eq!(format_time(83.456), "01:23.456");
+        assert_eq!(format_time(600.5), "10:00.500");
+    }
+}
@@ -1,67 +1,232 @@
 use macroquad::prelude::*;
 
+use super::enemy::EnemyKind;
+
+// Walk frames advance once per this many seconds when moving at full `move_speed`.
+const WALK_FRAME_TIME: f32 = 0.12;
+
+/// Shirt and overall colors for the unpowered player.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlayerPalette {
+    pub name: &'static str,
+    pub shirt: Color,
+    pub overalls: Color,
+}
+
+/// Palettes the options menu cycles through; the first is the default.
+/// None are green, so the powered-up look stays distinct.
+pub const PLAYER_PALETTES: [PlayerPalette; 4] = [
+    PlayerPalette {
+        name: "classic",
+        shirt: Color::new(0.78, 0.14, 0.16, 1.0),
+        overalls: Color::new(0.16, 0.28, 0.78, 1.0),
+    },
+    PlayerPalette {
+        name: "sunset",
+        shirt: Color::new(0.95, 0.55, 0.15, 1.0),
+        overalls: Color::new(0.45, 0.2, 0.6, 1.0),
+    },
+    PlayerPalette {
+        name: "shadow",
+        shirt: Color::new(0.3, 0.3, 0.34, 1.0),
+        overalls: Color::new(0.1, 0.1, 0.14, 1.0),
+    },
+    PlayerPalette {
+        name: "snow",
+        shirt: Color::new(0.95, 0.95, 0.98, 1.0),
+        overalls: Color::new(0.8, 0.16, 0.2, 1.0),
+    },
+];
+
+impl PlayerPalette {
+    /// Index into `PLAYER_PALETTES` of the palette called `name`.
+    pub fn find(name: &str) -> Option<usize> {
+        PLAYER_PALETTES
+            .iter()
+            .position(|palette| palette.name.eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct PlayerMotion {
+    pub on_ground: bool,
+    pub crouching: bool,
+    pub speed_x: f32,
+}
+
+struct PlayerFrames {
+    idle: Texture2D,
+    walk: [Texture2D; 2],
+    jump: Texture2D,
+    crouch: Texture2D,
+}
+
+impl PlayerFrames {
+    fn new(shirt: Color, overalls: Color) -> Self {
+        Self {
+            idle: player_texture(&PLAYER_IDLE, shirt, overalls),
+            walk: [
+                player_texture(&PLAYER_WALK_A, shirt, overalls),
+                player_texture(&PLAYER_WALK_B, shirt, overalls),
+            ],
+            jump: player_texture(&PLAYER_JUMP, shirt, overalls),
+            crouch: player_texture(&PLAYER_CROUCH, shirt, overalls),
+        }
+    }
+}
+
 pub struct Sprites {
-    player_base: Texture2D,
-    player_powered: Texture2D,
+    player_base: PlayerFrames,
+    player_powered: PlayerFrames,
     chestnut_guy: Texture2D,
+    spiky: Texture2D,
 }
 
 impl Sprites {
     pub fn new() -> Self {
-        let player_base = player_texture(
-            Color::new(0.78, 0.14, 0.16, 1.0),
-            Color::new(0.16, 0.28, 0.78, 1.0),
-        );
-        let player_powered = player_texture(
+        let classic = PLAYER_PALETTES[0];
+        let player_base = PlayerFrames::new(classic.shirt, classic.overalls);
+        let player_powered = PlayerFrames::new(
             Color::new(0.18, 0.62, 0.35, 1.0),
             Color::new(0.2, 0.6, 0.86, 1.0),
         );
         let chestnut_guy = chestnut_guy_texture();
+        let spiky = spiky_texture();
 
         Self {
             player_base,
             player_powered,
             chestnut_guy,
+            spiky,
         }
     }
 
-    pub fn player(&self, powered: bool) -> &Texture2D {
-        if powered {
+    /// Rebuilds the unpowered player's frames with new colors. The powered-up
+    /// frames keep their own colors so a power-up still shows.
+    pub fn set_player_colors(&mut self, shirt: Color, overalls: Color) {
+        self.player_base = PlayerFrames::new(shirt, overalls);
+    }
+
+    /// Picks the player frame for the current motion. `anim_time` is expected to
+    /// advance proportionally to horizontal speed so the walk cycle speeds up
+    /// as the player runs faster.
+    pub fn player_frame(&self, powered: bool, motion: PlayerMotion, anim_time: f32) -> &Texture2D {
+        let frames = if powered {
             &self.player_powered
         } else {
             &self.player_base
+        };
+
+        if motion.crouching {
+            &frames.crouch
+        } else if !motion.on_ground {
+            &frames.jump
+        } else if motion.speed_x.abs() < 1.0 {
+            &frames.idle
+        } else {
+            let index = (anim_time / WALK_FRAME_TIME) as usize % frames.walk.len();
+            &frames.walk[index]
         }
     }
 
-    pub fn chestnut_guy(&self) -> &Texture2D {
-        &self.chestnut_guy
+    /// Body texture for an enemy of `kind`; anything without its own sprite
+    /// uses the chestnut guy, tinted by the caller.
+    pub fn enemy(&self, kind: EnemyKind) -> &Texture2D {
+        match kind {
+            EnemyKind::Spiky => &self.spiky,
+            _ => &self.chestnut_guy,
+        }
     }
 }
 
-fn player_texture(shirt: Color, overalls: Color) -> Texture2D {
-    // 11x14 pixels, scaled 2x to match the default 22x28 player hitbox.
-    let pixels: [&str; 14] = [
-        "...RRRRR...",
-        "..RRRRRRR..",
-        "..RRRRRRR..",
-        "...SSSSS...",
-        "..SSSSSSS..",
-        "..SSKKKSS..",
-        "...RRRRR...",
-        "..RRBBBBR..",
-        "..RBBBBBR..",
-        "..BBBBBBB..",
-        "..BBYYBB...",
-        "...BBBBB...",
-        "..KK..KK...",
-        "..KK..KK...",
-    ];
+// 11x14 pixels, scaled 2x to match the default 22x28 player hitbox.
+const PLAYER_IDLE: [&str; 14] = [
+    "...RRRRR...",
+    "..RRRRRRR..",
+    "..RRRRRRR..",
+    "...SSSSS...",
+    "..SSSSSSS..",
+    "..SSKKKSS..",
+    "...RRRRR...",
+    "..RRBBBBR..",
+    "..RBBBBBR..",
+    "..BBBBBBB..",
+    "..BBYYBB...",
+    "...BBBBB...",
+    "..KK..KK...",
+    "..KK..KK...",
+];
+
+const PLAYER_WALK_A: [&str; 14] = [
+    "...RRRRR...",
+    "..RRRRRRR..",
+    "..RRRRRRR..",
+    "...SSSSS...",
+    "..SSSSSSS..",
+    "..SSKKKSS..",
+    "...RRRRR...",
+    "..RRBBBBR..",
+    "..RBBBBBR..",
+    "..BBBBBBB..",
+    "..BBYYBB...",
+    "..BBBBBBB..",
+    ".KK....KK..",
+    ".KK.....KK.",
+];
+
+const PLAYER_WALK_B: [&str; 14] = [
+    "...RRRRR...",
+    "..RRRRRRR..",
+    "..RRRRRRR..",
+    "...SSSSS...",
+    "..SSSSSSS..",
+    "..SSKKKSS..",
+    "...RRRRR...",
+    "..RRBBBBR..",
+    "..RBBBBBR..",
+    "..BBBBBBB..",
+    "..BBYYBB...",
+    "...BBBBB...",
+    "....KKK....",
+    "....KK.....",
+];
 
+const PLAYER_JUMP: [&str; 14] = [
+    "...RRRRR..S",
+    "..RRRRRRR.R",
+    "..RRRRRRRR.",
+    "...SSSSS...",
+    "..SSSSSSS..",
+    "..SSKKKSS..",
+    "...RRRRR...",
+    "..RRBBBBR..",
+    "..RBBBBBR..",
+    "..BBBBBBB..",
+    "..BBYYBBB..",
+    "..BBB.BBB..",
+    ".KK....KK..",
+    "KK......KK.",
+];
+
+// 11x9 pixels, drawn into the shorter crouching hitbox.
+const PLAYER_CROUCH: [&str; 9] = [
+    "...RRRRR...",
+    "..RRRRRRR..",
+    "...SSSSS...",
+    "..SSKKKSS..",
+    "..RRBBBBR..",
+    "..RBBBBBR..",
+    "..BBYYBBB..",
+    "..BBBBBBB..",
+    ".KKK...KKK.",
+];
+
+fn player_texture(pixels: &[&str], shirt: Color, overalls: Color) -> Texture2D {
     let skin = Color::new(0.98, 0.82, 0.68, 1.0);
     let dark = Color::new(0.12, 0.08, 0.07, 1.0);
     let button = Color::new(0.98, 0.88, 0.2, 1.0);
 
-    texture_from_pixels(pixels.as_slice(), |ch| match ch {
+    texture_from_pixels(pixels, |ch| match ch {
         '.' => None,
         'R' => Some(shirt),
         'B' => Some(overalls),
@@ -100,6 +265,36 @@ fn chestnut_guy_texture() -> Texture2D {
     })
 }
 
+fn spiky_texture() -> Texture2D {
+    // 12x10 pixels, same size as the chestnut guy.
+    let pixels: [&str; 10] = [
+        "..S..S..S...",
+        ".SRS.SRS.S..",
+        ".RRRRRRRRRS.",
+        "SRRWKRRWKRRS",
+        ".RRWKRRWKRR.",
+        "SRRRRRRRRRRS",
+        ".RRRRRRRRRR.",
+        "..RRRRRRRR..",
+        "..TT....TT..",
+        ".TTT....TTT.",
+    ];
+
+    let red = Color::new(0.8, 0.2, 0.15, 1.0);
+    let spike = Color::new(0.95, 0.92, 0.8, 1.0);
+    let toes = Color::new(0.45, 0.12, 0.08, 1.0);
+
+    texture_from_pixels(pixels.as_slice(), |ch| match ch {
+        '.' => None,
+        'R' => Some(red),
+        'S' => Some(spike),
+        'T' => Some(toes),
+        'W' => Some(WHITE),
+        'K' => Some(BLACK),
+        _ => None,
+    })
+}
+
 fn texture_from_pixels<F>(rows: &[&str], mut color_for: F) -> Texture2D
 where
     F: FnMut(char) -> Option<Color>,
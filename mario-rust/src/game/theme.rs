@@ -0,0 +1,99 @@
+use macroquad::prelude::*;
+
+/// Colors for one visual theme. Adding a theme means adding a palette here
+/// and a name for it in `LevelTheme`.
+pub struct Theme {
+    pub sky: Color,
+    pub ground: Color,
+    pub far_hills: Color,
+    pub near_hills: Color,
+    /// `None` hides the procedural clouds, e.g. underground.
+    pub clouds: Option<Color>,
+    /// HUD text, chosen to stay readable against `sky`.
+    pub hud_text: Color,
+    /// Grass tufts along the bottom of the screen, drawn in front of
+    /// everything; `None` leaves the view unobstructed.
+    pub foreground: Option<Color>,
+}
+
+const OVERWORLD: Theme = Theme {
+    sky: Color::new(0.45, 0.75, 0.95, 1.0),
+    ground: Color::new(0.25, 0.55, 0.25, 1.0),
+    far_hills: Color::new(0.35, 0.68, 0.84, 1.0),
+    near_hills: Color::new(0.28, 0.62, 0.34, 1.0),
+    clouds: Some(Color::new(1.0, 1.0, 1.0, 0.9)),
+    hud_text: BLACK,
+    foreground: Some(Color::new(0.16, 0.42, 0.18, 0.85)),
+};
+
+const CAVE: Theme = Theme {
+    sky: Color::new(0.08, 0.07, 0.1, 1.0),
+    ground: Color::new(0.36, 0.3, 0.26, 1.0),
+    far_hills: Color::new(0.14, 0.12, 0.16, 1.0),
+    near_hills: Color::new(0.2, 0.17, 0.2, 1.0),
+    clouds: None,
+    hud_text: WHITE,
+    foreground: None,
+};
+
+const NIGHT: Theme = Theme {
+    sky: Color::new(0.07, 0.1, 0.25, 1.0),
+    ground: Color::new(0.16, 0.34, 0.24, 1.0),
+    far_hills: Color::new(0.12, 0.18, 0.36, 1.0),
+    near_hills: Color::new(0.12, 0.26, 0.22, 1.0),
+    clouds: Some(Color::new(0.55, 0.6, 0.75, 0.5)),
+    hud_text: WHITE,
+    foreground: Some(Color::new(0.05, 0.12, 0.1, 0.85)),
+};
+
+const SNOW: Theme = Theme {
+    sky: Color::new(0.78, 0.86, 0.94, 1.0),
+    ground: Color::new(0.9, 0.93, 0.97, 1.0),
+    far_hills: Color::new(0.68, 0.76, 0.86, 1.0),
+    near_hills: Color::new(0.84, 0.89, 0.95, 1.0),
+    clouds: Some(Color::new(1.0, 1.0, 1.0, 0.95)),
+    hud_text: BLACK,
+    foreground: None,
+};
+
+/// Visual theme named by a level's `THEME=` header.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LevelTheme {
+    #[default]
+    Overworld,
+    Cave,
+    Night,
+    Snow,
+}
+
+impl LevelTheme {
+    pub const ALL: [Self; 4] = [Self::Overworld, Self::Cave, Self::Night, Self::Snow];
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "overworld" => Some(Self::Overworld),
+            "cave" => Some(Self::Cave),
+            "night" => Some(Self::Night),
+            "snow" => Some(Self::Snow),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Overworld => "overworld",
+            Self::Cave => "cave",
+            Self::Night => "night",
+            Self::Snow => "snow",
+        }
+    }
+
+    pub fn palette(self) -> &'static Theme {
+        match self {
+            Self::Overworld => &OVERWORLD,
+            Self::Cave => &CAVE,
+            Self::Night => &NIGHT,
+            Self::Snow => &SNOW,
+        }
+    }
+}
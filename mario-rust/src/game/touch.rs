@@ -0,0 +1,122 @@
+use macroquad::prelude::*;
+
+/// Button size as a fraction of the window height.
+const BUTTON_SCALE: f32 = 0.2;
+const MIN_BUTTON_SIZE: f32 = 72.0;
+const MARGIN: f32 = 24.0;
+
+/// Which on-screen buttons are held this frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TouchZones {
+    pub left: bool,
+    pub right: bool,
+    pub jump: bool,
+}
+
+impl TouchZones {
+    /// Movement from the arrow buttons; holding both cancels out.
+    pub fn move_x(self) -> f32 {
+        match (self.left, self.right) {
+            (true, false) => -1.0,
+            (false, true) => 1.0,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Left and right arrows in the bottom-left corner, jump in the
+/// bottom-right, for a window of size `screen`.
+fn button_rects(screen: Vec2) -> [Rect; 3] {
+    let size = (screen.y * BUTTON_SCALE).max(MIN_BUTTON_SIZE);
+    let y = screen.y - MARGIN - size;
+    [
+        Rect::new(MARGIN, y, size, size),
+        Rect::new(MARGIN * 2.0 + size, y, size, size),
+        Rect::new(screen.x - MARGIN - size, y, size, size),
+    ]
+}
+
+/// The buttons under any of `points`, so one finger can hold an arrow while
+/// another presses jump.
+pub fn pressed_zones(points: &[Vec2], screen: Vec2) -> TouchZones {
+    let [left, right, jump] = button_rects(screen);
+    let hit = |rect: Rect| points.iter().any(|&point| rect.contains(point));
+    TouchZones {
+        left: hit(left),
+        right: hit(right),
+        jump: hit(jump),
+    }
+}
+
+/// Window positions of every finger on the screen, plus the mouse cursor
+/// while its left button is held.
+pub fn pointer_positions() -> Vec<Vec2> {
+    let mut points: Vec<Vec2> = touches()
+        .into_iter()
+        .filter(|touch| !matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled))
+        .map(|touch| touch.position)
+        .collect();
+    if is_mouse_button_down(MouseButton::Left) {
+        points.push(mouse_position().into());
+    }
+    points
+}
+
+/// Semi-transparent buttons, brighter while held. Call with the default
+/// camera set.
+pub fn draw(held: TouchZones, screen: Vec2) {
+    let [left, right, jump] = button_rects(screen);
+    for (rect, pressed, label) in [
+        (left, held.left, "<"),
+        (right, held.right, ">"),
+        (jump, held.jump, "^"),
+    ] {
+        let alpha = if pressed { 0.45 } else { 0.2 };
+        draw_rectangle(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            Color::new(1.0, 1.0, 1.0, alpha),
+        );
+        draw_rectangle_lines(
+            rect.x,
+            rect.y,
+            rect.w,
+            rect.h,
+            2.0,
+            Color::new(1.0, 1.0, 1.0, 0.5),
+        );
+        let font_size = rect.h * 0.5;
+        let dim = measure_text(label, None, font_size as u16, 1.0);
+        draw_text(
+            label,
+            rect.center().x - dim.width * 0.5,
+            rect.center().y + dim.height * 0.5,
+            font_size,
+            Color::new(1.0, 1.0, 1.0, 0.7),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simultaneous_touches_hold_separate_buttons() {
+        let screen = vec2(960.0, 540.0);
+        let [left, right, jump] = button_rects(screen);
+
+        let zones = pressed_zones(&[left.center(), jump.center()], screen);
+        assert_eq!(zones.move_x(), -1.0);
+        assert!(zones.jump);
+
+        let zones = pressed_zones(&[left.center(), right.center()], screen);
+        assert_eq!(zones.move_x(), 0.0);
+        assert!(!zones.jump);
+
+        let zones = pressed_zones(&[screen * 0.5], screen);
+        assert_eq!(zones, TouchZones::default());
+    }
+}
@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+use super::{
+    physics,
+    world::{BlockPayload, World},
+    Config,
+};
+
+/// Sanity checks run on every freshly parsed level. A player spawn stuck in
+/// a wall or a goal needing more coins than the level holds is an error;
+/// anything merely suspicious comes back as warnings so unusual levels still
+/// load.
+pub fn check(world: &World, config: &Config) -> Result<Vec<String>, String> {
+    let tile = config.tile_size;
+    let size = config.player_size;
+    let spawn_pos = world.player_spawn + vec2((tile - size.x) * 0.5, tile - size.y);
+    if !world.is_rect_free(physics::rect_at(spawn_pos, size)) {
+        return Err("Player spawn is inside a solid tile".to_string());
+    }
+    let coins = available_coins(world);
+    if world.meta.coins_required > coins {
+        return Err(format!(
+            "COINS_REQUIRED={} but the level only has {coins} coins",
+            world.meta.coins_required
+        ));
+    }
+
+    let mut warnings = Vec::new();
+    let gravity_dir = world.meta.gravity_dir.unwrap_or(config.gravity_dir);
+    let goal_center_x = world.goal_tile.x + tile * 0.5;
+    if world
+        .surface_y_for_x(goal_center_x, world.goal_tile.y, gravity_dir, config)
+        .is_none()
+    {
+        warnings.push("Goal has no ground beneath it".to_string());
+    }
+    // The flood fill below assumes gravity pulls down.
+    if gravity_dir > 0.0 && !goal_reachable(world, config) {
+        warnings.push("Goal looks unreachable from the player spawn".to_string());
+    }
+
+    Ok(warnings)
+}
+
+/// Loose coins plus coin question blocks: every coin the player can collect.
+fn available_coins(world: &World) -> u32 {
+    let block_coins = world
+        .question_blocks
+        .iter()
+        .filter(|block| block.payload == BlockPayload::Coin)
+        .count();
+    (world.coins.len() + block_coins) as u32
+}
+
+/// Rough flood fill over empty tiles. Standing on a solid tile refills a
+/// budget of upward moves worth one full jump; sideways moves and falling
+/// are always allowed, so air control is overestimated. Standing on a warp
+/// pipe also leads to the top of its partner, a spring refills a bigger
+/// budget for its launch, and ladders can be climbed
+/// (and stood on) at any height. Moving platforms,
+/// wall jumps and hazards are ignored, which is why this only warns.
+fn goal_reachable(world: &World, config: &Config) -> bool {
+    let tile = config.tile_size;
+    let jump_height = config.jump_speed_max.max(config.jump_speed).powi(2) / (2.0 * config.gravity);
+    let spring_height = config.spring_speed.powi(2) / (2.0 * config.gravity);
+    let max_rise = (jump_height / tile).floor().max(0.0) as usize;
+    let spring_rise = ((spring_height / tile).floor().max(0.0) as usize).max(max_rise);
+
+    let to_cell = |pos: Vec2| ((pos.x / tile) as i32, (pos.y / tile) as i32);
+    let (start_col, start_row) = to_cell(world.player_spawn);
+    let goal = to_cell(world.goal_tile);
+
+    let (width, height) = (world.width as i32, world.height as i32);
+    let is_open = |col: i32, row: i32| {
+        (0..width).contains(&col) && (0..height).contains(&row) && !world.is_solid_tile(col, row)
+    };
+    let index =
+        |col: i32, row: i32, rise: usize| ((row * width + col) as usize) * (spring_rise + 1) + rise;
+
+    let mut visited = vec![false; (width * height) as usize * (spring_rise + 1)];
+    let mut queue = VecDeque::new();
+    queue.push_back((start_col, start_row, max_rise));
+    visited[index(start_col, start_row, max_rise)] = true;
+
+    while let Some((col, row, rise)) = queue.pop_front() {
+        if (col, row) == goal {
+            return true;
+        }
+
+        let on_ladder = world.is_ladder_tile(col, row, config);
+        let grounded = world.is_solid_tile(col, row + 1)
+            || on_ladder
+            || world.is_ladder_tile(col, row + 1, config);
+        let rise = if world.is_spring_tile(col, row + 1, config) {
+            spring_rise
+        } else if grounded {
+            max_rise
+        } else {
+            rise
+        };
+        let mut moves = vec![
+            (col - 1, row, rise),
+            (col + 1, row, rise),
+            (col, row + 1, 0),
+        ];
+        if on_ladder {
+            moves.push((col, row - 1, max_rise));
+        } else if rise > 0 {
+            moves.push((col, row - 1, rise - 1));
+        }
+        if let Some((dest_col, dest_row)) = world.warp_destination(col, row + 1, config) {
+            moves.push((dest_col, dest_row - 1, max_rise));
+        }
+
+        for (next_col, next_row, next_rise) in moves {
+            if !is_open(next_col, next_row) {
+                continue;
+            }
+            let idx = index(next_col, next_row, next_rise);
+            if !visited[idx] {
+                visited[idx] = true;
+                queue.push_back((next_col, next_row, next_rise));
+            }
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn walled_off_goal_warns_unreachable() {
+        let config = Config::default();
+        let level = "\
+..........#...
+..........#...
+..P.......#.G.
+##############
+";
+        let world = World::from_ascii(level, &config).expect("test level is valid");
+        let warnings = check(&world, &config).expect("spawn is clear");
+        assert!(
+            warnings
+                .iter()
+                .any(|warning| warning.contains("unreachable")),
+            "warnings: {warnings:?}"
+        );
+
+        let low_wall = "\
+..............
+..............
+..P.......#.G.
+##############
+";
+        let world = World::from_ascii(low_wall, &config).expect("test level is valid");
+        assert!(check(&world, &config).expect("spawn is clear").is_empty());
+    }
+
+    #[test]
+    fn requiring_more_coins_than_the_level_has_is_an_error() {
+        let config = Config::default();
+        let level = "\
+.....?.....
+...........
+.P..C...G..
+###########
+";
+        let enough = format!("COINS_REQUIRED=2\n\n{level}");
+        assert!(World::from_ascii(&enough, &config).is_ok());
+        let too_many = format!("COINS_REQUIRED=3\n\n{level}");
+        let error = World::from_ascii(&too_many, &config).err();
+        assert!(
+            error
+                .as_ref()
+                .is_some_and(|error| error.contains("only has 2 coins")),
+            "error: {error:?}"
+        );
+    }
+}
@@ -1,7 +1,14 @@
 use macroquad::file::load_string;
 use macroquad::prelude::*;
 
-use super::{physics, Config};
+use super::physics::{self, Slope, SlopeDir};
+use super::{
+    enemy::EnemyKind, mushroom::Mushroom, platform::MovingPlatform, theme::LevelTheme, validate,
+    Config,
+};
+
+/// Widest row a level may expand to, in tiles.
+const MAX_LEVEL_WIDTH: usize = 4096;
 
 const FALLBACK_LEVEL: &str = "\
 ................................\n\
@@ -13,16 +20,236 @@ const FALLBACK_LEVEL: &str = "\
 ..P....M....E................G..\n\
 #######...########..######...###\n";
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockPayload {
+    Coin,
+    Mushroom,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct QuestionBlock {
+    pub rect: Rect,
+    pub payload: BlockPayload,
+    pub used: bool,
+}
+
+/// A solid pipe tile the player can crouch on to warp to its partner.
+#[derive(Clone, Copy, Debug)]
+pub struct WarpPipe {
+    pub rect: Rect,
+    /// Index of the linked pipe in `World::pipes`.
+    pub dest: usize,
+}
+
+/// A bounce pad. Landing on its top launches the player or an enemy upward.
+#[derive(Clone, Copy, Debug)]
+pub struct Spring {
+    pub rect: Rect,
+    /// Seconds left on the compress/extend animation after a launch.
+    timer: f32,
+}
+
+/// The bonkable block a player's head hit from below.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BonkTarget {
+    Brick(usize),
+    QuestionBlock(usize),
+}
+
+/// Settings from the optional `KEY=VALUE` header at the top of a level file.
+#[derive(Clone, Debug, Default)]
+pub struct LevelMeta {
+    pub name: String,
+    /// Replaces `Config::gravity` while this level is loaded.
+    pub gravity: Option<f32>,
+    /// Replaces `Config::terminal_velocity`, for floaty or heavy levels.
+    pub terminal_velocity: Option<f32>,
+    /// Replaces `Config::jump_speed`.
+    pub jump_speed: Option<f32>,
+    /// Replaces `Config::gravity_dir`; `-1.0` runs the level upside down.
+    pub gravity_dir: Option<f32>,
+    pub theme: LevelTheme,
+    /// Dead enemies come back at their spawn once the player is
+    /// `Config::respawn_distance` away from it.
+    pub respawn_enemies: bool,
+    /// Points an arrow at the goal when the player stands idle.
+    pub hints: bool,
+    /// Coins the player must collect in the level before the goal opens.
+    pub coins_required: u32,
+}
+
+impl LevelMeta {
+    /// Splits the header off `contents`, returning the metadata and the
+    /// remaining tile rows. The header ends at a blank line or at the first
+    /// line without an `=`. Unknown keys and bad values are reported and
+    /// ignored.
+    pub fn parse(contents: &str) -> (Self, &str) {
+        let mut meta = Self::default();
+        let mut rest = contents;
+
+        while !rest.is_empty() {
+            let (line, tail) = rest.split_once('\n').unwrap_or((rest, ""));
+            let line = line.trim();
+            if line.is_empty() {
+                rest = tail;
+                break;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                break;
+            };
+            rest = tail;
+
+            let (key, value) = (key.trim(), value.trim());
+            match key.to_ascii_uppercase().as_str() {
+                "NAME" => meta.name = value.to_string(),
+                "GRAVITY" => meta.gravity = parse_positive(key, value),
+                "TERMINAL_VELOCITY" => meta.terminal_velocity = parse_positive(key, value),
+                "JUMP_SPEED" => meta.jump_speed = parse_positive(key, value),
+                "GRAVITY_DIR" => match value.to_ascii_lowercase().as_str() {
+                    "down" => meta.gravity_dir = Some(1.0),
+                    "up" => meta.gravity_dir = Some(-1.0),
+                    _ => eprintln!("Level header: invalid GRAVITY_DIR '{value}', using down."),
+                },
+                "RESPAWN_ENEMIES" => match value.to_ascii_lowercase().as_str() {
+                    "on" => meta.respawn_enemies = true,
+                    "off" => meta.respawn_enemies = false,
+                    _ => eprintln!("Level header: invalid RESPAWN_ENEMIES '{value}', using off."),
+                },
+                "HINTS" => match value.to_ascii_lowercase().as_str() {
+                    "on" => meta.hints = true,
+                    "off" => meta.hints = false,
+                    _ => eprintln!("Level header: invalid HINTS '{value}', using off."),
+                },
+                "COINS_REQUIRED" => match value.parse::<u32>() {
+                    Ok(count) => meta.coins_required = count,
+                    Err(_) => eprintln!("Level header: invalid COINS_REQUIRED '{value}', using 0."),
+                },
+                "THEME" => match LevelTheme::parse(value) {
+                    Some(theme) => meta.theme = theme,
+                    None => eprintln!("Level header: unknown THEME '{value}', using overworld."),
+                },
+                _ => eprintln!("Level header: ignoring unknown key '{key}'."),
+            }
+        }
+
+        (meta, rest)
+    }
+
+    /// The header lines that `parse` would read back into this metadata,
+    /// followed by the blank separator line. Empty if everything is default.
+    pub fn to_header(&self) -> String {
+        let mut header = String::new();
+        if !self.name.is_empty() {
+            header.push_str(&format!("NAME={}\n", self.name));
+        }
+        if let Some(gravity) = self.gravity {
+            header.push_str(&format!("GRAVITY={gravity}\n"));
+        }
+        if let Some(speed) = self.terminal_velocity {
+            header.push_str(&format!("TERMINAL_VELOCITY={speed}\n"));
+        }
+        if let Some(speed) = self.jump_speed {
+            header.push_str(&format!("JUMP_SPEED={speed}\n"));
+        }
+        if let Some(dir) = self.gravity_dir {
+            let name = if dir < 0.0 { "up" } else { "down" };
+            header.push_str(&format!("GRAVITY_DIR={name}\n"));
+        }
+        if self.theme != LevelTheme::default() {
+            header.push_str(&format!("THEME={}\n", self.theme.name()));
+        }
+        if self.respawn_enemies {
+            header.push_str("RESPAWN_ENEMIES=on\n");
+        }
+        if self.hints {
+            header.push_str("HINTS=on\n");
+        }
+        if self.coins_required > 0 {
+            header.push_str(&format!("COINS_REQUIRED={}\n", self.coins_required));
+        }
+        if !header.is_empty() {
+            header.push('\n');
+        }
+        header
+    }
+}
+
+/// A header value that must be a positive number, or `None` (keeping the
+/// default) with a warning if it isn't.
+fn parse_positive(key: &str, value: &str) -> Option<f32> {
+    match value.parse::<f32>() {
+        Ok(number) if number.is_finite() && number > 0.0 => Some(number),
+        _ => {
+            eprintln!("Level header: invalid {key} '{value}', using default.");
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnemySpawn {
+    pub tile_pos: Vec2,
+    pub kind: EnemyKind,
+    /// Walk speed multiplier from a digit after the tile, e.g. `E2`.
+    pub speed_tier: u32,
+    /// Patrol limits from `|` markers on the spawn's row, in world X.
+    pub min_x: Option<f32>,
+    pub max_x: Option<f32>,
+}
+
+impl EnemySpawn {
+    fn new(tile_pos: Vec2, kind: EnemyKind) -> Self {
+        Self {
+            tile_pos,
+            kind,
+            speed_tier: 1,
+            min_x: None,
+            max_x: None,
+        }
+    }
+}
+
+/// Purely visual background props. Adding one means a tile character in
+/// `World::from_ascii` and a case in `draw_decoration`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecoKind {
+    Bush,
+    Rock,
+}
+
 pub struct World {
     pub solids: Vec<Rect>,
     solid_tiles: Vec<bool>,
+    ice_tiles: Vec<bool>,
+    conveyor_tiles: Vec<i8>,
+    pub bricks: Vec<Rect>,
+    pub question_blocks: Vec<QuestionBlock>,
+    pub platforms: Vec<MovingPlatform>,
+    pub hazards: Vec<Rect>,
+    pub slopes: Vec<Slope>,
+    pub water_regions: Vec<Rect>,
+    /// One rect per `H` tile; stacked tiles form a climbable column.
+    pub ladders: Vec<Rect>,
+    pub checkpoints: Vec<Vec2>,
+    pub pipes: Vec<WarpPipe>,
+    pub springs: Vec<Spring>,
+    /// Top-left corners of non-colliding props, drawn behind everything.
+    pub decorations: Vec<(Vec2, DecoKind)>,
+    colliders: Vec<Rect>,
     pub coins: Vec<Vec2>,
-    pub mushrooms: Vec<Vec2>,
-    pub enemy_spawns: Vec<Vec2>,
+    pub mushrooms: Vec<Mushroom>,
+    /// Star power-up centers.
+    pub stars: Vec<Vec2>,
+    pub enemy_spawns: Vec<EnemySpawn>,
     pub player_spawn: Vec2,
     pub goal_tile: Vec2,
     pub width: usize,
     pub height: usize,
+    tile_size: f32,
+    pub meta: LevelMeta,
+    /// The tile characters this world was built from, padded to `width`,
+    /// so the level can be edited and written back out.
+    pub tiles: Vec<Vec<char>>,
 }
 
 impl World {
@@ -43,11 +270,13 @@ impl World {
     }
 
     pub fn from_ascii(contents: &str, config: &Config) -> Result<Self, String> {
-        let lines: Vec<&str> = contents
+        let (meta, contents) = LevelMeta::parse(contents);
+        let lines: Vec<String> = contents
             .lines()
             .map(str::trim_end)
             .filter(|line| !line.is_empty())
-            .collect();
+            .map(expand_runs)
+            .collect::<Result<_, _>>()?;
 
         let height = lines.len();
         let width = lines
@@ -60,17 +289,43 @@ impl World {
             return Err("Level has no tiles".to_string());
         }
 
+        let tiles = lines
+            .iter()
+            .map(|line| {
+                let mut row: Vec<char> = line.chars().collect();
+                row.resize(width, '.');
+                row
+            })
+            .collect();
+
         let tile_size = config.tile_size;
         let mut solid_tiles = vec![false; width * height];
+        let mut ice_tiles = vec![false; width * height];
+        let mut conveyor_tiles = vec![0i8; width * height];
         let mut solids = Vec::new();
+        let mut bricks = Vec::new();
+        let mut question_blocks = Vec::new();
+        let mut hazards = Vec::new();
+        let mut slopes = Vec::new();
+        let mut water_regions: Vec<Rect> = Vec::new();
+        let mut ladders = Vec::new();
+        let mut checkpoints = Vec::new();
+        let mut pipe_rects = Vec::new();
+        let mut springs = Vec::new();
+        let mut decorations = Vec::new();
+        let mut platform_starts = Vec::new();
+        let mut platform_ends = Vec::new();
         let mut coins = Vec::new();
         let mut mushroom_tiles = Vec::new();
+        let mut stars = Vec::new();
         let mut enemy_spawns = Vec::new();
+        let mut patrol_markers = Vec::new();
         let mut player_spawn = None;
         let mut goal_tile = None;
 
         for (row, line) in lines.iter().enumerate() {
-            for (col, ch) in line.chars().enumerate() {
+            let mut chars = line.chars().enumerate().peekable();
+            while let Some((col, ch)) = chars.next() {
                 let world_x = col as f32 * tile_size;
                 let world_y = row as f32 * tile_size;
                 let tile_pos = vec2(world_x, world_y);
@@ -80,9 +335,63 @@ impl World {
                         solid_tiles[row * width + col] = true;
                         solids.push(physics::rect_at(tile_pos, vec2(tile_size, tile_size)));
                     }
+                    'I' => {
+                        solid_tiles[row * width + col] = true;
+                        ice_tiles[row * width + col] = true;
+                        solids.push(physics::rect_at(tile_pos, vec2(tile_size, tile_size)));
+                    }
+                    '<' | '>' => {
+                        solid_tiles[row * width + col] = true;
+                        conveyor_tiles[row * width + col] = if ch == '<' { -1 } else { 1 };
+                        solids.push(physics::rect_at(tile_pos, vec2(tile_size, tile_size)));
+                    }
+                    'O' => {
+                        let rect = physics::rect_at(tile_pos, vec2(tile_size, tile_size));
+                        solid_tiles[row * width + col] = true;
+                        solids.push(rect);
+                        pipe_rects.push(rect);
+                    }
+                    'B' => {
+                        let rect = physics::rect_at(tile_pos, vec2(tile_size, tile_size));
+                        solid_tiles[row * width + col] = true;
+                        solids.push(rect);
+                        bricks.push(rect);
+                    }
+                    '?' => {
+                        // `?M` holds a mushroom; the `M` cell is part of the block token.
+                        let payload = if chars.next_if(|(_, next)| *next == 'M').is_some() {
+                            BlockPayload::Mushroom
+                        } else {
+                            BlockPayload::Coin
+                        };
+                        solid_tiles[row * width + col] = true;
+                        let rect = physics::rect_at(tile_pos, vec2(tile_size, tile_size));
+                        solids.push(rect);
+                        question_blocks.push(QuestionBlock {
+                            rect,
+                            payload,
+                            used: false,
+                        });
+                    }
                     'C' => coins.push(vec2(world_x + tile_size * 0.5, world_y + tile_size * 0.5)),
+                    '*' => stars.push(vec2(world_x + tile_size * 0.5, world_y + tile_size * 0.5)),
                     'M' => mushroom_tiles.push(tile_pos),
-                    'E' => enemy_spawns.push(tile_pos),
+                    'E' => {
+                        // `E2`..`E9` walk faster; the digit cell is part of the enemy token.
+                        let mut spawn = EnemySpawn::new(tile_pos, EnemyKind::Walker);
+                        if let Some((_, digit)) =
+                            chars.next_if(|(_, next)| matches!(next, '1'..='9'))
+                        {
+                            spawn.speed_tier = digit.to_digit(10).unwrap_or(1);
+                        }
+                        enemy_spawns.push(spawn);
+                    }
+                    'F' => enemy_spawns.push(EnemySpawn::new(tile_pos, EnemyKind::Flyer)),
+                    'K' => enemy_spawns.push(EnemySpawn::new(tile_pos, EnemyKind::Shelled)),
+                    'T' => enemy_spawns.push(EnemySpawn::new(tile_pos, EnemyKind::Turret)),
+                    'Z' => enemy_spawns.push(EnemySpawn::new(tile_pos, EnemyKind::Boss)),
+                    'Y' => enemy_spawns.push(EnemySpawn::new(tile_pos, EnemyKind::Hopper)),
+                    'S' => enemy_spawns.push(EnemySpawn::new(tile_pos, EnemyKind::Spiky)),
                     'P' => {
                         if player_spawn.is_some() {
                             return Err("Multiple player spawns found".to_string());
@@ -95,6 +404,45 @@ impl World {
                         }
                         goal_tile = Some(tile_pos);
                     }
+                    '^' => {
+                        // Shorter than a full tile so grazing the tip doesn't kill.
+                        let inset = tile_size * 0.4;
+                        hazards.push(Rect::new(
+                            world_x,
+                            world_y + inset,
+                            tile_size,
+                            tile_size - inset,
+                        ));
+                    }
+                    '/' | '\\' => slopes.push(Slope {
+                        rect: physics::rect_at(tile_pos, vec2(tile_size, tile_size)),
+                        dir: if ch == '/' {
+                            SlopeDir::Up
+                        } else {
+                            SlopeDir::Down
+                        },
+                    }),
+                    'W' => match water_regions.last_mut() {
+                        // Merge horizontal runs of water into one region.
+                        Some(last) if last.y == world_y && last.x + last.w == world_x => {
+                            last.w += tile_size;
+                        }
+                        _ => water_regions.push(Rect::new(world_x, world_y, tile_size, tile_size)),
+                    },
+                    '~' => checkpoints.push(tile_pos),
+                    '[' => platform_starts.push((col, row)),
+                    ']' => platform_ends.push((col, row)),
+                    '|' => patrol_markers.push((col, row)),
+                    'J' => {
+                        solid_tiles[row * width + col] = true;
+                        springs.push(Spring {
+                            rect: physics::rect_at(tile_pos, vec2(tile_size, tile_size)),
+                            timer: 0.0,
+                        });
+                    }
+                    'H' => ladders.push(Rect::new(world_x, world_y, tile_size, tile_size)),
+                    't' => decorations.push((tile_pos, DecoKind::Bush)),
+                    'o' => decorations.push((tile_pos, DecoKind::Rock)),
                     '.' => {}
                     _ => {
                         return Err(format!("Unexpected tile '{ch}'"));
@@ -103,19 +451,56 @@ impl World {
             }
         }
 
+        apply_patrol_markers(&mut enemy_spawns, &patrol_markers, tile_size);
+        let pipes = link_pipes(&pipe_rects, tile_size)?;
+
         let player_spawn = player_spawn.ok_or_else(|| "Missing player spawn".to_string())?;
         let goal_tile = goal_tile.ok_or_else(|| "Missing goal tile".to_string())?;
 
+        let platforms = pair_platforms(&platform_starts, &platform_ends)?
+            .into_iter()
+            .map(|((start_col, start_row), (end_col, end_row))| {
+                let size = config.platform_size;
+                let start = vec2(start_col as f32, start_row as f32) * tile_size;
+                let mut end = vec2(end_col as f32, end_row as f32) * tile_size;
+                // Keep the whole platform inside the span marked by `[` and `]`.
+                if end_row == start_row {
+                    end.x = (end.x + tile_size - size.x).max(start.x);
+                } else {
+                    end.y = (end.y + tile_size - size.y).max(start.y);
+                }
+                MovingPlatform::new(start, end, size, config.platform_speed)
+            })
+            .collect();
+
         let mut world = Self {
             solids,
             solid_tiles,
+            ice_tiles,
+            conveyor_tiles,
+            bricks,
+            question_blocks,
+            platforms,
+            hazards,
+            slopes,
+            water_regions,
+            ladders,
+            checkpoints,
+            pipes,
+            springs,
+            decorations,
+            colliders: Vec::new(),
             coins,
             mushrooms: Vec::new(),
+            stars,
             enemy_spawns,
             player_spawn,
             goal_tile,
             width,
             height,
+            tile_size,
+            meta,
+            tiles,
         };
 
         world.mushrooms = mushroom_tiles
@@ -125,36 +510,116 @@ impl World {
                 let tile = config.tile_size;
                 let x = tile_pos.x + (tile - size.x) * 0.5;
                 let sample_x = tile_pos.x + tile * 0.5;
-                let base_y = world
-                    .ground_y_for_x(sample_x, tile_pos.y, config)
-                    .unwrap_or(tile_pos.y + tile);
-                let y = base_y - size.y;
-                vec2(x, y)
+                let gravity_dir = world.meta.gravity_dir.unwrap_or(config.gravity_dir);
+                let surface = world.surface_y_for_x(sample_x, tile_pos.y, gravity_dir, config);
+                let y = if gravity_dir < 0.0 {
+                    surface.unwrap_or(tile_pos.y)
+                } else {
+                    surface.unwrap_or(tile_pos.y + tile) - size.y
+                };
+                Mushroom::new(vec2(x, y))
             })
             .collect();
+        world.refresh_colliders();
+
+        for warning in validate::check(&world, config)? {
+            eprintln!("Level warning: {warning}");
+        }
 
         Ok(world)
     }
 
-    pub fn draw(&self, config: &Config) {
+    /// `time` is gameplay time in seconds and drives the coin spin.
+    pub fn draw(&self, config: &Config, time: f32) {
         let tile = config.tile_size;
 
+        for (pos, kind) in &self.decorations {
+            draw_decoration(Rect::new(pos.x, pos.y, tile, tile), *kind);
+        }
+
+        for ladder in &self.ladders {
+            draw_ladder(*ladder);
+        }
+        for spring in &self.springs {
+            draw_spring(spring, config);
+        }
+
         for solid in &self.solids {
             draw_rectangle(
                 solid.x,
                 solid.y,
                 solid.w,
                 solid.h,
-                Color::new(0.25, 0.55, 0.25, 1.0),
+                self.meta.theme.palette().ground,
             );
         }
 
+        for (idx, _) in self.ice_tiles.iter().enumerate().filter(|(_, ice)| **ice) {
+            let col = (idx % self.width) as f32;
+            let row = (idx / self.width) as f32;
+            draw_ice(Rect::new(col * tile, row * tile, tile, tile));
+        }
+
+        for (idx, dir) in self.conveyor_tiles.iter().enumerate() {
+            if *dir != 0 {
+                let col = (idx % self.width) as f32;
+                let row = (idx / self.width) as f32;
+                draw_conveyor(Rect::new(col * tile, row * tile, tile, tile), *dir as f32);
+            }
+        }
+
+        for slope in &self.slopes {
+            draw_slope(slope);
+        }
+
+        for hazard in &self.hazards {
+            draw_hazard(*hazard);
+        }
+
+        for mushroom in &self.mushrooms {
+            mushroom.draw(config);
+        }
+
+        for brick in &self.bricks {
+            draw_brick(*brick);
+        }
+
+        for block in &self.question_blocks {
+            draw_question_block(block);
+        }
+
         for coin in &self.coins {
-            draw_circle(coin.x, coin.y, tile * 0.2, Color::new(0.95, 0.8, 0.2, 1.0));
+            draw_coin(*coin, tile * 0.2, time);
         }
 
-        self.draw_mushrooms(config);
-        self.draw_goal_post(config);
+        for star in &self.stars {
+            draw_star(*star, tile * 0.35, time);
+        }
+
+        for platform in &self.platforms {
+            platform.draw();
+        }
+    }
+
+    pub fn draw_checkpoints(&self, active: Option<Vec2>, config: &Config) {
+        let tile = config.tile_size;
+        for checkpoint in &self.checkpoints {
+            let pole_w = tile * 0.1;
+            let pole_x = checkpoint.x + tile * 0.3;
+            draw_rectangle(pole_x, checkpoint.y, pole_w, tile, DARKGRAY);
+
+            let flag_color = if active == Some(*checkpoint) {
+                Color::new(0.2, 0.75, 0.3, 1.0)
+            } else {
+                Color::new(0.7, 0.7, 0.7, 1.0)
+            };
+            draw_triangle(
+                vec2(pole_x + pole_w, checkpoint.y),
+                vec2(pole_x + pole_w, checkpoint.y + tile * 0.4),
+                vec2(pole_x + pole_w + tile * 0.45, checkpoint.y + tile * 0.2),
+                flag_color,
+            );
+        }
     }
 
     pub fn goal_trigger_rect(&self, config: &Config) -> Rect {
@@ -172,39 +637,189 @@ impl World {
         Rect::new(pole_x, pole_y, pole_w, pole_height)
     }
 
-    fn draw_mushrooms(&self, config: &Config) {
-        let size = config.mushroom_size;
-        for pos in &self.mushrooms {
-            let stem_w = size.x * 0.35;
-            let stem_h = size.y * 0.45;
-            let stem_x = pos.x + (size.x - stem_w) * 0.5;
-            let stem_y = pos.y + size.y - stem_h;
+    /// Drawn after the player so they can sink into a pipe when warping.
+    pub fn draw_pipes(&self) {
+        for pipe in &self.pipes {
+            let rect = pipe.rect;
             draw_rectangle(
-                stem_x,
-                stem_y,
-                stem_w,
-                stem_h,
-                Color::new(0.95, 0.9, 0.75, 1.0),
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h,
+                Color::new(0.15, 0.65, 0.2, 1.0),
             );
-
-            let cap_h = size.y * 0.6;
             draw_rectangle(
-                pos.x,
-                pos.y,
-                size.x,
-                cap_h,
-                Color::new(0.85, 0.15, 0.55, 1.0),
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h * 0.3,
+                Color::new(0.2, 0.75, 0.25, 1.0),
             );
-            draw_rectangle(
-                pos.x + size.x * 0.15,
-                pos.y + cap_h * 0.25,
-                size.x * 0.2,
-                cap_h * 0.35,
-                WHITE,
+            draw_rectangle_lines(
+                rect.x,
+                rect.y,
+                rect.w,
+                rect.h * 0.3,
+                2.0,
+                Color::new(0.05, 0.35, 0.1, 1.0),
             );
         }
     }
 
+    /// Tile coordinates of the pipe linked to the pipe at (`col`, `row`).
+    pub fn warp_destination(&self, col: i32, row: i32, config: &Config) -> Option<(i32, i32)> {
+        let tile = config.tile_size;
+        let pipe = self.pipes.iter().find(|pipe| {
+            (pipe.rect.x / tile) as i32 == col && (pipe.rect.y / tile) as i32 == row
+        })?;
+        let dest = self.pipes[pipe.dest].rect;
+        Some(((dest.x / tile) as i32, (dest.y / tile) as i32))
+    }
+
+    /// Everything entities collide with: static solids plus moving platforms.
+    /// Movement goes through the `physics::Solids` grid lookup instead of
+    /// scanning this.
+    pub fn colliders(&self) -> &[Rect] {
+        &self.colliders
+    }
+
+    /// Moves every mushroom, dropping those that fell out of the level.
+    pub fn update_mushrooms(&mut self, config: &Config, dt: f32) {
+        let mut mushrooms = std::mem::take(&mut self.mushrooms);
+        mushrooms.retain_mut(|mushroom| mushroom.update(self, config, dt));
+        self.mushrooms = mushrooms;
+    }
+
+    pub fn update_platforms(&mut self, dt: f32) {
+        for platform in &mut self.platforms {
+            platform.update(dt);
+        }
+        self.refresh_colliders();
+    }
+
+    pub fn update_springs(&mut self, dt: f32) {
+        for spring in &mut self.springs {
+            spring.timer = (spring.timer - dt).max(0.0);
+        }
+    }
+
+    /// The spring `rect` is standing on, judged by its bottom edge resting on
+    /// the spring's top under its horizontal center, or its top edge against
+    /// the spring's underside when `gravity_dir` points up. Side contact
+    /// never counts.
+    pub fn spring_under(&self, rect: Rect, gravity_dir: f32) -> Option<usize> {
+        let center_x = rect.x + rect.w * 0.5;
+        self.springs.iter().position(|spring| {
+            let gap = if gravity_dir < 0.0 {
+                rect.y - spring.rect.bottom()
+            } else {
+                rect.bottom() - spring.rect.y
+            };
+            center_x >= spring.rect.x && center_x < spring.rect.x + spring.rect.w && gap.abs() < 1.0
+        })
+    }
+
+    /// Starts the launch animation of spring `idx`.
+    pub fn bounce_spring(&mut self, idx: usize, config: &Config) {
+        if let Some(spring) = self.springs.get_mut(idx) {
+            spring.timer = config.spring_anim_time;
+        }
+    }
+
+    pub fn is_spring_tile(&self, col: i32, row: i32, config: &Config) -> bool {
+        let pos = vec2(col as f32, row as f32) * config.tile_size;
+        self.springs
+            .iter()
+            .any(|spring| spring.rect.x == pos.x && spring.rect.y == pos.y)
+    }
+
+    pub fn reset_platforms(&mut self, platforms: &[MovingPlatform]) {
+        self.platforms = platforms.to_vec();
+        self.refresh_colliders();
+    }
+
+    /// Offset to apply to something standing on a platform that just moved.
+    pub fn platform_carry(&self, rect: Rect) -> Vec2 {
+        self.platforms
+            .iter()
+            .find_map(|platform| platform.carry(rect))
+            .unwrap_or(Vec2::ZERO)
+    }
+
+    fn refresh_colliders(&mut self) {
+        self.colliders.clear();
+        self.colliders.extend_from_slice(&self.solids);
+        self.colliders
+            .extend(self.platforms.iter().map(|platform| platform.rect));
+        self.colliders
+            .extend(self.springs.iter().map(|spring| spring.rect));
+    }
+
+    /// Picks the brick or question block most directly above `probe`, like the
+    /// classic games do when the head overlaps two blocks.
+    pub fn bonk_target(&self, probe: Rect) -> Option<BonkTarget> {
+        let bricks = self
+            .bricks
+            .iter()
+            .enumerate()
+            .map(|(idx, rect)| (BonkTarget::Brick(idx), *rect));
+        let blocks = self
+            .question_blocks
+            .iter()
+            .enumerate()
+            .map(|(idx, block)| (BonkTarget::QuestionBlock(idx), block.rect));
+
+        let mut best: Option<(BonkTarget, f32)> = None;
+        for (target, rect) in bricks.chain(blocks) {
+            if !physics::rects_intersect(probe, rect) {
+                continue;
+            }
+            let overlap = (probe.x + probe.w).min(rect.x + rect.w) - probe.x.max(rect.x);
+            if best.is_none_or(|(_, best_overlap)| overlap > best_overlap) {
+                best = Some((target, overlap));
+            }
+        }
+        best.map(|(target, _)| target)
+    }
+
+    /// Removes the brick at `index` from both the brick list and the collision set.
+    pub fn break_brick(&mut self, index: usize, config: &Config) -> Rect {
+        let brick = self.bricks.swap_remove(index);
+        self.remove_solid(brick, config);
+        brick
+    }
+
+    pub fn reset_bricks(&mut self, bricks: &[Rect], config: &Config) {
+        for brick in std::mem::take(&mut self.bricks) {
+            self.remove_solid(brick, config);
+        }
+        for brick in bricks {
+            self.add_solid(*brick, config);
+        }
+        self.bricks = bricks.to_vec();
+    }
+
+    fn add_solid(&mut self, rect: Rect, config: &Config) {
+        self.set_solid_tile_at(rect, config, true);
+        self.solids.push(rect);
+        self.refresh_colliders();
+    }
+
+    fn remove_solid(&mut self, rect: Rect, config: &Config) {
+        self.set_solid_tile_at(rect, config, false);
+        self.solids.retain(|solid| *solid != rect);
+        self.refresh_colliders();
+    }
+
+    fn set_solid_tile_at(&mut self, rect: Rect, config: &Config, solid: bool) {
+        let col = (rect.x / config.tile_size).floor() as i32;
+        let row = (rect.y / config.tile_size).floor() as i32;
+        if col < 0 || row < 0 || col as usize >= self.width || row as usize >= self.height {
+            return;
+        }
+        self.solid_tiles[row as usize * self.width + col as usize] = solid;
+    }
+
     pub fn is_solid_tile(&self, col: i32, row: i32) -> bool {
         if col < 0 || row < 0 {
             return false;
@@ -217,6 +832,111 @@ impl World {
         self.solid_tiles[row * self.width + col]
     }
 
+    pub fn is_in_water(&self, point: Vec2) -> bool {
+        self.water_regions
+            .iter()
+            .any(|region| region.contains(point))
+    }
+
+    /// The ladder tile under the horizontal center of `rect` that `rect`
+    /// overlaps, if any.
+    pub fn ladder_at(&self, rect: Rect) -> Option<Rect> {
+        let center_x = rect.x + rect.w * 0.5;
+        self.ladders.iter().copied().find(|ladder| {
+            center_x >= ladder.x
+                && center_x < ladder.x + ladder.w
+                && rect.y < ladder.y + ladder.h
+                && rect.y + rect.h > ladder.y
+        })
+    }
+
+    /// Top edge of the ladder column `ladder` belongs to.
+    pub fn ladder_top(&self, ladder: Rect) -> f32 {
+        let mut top = ladder.y;
+        while self
+            .ladders
+            .iter()
+            .any(|other| other.x == ladder.x && other.y == top - ladder.h)
+        {
+            top -= ladder.h;
+        }
+        top
+    }
+
+    /// Ladder tops can be stood on like the top of a one-way platform. Returns
+    /// the top a falling `rect` crossed this step, having started at or above
+    /// it with its bottom at `prev_bottom`.
+    pub fn ladder_top_crossed(&self, prev_bottom: f32, rect: Rect) -> Option<f32> {
+        let center_x = rect.x + rect.w * 0.5;
+        self.ladders
+            .iter()
+            .filter(|ladder| center_x >= ladder.x && center_x < ladder.x + ladder.w)
+            .filter(|ladder| prev_bottom <= ladder.y && rect.y + rect.h >= ladder.y)
+            .find(|ladder| self.ladder_top(**ladder) == ladder.y)
+            .map(|ladder| ladder.y)
+    }
+
+    pub fn is_ladder_tile(&self, col: i32, row: i32, config: &Config) -> bool {
+        let pos = vec2(col as f32, row as f32) * config.tile_size;
+        self.ladders
+            .iter()
+            .any(|ladder| ladder.x == pos.x && ladder.y == pos.y)
+    }
+
+    pub fn draw_water(&self) {
+        for region in &self.water_regions {
+            draw_rectangle(
+                region.x,
+                region.y,
+                region.w,
+                region.h,
+                Color::new(0.2, 0.45, 0.9, 0.4),
+            );
+        }
+    }
+
+    /// True if `rect` doesn't overlap any solid or platform.
+    pub fn is_rect_free(&self, rect: Rect) -> bool {
+        !self
+            .colliders
+            .iter()
+            .any(|solid| physics::rects_intersect(rect, *solid))
+    }
+
+    /// True if the tile directly under the middle of `rect`'s feet is ice.
+    pub fn is_on_ice(&self, rect: Rect, config: &Config) -> bool {
+        self.tile_index_below(rect, config)
+            .is_some_and(|idx| self.ice_tiles[idx])
+    }
+
+    /// Moves something standing on a conveyor along with the belt, stopping
+    /// at walls like any other horizontal move.
+    pub fn conveyor_carry(&self, pos: Vec2, size: Vec2, config: &Config, dt: f32) -> Vec2 {
+        let dir = self
+            .tile_index_below(physics::rect_at(pos, size), config)
+            .map_or(0, |idx| self.conveyor_tiles[idx]);
+        if dir == 0 {
+            return pos;
+        }
+
+        let belt_vel = vec2(dir as f32 * config.conveyor_speed, 0.0);
+        physics::move_with_collisions(pos, size, belt_vel, self, dt).pos
+    }
+
+    fn tile_index_below(&self, rect: Rect, config: &Config) -> Option<usize> {
+        let tile = config.tile_size;
+        let col = ((rect.x + rect.w * 0.5) / tile).floor() as i32;
+        let row = ((rect.y + rect.h + 1.0) / tile).floor() as i32;
+        if col < 0 || row < 0 || col as usize >= self.width || row as usize >= self.height {
+            return None;
+        }
+        Some(row as usize * self.width + col as usize)
+    }
+
+    /// Top of the first solid tile at or below `start_y` in the column under
+    /// `world_x`, searching down to the bottom of the level however tall it
+    /// is. Spawns over a shaft settle on its floor, where they would fall
+    /// to anyway.
     pub fn ground_y_for_x(&self, world_x: f32, start_y: f32, config: &Config) -> Option<f32> {
         let tile = config.tile_size;
         let col = (world_x / tile).floor() as i32;
@@ -229,35 +949,59 @@ impl World {
         None
     }
 
-    pub fn camera_for_focus(&self, focus: Vec2, config: &Config) -> Camera2D {
+    /// `ground_y_for_x` in the direction of `gravity_dir`: with gravity
+    /// pointing up, the bottom of the first solid tile at or above `start_y`.
+    pub fn surface_y_for_x(
+        &self,
+        world_x: f32,
+        start_y: f32,
+        gravity_dir: f32,
+        config: &Config,
+    ) -> Option<f32> {
+        if gravity_dir >= 0.0 {
+            return self.ground_y_for_x(world_x, start_y, config);
+        }
+        let tile = config.tile_size;
+        let col = (world_x / tile).floor() as i32;
+        let start_row = (start_y / tile).floor() as i32;
+        (0..=start_row)
+            .rev()
+            .find(|&row| self.is_solid_tile(col, row))
+            .map(|row| (row + 1) as f32 * tile)
+    }
+
+    /// Camera centered on `focus`, clamped to the level, showing `view`
+    /// world units. Both axes use the same scale as long as `view` has the
+    /// aspect of the area it's drawn into, as `Letterbox::fit` guarantees.
+    pub fn camera_for_focus(&self, focus: Vec2, view: Vec2, config: &Config) -> Camera2D {
         let world_w = self.width as f32 * config.tile_size;
         let world_h = self.height as f32 * config.tile_size;
-        let screen_w = screen_width();
-        let screen_h = screen_height();
+        let (view_w, view_h) = (view.x, view.y);
 
         let mut cam_x = focus.x;
         let mut cam_y = focus.y;
 
-        if world_w > screen_w {
-            cam_x = cam_x.clamp(screen_w * 0.5, world_w - screen_w * 0.5);
+        if world_w > view_w {
+            cam_x = cam_x.clamp(view_w * 0.5, world_w - view_w * 0.5);
         } else {
             cam_x = world_w * 0.5;
         }
 
-        if world_h > screen_h {
-            cam_y = cam_y.clamp(screen_h * 0.5, world_h - screen_h * 0.5);
+        if world_h > view_h {
+            cam_y = cam_y.clamp(view_h * 0.5, world_h - view_h * 0.5);
         } else {
             cam_y = world_h * 0.5;
         }
 
         Camera2D {
             target: vec2(cam_x, cam_y),
-            zoom: vec2(2.0 / screen_w, 2.0 / screen_h),
+            zoom: vec2(2.0 / view_w, 2.0 / view_h),
             ..Default::default()
         }
     }
 
-    fn draw_goal_post(&self, config: &Config) {
+    /// The flagpole, grayed out while `locked`.
+    pub fn draw_goal_post(&self, locked: bool, config: &Config) {
         let tile = config.tile_size;
         let goal_center_x = self.goal_tile.x + tile * 0.5;
         let base_y = self
@@ -269,13 +1013,18 @@ impl World {
         let pole_x = self.goal_tile.x + tile * 0.5 - pole_w * 0.5;
         let pole_y = base_y - pole_height;
 
-        draw_rectangle(pole_x, pole_y, pole_w, pole_height, GRAY);
+        let (pole_color, flag_color) = if locked {
+            (DARKGRAY, Color::new(0.35, 0.35, 0.4, 1.0))
+        } else {
+            (GRAY, RED)
+        };
+        draw_rectangle(pole_x, pole_y, pole_w, pole_height, pole_color);
         draw_rectangle(
             pole_x + pole_w,
             pole_y + tile * 0.3,
             tile * 0.55,
             tile * 0.35,
-            RED,
+            flag_color,
         );
         draw_rectangle(
             self.goal_tile.x + tile * 0.4,
@@ -286,3 +1035,578 @@ impl World {
         );
     }
 }
+
+/// Grid broadphase: the solid tiles under `area`, looked up in
+/// `solid_tiles` row by row, then any moving platforms overlapping it.
+impl physics::Solids for World {
+    fn solids_near(&self, area: Rect, out: &mut Vec<Rect>) {
+        let tile = self.tile_size;
+        let first_col = (area.x / tile).floor().max(0.0) as i32;
+        let first_row = (area.y / tile).floor().max(0.0) as i32;
+        let last_col = ((area.x + area.w) / tile)
+            .floor()
+            .min(self.width as f32 - 1.0) as i32;
+        let last_row = ((area.y + area.h) / tile)
+            .floor()
+            .min(self.height as f32 - 1.0) as i32;
+        for row in first_row..=last_row {
+            for col in first_col..=last_col {
+                let rect = Rect::new(col as f32 * tile, row as f32 * tile, tile, tile);
+                if self.is_solid_tile(col, row) && physics::rects_intersect(area, rect) {
+                    out.push(rect);
+                }
+            }
+        }
+        out.extend(
+            self.platforms
+                .iter()
+                .map(|platform| platform.rect)
+                .filter(|rect| physics::rects_intersect(area, *rect)),
+        );
+    }
+}
+
+type TileCoord = (usize, usize);
+
+/// Links pipes in pairs by order of appearance (reading rows top to bottom,
+/// left to right): the first with the second, the third with the fourth.
+fn link_pipes(rects: &[Rect], tile_size: f32) -> Result<Vec<WarpPipe>, String> {
+    if rects.len() % 2 == 1 {
+        let last = rects[rects.len() - 1];
+        return Err(format!(
+            "Warp pipe at ({}, {}) has no partner",
+            (last.x / tile_size) as i32,
+            (last.y / tile_size) as i32
+        ));
+    }
+    Ok(rects
+        .iter()
+        .enumerate()
+        .map(|(idx, rect)| WarpPipe {
+            rect: *rect,
+            dest: idx ^ 1,
+        })
+        .collect())
+}
+
+/// Matches each `[` with the nearest `]` to its right on the same row, or
+/// failing that the nearest one below it in the same column.
+fn pair_platforms(
+    starts: &[TileCoord],
+    ends: &[TileCoord],
+) -> Result<Vec<(TileCoord, TileCoord)>, String> {
+    let mut unused: Vec<TileCoord> = ends.to_vec();
+    let mut pairs = Vec::with_capacity(starts.len());
+
+    for &(col, row) in starts {
+        let same_row = unused
+            .iter()
+            .enumerate()
+            .filter(|(_, (end_col, end_row))| *end_row == row && *end_col > col)
+            .min_by_key(|(_, (end_col, _))| *end_col);
+        let same_col = unused
+            .iter()
+            .enumerate()
+            .filter(|(_, (end_col, end_row))| *end_col == col && *end_row > row)
+            .min_by_key(|(_, (_, end_row))| *end_row);
+
+        let Some((idx, _)) = same_row.or(same_col) else {
+            return Err(format!(
+                "Platform start at ({col}, {row}) has no matching ']'"
+            ));
+        };
+        pairs.push(((col, row), unused.swap_remove(idx)));
+    }
+
+    if let Some((col, row)) = unused.first() {
+        return Err(format!(
+            "Platform end at ({col}, {row}) has no matching '['"
+        ));
+    }
+
+    Ok(pairs)
+}
+
+/// Expands run-length groups in a tile row: a tile character followed by a
+/// count repeats it, so `#10` is ten `#` tiles. Plain rows pass through
+/// unchanged. `E` followed by a digit is an enemy speed tier, not a run.
+fn expand_runs(line: &str) -> Result<String, String> {
+    let mut expanded = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(ch) = chars.next() {
+        if ch.is_ascii_digit() {
+            return Err(format!("Run length without a tile in row '{line}'"));
+        }
+        if ch == 'E' {
+            expanded.push(ch);
+            if let Some(tier) = chars.next_if(|next| matches!(next, '1'..='9')) {
+                expanded.push(tier);
+            }
+            continue;
+        }
+
+        let mut count = 0usize;
+        let mut has_count = false;
+        while let Some(digit) = chars.peek().and_then(|next| next.to_digit(10)) {
+            count = count.saturating_mul(10).saturating_add(digit as usize);
+            has_count = true;
+            chars.next();
+        }
+        let count = if has_count { count } else { 1 };
+
+        if expanded.len() + count > MAX_LEVEL_WIDTH {
+            return Err(format!(
+                "Row is wider than the {MAX_LEVEL_WIDTH} tile limit"
+            ));
+        }
+        expanded.extend(std::iter::repeat_n(ch, count));
+    }
+    Ok(expanded)
+}
+
+/// Bounds each enemy spawn by the nearest `|` marker on either side of it in
+/// the same row. Spawns without a marker on a side keep that side open.
+fn apply_patrol_markers(spawns: &mut [EnemySpawn], markers: &[TileCoord], tile_size: f32) {
+    for spawn in spawns {
+        let col = (spawn.tile_pos.x / tile_size).round() as usize;
+        let row = (spawn.tile_pos.y / tile_size).round() as usize;
+        let in_row = markers.iter().filter(|(_, marker_row)| *marker_row == row);
+
+        spawn.min_x = in_row
+            .clone()
+            .filter(|(marker_col, _)| *marker_col < col)
+            .map(|(marker_col, _)| *marker_col)
+            .max()
+            .map(|marker_col| (marker_col + 1) as f32 * tile_size);
+        spawn.max_x = in_row
+            .filter(|(marker_col, _)| *marker_col > col)
+            .map(|(marker_col, _)| *marker_col)
+            .min()
+            .map(|marker_col| marker_col as f32 * tile_size);
+    }
+}
+
+/// Fakes a spinning coin by narrowing the ellipse with a sine of time. The
+/// phase comes from the coin's position so neighbours don't spin in sync.
+fn draw_coin(center: Vec2, radius: f32, time: f32) {
+    const SPINS_PER_SECOND: f32 = 0.8;
+    let phase = center.x * 0.037 + center.y * 0.061;
+    let angle = time * SPINS_PER_SECOND * std::f32::consts::PI + phase;
+    let half_w = radius * angle.cos().abs().max(0.12);
+
+    draw_ellipse(
+        center.x,
+        center.y,
+        half_w,
+        radius,
+        0.0,
+        Color::new(0.95, 0.8, 0.2, 1.0),
+    );
+    draw_ellipse(
+        center.x,
+        center.y,
+        half_w * 0.45,
+        radius * 0.6,
+        0.0,
+        Color::new(1.0, 0.93, 0.55, 1.0),
+    );
+}
+
+/// Five-pointed star that pulses gently in size.
+fn draw_star(center: Vec2, radius: f32, time: f32) {
+    let radius = radius * (1.0 + (time * 6.0).sin() * 0.08);
+    let point = |angle: f32, r: f32| center + Vec2::from_angle(angle) * r;
+    let color = Color::new(1.0, 0.85, 0.15, 1.0);
+    let step = std::f32::consts::TAU / 5.0;
+    for idx in 0..5 {
+        let angle = -std::f32::consts::FRAC_PI_2 + step * idx as f32;
+        let tip = point(angle, radius);
+        let left = point(angle - step * 0.5, radius * 0.45);
+        let right = point(angle + step * 0.5, radius * 0.45);
+        draw_triangle(center, tip, left, color);
+        draw_triangle(center, tip, right, color);
+    }
+}
+
+/// Squashes on launch, then overshoots back up past its rest height.
+fn draw_spring(spring: &Spring, config: &Config) {
+    let rect = spring.rect;
+    let t = if config.spring_anim_time > 0.0 {
+        1.0 - spring.timer / config.spring_anim_time
+    } else {
+        1.0
+    };
+    let stretch = if spring.timer > 0.0 {
+        if t < 0.3 {
+            -t / 0.3
+        } else {
+            (1.0 - t) / 0.7 * 0.5
+        }
+    } else {
+        0.0
+    };
+    let bottom = rect.y + rect.h;
+    let plate_h = rect.h * 0.15;
+    let rest_h = rect.h - plate_h;
+    let top = bottom - plate_h - rest_h * (1.0 + stretch * 0.5);
+
+    let coil = Color::new(0.75, 0.75, 0.8, 1.0);
+    let plate = Color::new(0.9, 0.25, 0.2, 1.0);
+    draw_rectangle(rect.x, bottom - plate_h, rect.w, plate_h, plate);
+    let coil_h = bottom - plate_h - (top + plate_h);
+    for turn in 0..3 {
+        let y = top + plate_h + coil_h * (turn as f32 + 0.5) / 3.0;
+        draw_line(
+            rect.x + rect.w * 0.25,
+            y - coil_h / 6.0,
+            rect.x + rect.w * 0.75,
+            y + coil_h / 6.0,
+            3.0,
+            coil,
+        );
+    }
+    draw_rectangle(rect.x, top, rect.w, plate_h, plate);
+}
+
+fn draw_ladder(rect: Rect) {
+    let color = Color::new(0.6, 0.4, 0.2, 1.0);
+    let rail = rect.w * 0.1;
+    draw_rectangle(rect.x + rect.w * 0.15, rect.y, rail, rect.h, color);
+    draw_rectangle(rect.x + rect.w * 0.75, rect.y, rail, rect.h, color);
+    for rung in 0..3 {
+        let y = rect.y + rect.h * (0.2 + rung as f32 * 0.3);
+        draw_rectangle(
+            rect.x + rect.w * 0.15,
+            y,
+            rect.w * 0.7,
+            rect.h * 0.08,
+            color,
+        );
+    }
+}
+
+fn draw_decoration(rect: Rect, kind: DecoKind) {
+    let bottom = rect.y + rect.h;
+    match kind {
+        DecoKind::Bush => {
+            let color = Color::new(0.2, 0.6, 0.25, 1.0);
+            let radius = rect.w * 0.28;
+            draw_circle(rect.x + rect.w * 0.28, bottom - radius, radius, color);
+            draw_circle(rect.x + rect.w * 0.72, bottom - radius, radius, color);
+            draw_circle(rect.x + rect.w * 0.5, bottom - radius * 1.6, radius, color);
+        }
+        DecoKind::Rock => {
+            let center = vec2(rect.x + rect.w * 0.5, bottom - rect.h * 0.2);
+            draw_ellipse(
+                center.x,
+                center.y,
+                rect.w * 0.4,
+                rect.h * 0.22,
+                0.0,
+                Color::new(0.5, 0.5, 0.52, 1.0),
+            );
+            draw_ellipse(
+                center.x - rect.w * 0.1,
+                center.y - rect.h * 0.07,
+                rect.w * 0.15,
+                rect.h * 0.07,
+                0.0,
+                Color::new(0.68, 0.68, 0.7, 1.0),
+            );
+        }
+    }
+}
+
+fn draw_ice(rect: Rect) {
+    draw_rectangle(
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        Color::new(0.72, 0.9, 0.98, 1.0),
+    );
+    draw_line(
+        rect.x + rect.w * 0.2,
+        rect.y + rect.h * 0.35,
+        rect.x + rect.w * 0.45,
+        rect.y + rect.h * 0.15,
+        2.0,
+        WHITE,
+    );
+    draw_rectangle_lines(
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        2.0,
+        Color::new(0.45, 0.7, 0.85, 1.0),
+    );
+}
+
+fn draw_conveyor(rect: Rect, dir: f32) {
+    draw_rectangle(
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        Color::new(0.3, 0.3, 0.34, 1.0),
+    );
+
+    let arrow = Color::new(0.95, 0.8, 0.2, 1.0);
+    let mid_y = rect.y + rect.h * 0.5;
+    for offset in [0.3, 0.7] {
+        let tip_x = rect.x + rect.w * offset + dir * rect.w * 0.12;
+        let tail_x = rect.x + rect.w * offset - dir * rect.w * 0.12;
+        draw_triangle(
+            vec2(tip_x, mid_y),
+            vec2(tail_x, mid_y - rect.h * 0.18),
+            vec2(tail_x, mid_y + rect.h * 0.18),
+            arrow,
+        );
+    }
+}
+
+fn draw_slope(slope: &Slope) {
+    let rect = slope.rect;
+    let bottom_left = vec2(rect.x, rect.y + rect.h);
+    let bottom_right = vec2(rect.x + rect.w, rect.y + rect.h);
+    let peak = match slope.dir {
+        SlopeDir::Up => vec2(rect.x + rect.w, rect.y),
+        SlopeDir::Down => vec2(rect.x, rect.y),
+    };
+    draw_triangle(
+        bottom_left,
+        bottom_right,
+        peak,
+        Color::new(0.25, 0.55, 0.25, 1.0),
+    );
+}
+
+fn draw_hazard(rect: Rect) {
+    let color = Color::new(0.86, 0.16, 0.12, 1.0);
+    let spikes = 2;
+    let spike_w = rect.w / spikes as f32;
+    let base_y = rect.y + rect.h;
+    for i in 0..spikes {
+        let left = rect.x + i as f32 * spike_w;
+        draw_triangle(
+            vec2(left, base_y),
+            vec2(left + spike_w, base_y),
+            vec2(left + spike_w * 0.5, rect.y),
+            color,
+        );
+    }
+}
+
+fn draw_brick(rect: Rect) {
+    let mortar = Color::new(0.35, 0.18, 0.1, 1.0);
+    draw_rectangle(
+        rect.x,
+        rect.y,
+        rect.w,
+        rect.h,
+        Color::new(0.72, 0.36, 0.2, 1.0),
+    );
+
+    let row_h = rect.h * 0.5;
+    draw_line(
+        rect.x,
+        rect.y + row_h,
+        rect.x + rect.w,
+        rect.y + row_h,
+        2.0,
+        mortar,
+    );
+    draw_line(
+        rect.x + rect.w * 0.5,
+        rect.y,
+        rect.x + rect.w * 0.5,
+        rect.y + row_h,
+        2.0,
+        mortar,
+    );
+    draw_line(
+        rect.x + rect.w * 0.25,
+        rect.y + row_h,
+        rect.x + rect.w * 0.25,
+        rect.y + rect.h,
+        2.0,
+        mortar,
+    );
+    draw_line(
+        rect.x + rect.w * 0.75,
+        rect.y + row_h,
+        rect.x + rect.w * 0.75,
+        rect.y + rect.h,
+        2.0,
+        mortar,
+    );
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, mortar);
+}
+
+fn draw_question_block(block: &QuestionBlock) {
+    let rect = block.rect;
+    let (fill, edge) = if block.used {
+        (
+            Color::new(0.5, 0.38, 0.26, 1.0),
+            Color::new(0.3, 0.2, 0.12, 1.0),
+        )
+    } else {
+        (
+            Color::new(0.96, 0.72, 0.18, 1.0),
+            Color::new(0.6, 0.38, 0.1, 1.0),
+        )
+    };
+
+    draw_rectangle(rect.x, rect.y, rect.w, rect.h, fill);
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 2.0, edge);
+    if !block.used {
+        let font_size = rect.h * 0.8;
+        let dims = measure_text("?", None, font_size as u16, 1.0);
+        draw_text(
+            "?",
+            rect.x + (rect.w - dims.width) * 0.5,
+            rect.y + (rect.h + dims.height) * 0.5,
+            font_size,
+            edge,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ground_search_spans_the_height_of_tall_levels() {
+        let config = Config::default();
+        let shaft = 24;
+        let mut level = String::from("..E.G.\n.P....\n##.###\n");
+        level += &"#....#\n".repeat(shaft);
+        level += "######\n";
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        assert!(world.height > world.width * 4);
+        let tile = config.tile_size;
+        let shaft_floor = (shaft + 3) as f32 * tile;
+
+        // The enemy over the gap settles on the shaft's floor, as it would
+        // by falling; the goal stands on the ledge right below it.
+        assert_eq!(
+            world.ground_y_for_x(tile * 2.5, 0.0, &config),
+            Some(shaft_floor)
+        );
+        let pole = world.goal_trigger_rect(&config);
+        assert_eq!(pole.bottom(), tile * 2.0);
+        assert_eq!(
+            world.surface_y_for_x(tile * 2.5, shaft_floor - 1.0, -1.0, &config),
+            None
+        );
+        assert_eq!(
+            world.surface_y_for_x(tile * 1.5, shaft_floor - 1.0, -1.0, &config),
+            Some(tile * 3.0)
+        );
+        assert!(validate::check(&world, &config)
+            .expect("level is valid")
+            .is_empty());
+    }
+
+    #[test]
+    fn decorations_do_not_collide() {
+        let config = Config::default();
+        let level = "\
+..........
+.P.t.o..G.
+##########
+";
+        let world = World::from_ascii(level, &config).expect("test level is valid");
+        let plain = World::from_ascii(&level.replace(['t', 'o'], "."), &config)
+            .expect("test level is valid");
+
+        assert_eq!(
+            world.decorations,
+            vec![
+                (vec2(3.0, 1.0) * config.tile_size, DecoKind::Bush),
+                (vec2(5.0, 1.0) * config.tile_size, DecoKind::Rock),
+            ]
+        );
+        assert_eq!(world.colliders(), plain.colliders());
+        assert!(World::from_ascii(&level.replace('t', "q"), &config).is_err());
+    }
+
+    #[test]
+    fn run_length_rows_match_literal_rows() {
+        let config = Config::default();
+        let literal = "\
+..........
+..P....C.G
+####..####
+";
+        let runs = "\
+.10
+..P.4C.G
+#4.2#4
+";
+        let literal = World::from_ascii(literal, &config).expect("literal level is valid");
+        let runs = World::from_ascii(runs, &config).expect("run-length level is valid");
+
+        assert_eq!(runs.width, literal.width);
+        assert_eq!(runs.solids, literal.solids);
+        assert_eq!(runs.coins, literal.coins);
+        assert_eq!(runs.tiles, literal.tiles);
+    }
+
+    #[test]
+    fn grid_broadphase_matches_brute_force_collisions() {
+        use crate::game::rng::Rng;
+
+        let config = Config::default();
+        let mut rng = Rng::new(1094);
+        let (cols, rows) = (30, 20);
+        let mut level = format!("PG{}\n", ".".repeat(cols - 2));
+        for _ in 1..rows {
+            let row: String = (0..cols)
+                .map(|_| if rng.next_f32() < 0.3 { '#' } else { '.' })
+                .collect();
+            level += &row;
+            level.push('\n');
+        }
+        let world = World::from_ascii(&level, &config).expect("test level is valid");
+        let extent = vec2(cols as f32, rows as f32) * config.tile_size;
+
+        let mut checked = 0;
+        while checked < 2000 {
+            let size = vec2(rng.range(8.0, 40.0), rng.range(8.0, 40.0));
+            let pos = vec2(rng.range(-40.0, extent.x), rng.range(-40.0, extent.y));
+            if world
+                .colliders()
+                .iter()
+                .any(|solid| physics::rects_intersect(physics::rect_at(pos, size), *solid))
+            {
+                continue;
+            }
+            let vel = vec2(rng.range(-900.0, 900.0), rng.range(-900.0, 900.0));
+            let nudge = if rng.next_f32() < 0.5 { 0.0 } else { 6.0 };
+            let gravity_dir = if rng.next_f32() < 0.5 { 1.0 } else { -1.0 };
+            let dt = config.fixed_dt;
+
+            let grid = physics::move_with_gravity(pos, size, vel, &world, dt, nudge, gravity_dir);
+            let brute = physics::move_with_gravity(
+                pos,
+                size,
+                vel,
+                world.colliders(),
+                dt,
+                nudge,
+                gravity_dir,
+            );
+            assert_eq!(grid, brute, "body at {pos} size {size} vel {vel}");
+            checked += 1;
+        }
+    }
+
+    #[test]
+    fn run_length_rows_reject_absurd_widths() {
+        assert!(expand_runs("#99999").is_err());
+        assert!(expand_runs("5#").is_err());
+    }
+}
@@ -14,11 +14,34 @@ fn window_conf() -> Conf {
 
 #[macroquad::main(window_conf)]
 async fn main() {
-    let mut game = game::Game::new().await;
+    let args: Vec<String> = std::env::args().collect();
+    let mut audio = game::AudioConfig::default();
+    for arg in &args {
+        if let Some(rate) = arg.strip_prefix("--sample-rate=") {
+            match rate.parse::<u32>() {
+                Ok(rate) if rate > 0 => audio.sample_rate = rate,
+                _ => eprintln!("Ignoring invalid sample rate '{rate}'."),
+            }
+        }
+    }
+    audio.stereo = args.iter().any(|arg| arg == "--stereo");
+
+    let mut game = game::Game::new(audio).await;
+    if args.iter().any(|arg| arg == "--pixel-perfect") {
+        game.enable_pixel_perfect();
+    }
+    if args.iter().any(|arg| arg == "--touch-controls") {
+        game.enable_touch_controls();
+    }
 
     loop {
         let frame_dt = get_frame_time();
         game.update(frame_dt);
+        if game.should_quit() {
+            game.shutdown();
+            break;
+        }
+        game.load_pending_level().await;
         game.draw();
         next_frame().await;
     }